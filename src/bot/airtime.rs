@@ -0,0 +1,190 @@
+//! Channel use of each radio over time.
+//!
+//! Fed by the background sampler and by `/survey`, and kept in memory only,
+//! so peaks and averages start over whenever the bot does.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+
+use crate::domain::ubus::{ChannelSurvey, RadioInfo, WirelessStatus};
+use crate::domain::{Airtime, RouterError, RouterInfo};
+
+/// Readings closer together than this are compared but not kept, so a burst
+/// of `/survey` calls cannot pass for a peak.
+const MIN_PERIOD: Duration = Duration::from_secs(10);
+const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub at: Instant,
+    pub airtime: Airtime,
+}
+
+/// Samples still inside the window.
+pub struct AirtimeSummary {
+    pub peak: Sample,
+    pub average_busy: u8,
+    pub samples: usize,
+}
+
+/// What one radio reported.
+pub enum Reading {
+    Measured(Airtime),
+    /// Down, or no interface up to ask through.
+    Off,
+    /// The driver has no counters for the channel in use.
+    Unavailable,
+    Failed(RouterError),
+}
+
+#[derive(Default)]
+struct RadioAirtime {
+    last: Option<(Instant, ChannelSurvey)>,
+    samples: VecDeque<Sample>,
+}
+
+pub struct AirtimeLog {
+    window: Duration,
+    radios: Mutex<HashMap<String, RadioAirtime>>,
+}
+
+impl AirtimeLog {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            radios: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Channel use of `radio` since its previous reading.
+    pub fn record(&self, radio: &str, reading: &ChannelSurvey) -> Option<Airtime> {
+        let now = Instant::now();
+        let mut radios = self.radios.lock().unwrap();
+        let state = radios.entry(radio.to_string()).or_default();
+
+        let previous = state.last.as_ref();
+        let airtime = Airtime::between(previous.map(|(_, survey)| survey), reading)?;
+        if previous.is_some_and(|(at, _)| now.duration_since(*at) < MIN_PERIOD) {
+            return Some(airtime);
+        }
+
+        state.last = Some((now, reading.clone()));
+        state.samples.push_back(Sample { at: now, airtime });
+        while state
+            .samples
+            .front()
+            .is_some_and(|s| now.duration_since(s.at) > self.window)
+        {
+            state.samples.pop_front();
+        }
+        Some(airtime)
+    }
+
+    pub fn latest(&self, radio: &str) -> Option<Sample> {
+        let radios = self.radios.lock().unwrap();
+        radios.get(radio)?.samples.back().copied()
+    }
+
+    pub fn summary(&self, radio: &str) -> Option<AirtimeSummary> {
+        let radios = self.radios.lock().unwrap();
+        let samples = &radios.get(radio)?.samples;
+        let peak = *samples.iter().max_by_key(|s| (s.airtime.busy, s.at))?;
+        let total: usize = samples.iter().map(|s| usize::from(s.airtime.busy)).sum();
+        Some(AirtimeSummary {
+            peak,
+            average_busy: (total / samples.len()) as u8,
+            samples: samples.len(),
+        })
+    }
+}
+
+impl Default for AirtimeLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+/// Reads every radio's counters, by name, and records them in `log`.
+pub async fn sample<R: RouterInfo>(
+    router: &R,
+    wireless: &WirelessStatus,
+    log: &AirtimeLog,
+) -> Vec<(String, Reading)> {
+    let mut radios: Vec<_> = wireless.0.iter().collect();
+    radios.sort_by_key(|(name, _)| *name);
+
+    let readings = join_all(
+        radios
+            .iter()
+            .map(|(name, radio)| read(router, name, radio, log)),
+    )
+    .await;
+    radios
+        .into_iter()
+        .map(|(name, _)| name.clone())
+        .zip(readings)
+        .collect()
+}
+
+async fn read<R: RouterInfo>(
+    router: &R,
+    name: &str,
+    radio: &RadioInfo,
+    log: &AirtimeLog,
+) -> Reading {
+    let iface = radio.interfaces.iter().find(|i| !i.ifname.is_empty());
+    let Some(iface) = iface.filter(|_| radio.up && !radio.disabled) else {
+        return Reading::Off;
+    };
+    // hostapd knows the frequency in use even when the channel is `auto`.
+    let freq = match router.wifi_clients(&iface.ifname).await {
+        Ok(clients) => clients.freq,
+        Err(e) => return Reading::Failed(e),
+    };
+    let survey = match router.wifi_survey(&iface.ifname).await {
+        Ok(survey) => survey,
+        Err(e) => return Reading::Failed(e),
+    };
+
+    match survey
+        .find(freq)
+        .and_then(|reading| log.record(name, reading))
+    {
+        Some(airtime) => Reading::Measured(airtime),
+        None => Reading::Unavailable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(active: u64, busy: u64) -> ChannelSurvey {
+        ChannelSurvey {
+            mhz: 5180,
+            active_time: active,
+            busy_time: busy,
+            ..ChannelSurvey::default()
+        }
+    }
+
+    #[test]
+    fn keeps_samples_apart_and_summarises_them() {
+        let log = AirtimeLog::default();
+
+        assert_eq!(log.record("radio1", &reading(1_000, 200)).unwrap().busy, 20);
+        // Too soon to keep, but still compared with the first reading.
+        assert_eq!(
+            log.record("radio1", &reading(2_000, 1_100)).unwrap().busy,
+            90
+        );
+
+        let summary = log.summary("radio1").unwrap();
+        assert_eq!((summary.samples, summary.peak.airtime.busy), (1, 20));
+        assert_eq!(log.latest("radio1").unwrap().airtime.busy, 20);
+        assert!(log.summary("radio0").is_none());
+    }
+}
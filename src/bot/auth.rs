@@ -8,22 +8,36 @@ use crate::domain::messenger::{AuthFilter, UserId};
 
 pub struct UserWhitelist {
     allowed: HashSet<u64>,
+    admins: HashSet<u64>,
 }
 
 impl UserWhitelist {
     pub fn new(users: HashSet<u64>) -> Self {
-        Self { allowed: users }
+        Self {
+            allowed: users,
+            admins: HashSet::new(),
+        }
     }
 
     pub fn from_iter(iter: impl IntoIterator<Item = u64>) -> Self {
         Self {
             allowed: iter.into_iter().collect(),
+            admins: HashSet::new(),
         }
     }
+
+    pub fn with_admins(mut self, admins: impl IntoIterator<Item = u64>) -> Self {
+        self.admins = admins.into_iter().collect();
+        self
+    }
 }
 
 impl AuthFilter for UserWhitelist {
     fn is_allowed(&self, user_id: UserId) -> bool {
         self.allowed.contains(&user_id.0)
     }
+
+    fn is_admin(&self, user_id: UserId) -> bool {
+        self.is_allowed(user_id) && self.admins.contains(&user_id.0)
+    }
 }
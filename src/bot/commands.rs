@@ -2,7 +2,11 @@
 //!
 //! Defines available commands using Teloxide's BotCommands derive macro.
 
-use teloxide::utils::command::BotCommands;
+use teloxide::utils::command::{BotCommands, ParseError};
+
+use crate::domain::messenger::Role;
+
+use super::handlers::{ClientArgs, ShareArgs, parse_client_query, parse_share_request};
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -10,6 +14,44 @@ pub enum Command {
     Ping,
     Status,
     Wifi,
-    Clients,
+    #[command(parse_with = client_args)]
+    Clients(ClientArgs),
+    Client(String),
+    Scan(String),
+    Survey,
+    #[command(parse_with = share_args)]
+    Share(ShareArgs),
+    #[command(rename = "wifi_set")]
+    WifiSet(String),
     Help,
+    Logs(String),
+    Syslog(String),
+    LogLevel(String),
+    Update(String),
+}
+
+impl Command {
+    /// Who may run the command; `share` is the role set for `/share`.
+    pub fn role(&self, share: Role) -> Role {
+        match self {
+            Self::WifiSet(_)
+            | Self::Logs(_)
+            | Self::Syslog(_)
+            | Self::LogLevel(_)
+            | Self::Update(_) => Role::Admin,
+            // Credentials leaving the bot's own chats are an admin's call.
+            Self::Share(Ok(request)) if request.chat.is_some() => Role::Admin,
+            Self::Share(_) => share,
+            _ => Role::User,
+        }
+    }
+}
+
+/// Never fails, so bad arguments get an explanation instead of silence.
+fn client_args(args: String) -> Result<(ClientArgs,), ParseError> {
+    Ok((parse_client_query(&args),))
+}
+
+fn share_args(args: String) -> Result<(ShareArgs,), ParseError> {
+    Ok((parse_share_request(&args),))
 }
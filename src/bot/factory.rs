@@ -2,22 +2,38 @@
 
 use std::sync::Arc;
 
-use crate::domain::RouterInfo;
+use crate::domain::{ApplyTiming, Heartbeat, LogLevelControl, LogSource, RouterInfo, Updater};
 use crate::infrastructure::Config;
 
 use super::auth::UserWhitelist;
 use super::telegram::TelegramBot;
 
-const KEY_BOT_TOKEN: &str = "BOT_TOKEN";
-const KEY_ALLOWED_USERS: &str = "BOT_ALLOWED_USERS";
-
 pub fn create_telegram_bot<R: RouterInfo + 'static>(
     config: &Config,
     router: Arc<R>,
+    logs: Arc<dyn LogSource>,
+    log_level: Arc<dyn LogLevelControl>,
+    updater: Arc<dyn Updater>,
+    heartbeat: Heartbeat,
 ) -> anyhow::Result<TelegramBot<R, UserWhitelist>> {
-    let token = config.required(KEY_BOT_TOKEN)?;
-    let allowed_users: Vec<u64> = config.required_list(KEY_ALLOWED_USERS)?;
-    let auth = UserWhitelist::from_iter(allowed_users);
+    let telegram = &config.telegram;
+    let auth = UserWhitelist::from_iter(telegram.allowed_users.iter().copied())
+        .with_admins(telegram.admin_users.iter().copied());
 
-    Ok(TelegramBot::new(token, router, auth))
+    let bot = TelegramBot::new(
+        telegram.token.clone(),
+        router,
+        auth,
+        logs,
+        log_level,
+        updater,
+    );
+    Ok(bot
+        .with_airtime_sampling(config.survey.interval, config.survey.window)
+        .with_apply_timing(ApplyTiming {
+            timeout: config.router.apply_timeout,
+            ..ApplyTiming::default()
+        })
+        .with_share_role(telegram.share_role)
+        .with_heartbeat(heartbeat))
 }
@@ -0,0 +1,404 @@
+//! Single client formatter.
+
+use serde_json::Value;
+
+use crate::domain::router::InterfaceClients;
+use crate::domain::ubus::{
+    DhcpLease, Station, StationRate, WifiClient, WirelessStatus, band_from_freq,
+};
+use crate::domain::{RouterError, RouterInfo};
+
+use super::super::history::{BandHistory, Sighting};
+use super::super::messages::{ERROR_PREFIX, NOT_CONNECTED};
+use super::utils::{
+    format_band, format_bytes, format_error, format_speed, format_uptime, format_warnings,
+    wifi_mode,
+};
+
+/// Everything known about one connected station.
+struct ClientDetails<'a> {
+    mac: &'a str,
+    iface: &'a InterfaceClients,
+    client: &'a WifiClient,
+    /// Missing if iwinfo failed or does not list the station yet.
+    station: Option<&'a Station>,
+    leases: Vec<&'a DhcpLease>,
+    /// Whether the SSID offers 802.11r; hostapd does not say if the station
+    /// uses it.
+    fast_transition: bool,
+    sightings: &'a [Sighting],
+}
+
+/// Looks `target` up by MAC, or by DHCP hostname.
+pub async fn format_client_details<R: RouterInfo>(
+    router: &R,
+    target: &str,
+    history: &BandHistory,
+) -> String {
+    let wireless = match router.wireless_status().await {
+        Ok(w) => w,
+        Err(e) => return format_error(&e),
+    };
+    let mut warnings = Vec::new();
+    let leases = keep(router.dhcp_leases().await, &mut warnings).unwrap_or_default();
+
+    let mac = match normalize_mac(target) {
+        Some(mac) => mac,
+        None => match leases.find_host(target) {
+            Some(lease) => lease.macaddr.to_lowercase(),
+            None => {
+                return format!("{ERROR_PREFIX}: no client matches '{target}'")
+                    + &format_warnings(&warnings);
+            }
+        },
+    };
+
+    let clients = router.all_clients(&wireless).await;
+    history.record(&clients.value);
+    let found = clients
+        .value
+        .iter()
+        .find_map(|iface| Some((iface, iface.clients.clients.get(&mac)?)));
+    let Some((iface, client)) = found else {
+        warnings.extend(clients.warnings);
+        let mut lines = vec![format!("{mac}: {NOT_CONNECTED}")];
+        lines.extend(format_leases(&leases.for_mac(&mac).collect::<Vec<_>>()));
+        return lines.join("\n") + &format_warnings(&warnings);
+    };
+
+    let stations = keep(router.wifi_stations(&iface.ifname).await, &mut warnings);
+    let sightings = history.sightings(&mac);
+    let details = ClientDetails {
+        mac: &mac,
+        iface,
+        client,
+        station: stations.as_ref().and_then(|list| list.find(&mac)),
+        leases: leases.for_mac(&mac).collect(),
+        fast_transition: offers_fast_transition(&wireless, &iface.ifname),
+        sightings: &sightings,
+    };
+    format_client(&details) + &format_warnings(&warnings)
+}
+
+fn keep<T>(result: Result<T, RouterError>, warnings: &mut Vec<RouterError>) -> Option<T> {
+    result.map_err(|e| warnings.push(e)).ok()
+}
+
+/// Accepts `AA-BB-CC-DD-EE-FF` as well as `aa:bb:cc:dd:ee:ff`.
+fn normalize_mac(text: &str) -> Option<String> {
+    let mac = text.trim().to_lowercase().replace('-', ":");
+    let octets: Vec<&str> = mac.split(':').collect();
+    let valid = octets.len() == 6
+        && octets
+            .iter()
+            .all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then_some(mac)
+}
+
+/// UCI booleans arrive as `true`, `1` or `"1"` depending on the release.
+fn offers_fast_transition(wireless: &WirelessStatus, ifname: &str) -> bool {
+    wireless
+        .0
+        .values()
+        .flat_map(|radio| &radio.interfaces)
+        .find(|iface| iface.ifname == ifname)
+        .and_then(|iface| iface.config.extra.get("ieee80211r"))
+        .is_some_and(|value| match value {
+            Value::Bool(on) => *on,
+            Value::Number(n) => n.as_u64() == Some(1),
+            Value::String(s) => s == "1",
+            _ => false,
+        })
+}
+
+fn format_client(d: &ClientDetails) -> String {
+    let mut lines = vec![format!("Client {}", d.mac)];
+    lines.extend(format_leases(&d.leases));
+    lines.push(format!(
+        "Network: {} ({}) on {}",
+        d.iface.ssid,
+        format_band(&d.iface.band),
+        d.iface.ifname
+    ));
+    lines.push(String::new());
+
+    lines.push(format_signal(d.client, d.station));
+    lines.push(format_rate(
+        "TX",
+        d.client.rate.tx,
+        d.station.map(|s| &s.tx),
+    ));
+    lines.push(format_rate(
+        "RX",
+        d.client.rate.rx,
+        d.station.map(|s| &s.rx),
+    ));
+    if let Some(station) = d.station {
+        lines.push(format!(
+            "Connected: {} | Inactive: {} ms",
+            format_uptime(station.connected_time),
+            station.inactive
+        ));
+    }
+    // The access point sends what the device downloads.
+    lines.push(format!(
+        "Down: {} ({} packets) | Up: {} ({} packets)",
+        format_bytes(d.client.bytes.tx),
+        d.client.packets.tx,
+        format_bytes(d.client.bytes.rx),
+        d.client.packets.rx
+    ));
+    if let Some(tx) = d.station.map(|s| &s.tx)
+        && (tx.retries.is_some() || tx.failed.is_some())
+    {
+        lines.push(format!(
+            "Retries: {} | Failed: {}",
+            tx.retries.unwrap_or(0),
+            tx.failed.unwrap_or(0)
+        ));
+    }
+
+    lines.push(format_capabilities(d.client, &d.iface.band));
+    if !d.client.links.is_empty() {
+        lines.push(format_links(d.client));
+    }
+    lines.push(format_roaming(d.client, d.fast_transition));
+    if !d.sightings.is_empty() {
+        lines.push(format_sightings(d.sightings));
+    }
+    lines.join("\n")
+}
+
+fn format_leases(leases: &[&DhcpLease]) -> Vec<String> {
+    let mut names: Vec<&str> = leases
+        .iter()
+        .map(|l| l.hostname.as_str())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.dedup();
+    let addresses: Vec<&str> = leases
+        .iter()
+        .flat_map(|l| std::iter::once(&l.ipaddr).chain(&l.ip6addrs))
+        .filter_map(|addr| addr.split('/').next())
+        .filter(|addr| !addr.is_empty())
+        .collect();
+
+    let mut lines = Vec::new();
+    if !names.is_empty() {
+        lines.push(format!("Name: {}", names.join(", ")));
+    }
+    if !addresses.is_empty() {
+        lines.push(format!("IP: {}", addresses.join(", ")));
+    }
+    lines
+}
+
+fn format_signal(client: &WifiClient, station: Option<&Station>) -> String {
+    let signal = station.and_then(|s| s.signal).or(client.signal);
+    let Some(signal) = signal else {
+        return "Signal: ? dBm".to_string();
+    };
+
+    let mut parts = vec![format!("Signal: {signal} dBm")];
+    if let Some(avg) = station.and_then(|s| s.signal_avg) {
+        parts[0].push_str(&format!(" (avg {avg})"));
+    }
+    if let Some(noise) = station.and_then(|s| s.noise).filter(|&n| n != 0) {
+        parts.push(format!("Noise: {noise} dBm"));
+        parts.push(format!("SNR: {} dB", signal - noise));
+    }
+    parts.join(" | ")
+}
+
+/// Prefers iwinfo's rate, which comes with MCS, streams and width.
+fn format_rate(label: &str, kbps: u64, rate: Option<&StationRate>) -> String {
+    let Some(rate) = rate.filter(|r| r.rate > 0) else {
+        return format!("{label}: {}", format_speed(kbps));
+    };
+
+    let mut parts = vec![format!("{label}: {}", format_speed(rate.rate))];
+    if let Some(mcs) = rate.mcs {
+        parts.push(format!("MCS {mcs}"));
+    }
+    if let Some(nss) = rate.nss {
+        parts.push(format!("NSS {nss}"));
+    }
+    if rate.mhz > 0 {
+        parts.push(format!("{} MHz", rate.mhz));
+    }
+    let standard = [
+        (rate.eht, "EHT"),
+        (rate.he, "HE"),
+        (rate.vht, "VHT"),
+        (rate.ht, "HT"),
+    ]
+    .into_iter()
+    .find_map(|(on, name)| on.then_some(name));
+    if let Some(standard) = standard {
+        parts.push(standard.to_string());
+    }
+    if rate.short_gi {
+        parts.push("short GI".to_string());
+    }
+    parts.join(" | ")
+}
+
+fn format_capabilities(client: &WifiClient, band: &str) -> String {
+    let flags: Vec<&str> = [
+        (client.ht, "HT"),
+        (client.vht, "VHT"),
+        (client.he, "HE"),
+        (client.eht, "EHT"),
+        (client.mfp, "MFP"),
+    ]
+    .into_iter()
+    .filter_map(|(on, name)| on.then_some(name))
+    .collect();
+
+    if flags.is_empty() {
+        format!("Capabilities: {}", wifi_mode(client, band))
+    } else {
+        format!(
+            "Capabilities: {} ({})",
+            wifi_mode(client, band),
+            flags.join(", ")
+        )
+    }
+}
+
+/// The station's MLO links, each with its band and signal.
+fn format_links(client: &WifiClient) -> String {
+    let links: Vec<String> = client
+        .links
+        .iter()
+        .map(|link| {
+            let band = format_band(band_from_freq(link.freq));
+            match link.signal {
+                Some(signal) => format!("{band} {signal} dBm"),
+                None => band.to_string(),
+            }
+        })
+        .collect();
+    let mld = client.mld_addr.as_deref().unwrap_or("?");
+    format!("MLO: {mld} via {}", links.join(", "))
+}
+
+fn format_roaming(client: &WifiClient, fast_transition: bool) -> String {
+    let features: Vec<&str> = [
+        (client.supports_rrm(), "802.11k"),
+        (client.supports_bss_transition(), "802.11v"),
+        (fast_transition, "802.11r"),
+    ]
+    .into_iter()
+    .filter_map(|(on, name)| on.then_some(name))
+    .collect();
+
+    if features.is_empty() {
+        "Roaming: none".to_string()
+    } else {
+        format!("Roaming: {}", features.join(", "))
+    }
+}
+
+/// Oldest first, with how long ago each band was joined.
+fn format_sightings(sightings: &[Sighting]) -> String {
+    let bands: Vec<String> = sightings
+        .iter()
+        .map(|s| {
+            let ago = format_uptime(s.since.elapsed().as_secs());
+            format!("{} {} ({ago} ago)", s.ssid, format_band(&s.band))
+        })
+        .collect();
+    format!("Bands: {}", bands.join(" → "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::domain::ubus::{AssocList, DhcpLeases, HostapdClients};
+
+    use super::*;
+
+    fn load<T: serde::de::DeserializeOwned>(name: &str) -> T {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/router")
+            .join(name);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn recognises_mac_addresses() {
+        assert_eq!(
+            normalize_mac(" 3C-22-FB-8A-11-D4 ").as_deref(),
+            Some("3c:22:fb:8a:11:d4")
+        );
+        assert_eq!(normalize_mac("pixel-7"), None);
+        assert_eq!(normalize_mac("3c:22:fb:8a:11"), None);
+    }
+
+    #[test]
+    fn combines_hostapd_iwinfo_and_dhcp() {
+        let mac = "3c:22:fb:8a:11:d4";
+        let iface = InterfaceClients {
+            ifname: "phy1-ap0".to_string(),
+            ssid: "OpenWrt".to_string(),
+            band: "5g".to_string(),
+            clients: load::<HostapdClients>("hostapd.phy1-ap0.get_clients.json"),
+        };
+        let stations: AssocList = load("iwinfo.assoclist.phy1-ap0.json");
+        let leases: DhcpLeases = load("luci-rpc.getDHCPLeases.json");
+        let details = ClientDetails {
+            mac,
+            iface: &iface,
+            client: &iface.clients.clients[mac],
+            station: stations.find(mac),
+            leases: leases.for_mac(mac).collect(),
+            fast_transition: true,
+            sightings: &[],
+        };
+
+        let text = format_client(&details);
+
+        assert!(
+            text.starts_with(
+                "Client 3c:22:fb:8a:11:d4\nName: pixel-7\nIP: 192.168.1.120, fd4e:8b2c:91a0::120\n"
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains("Network: OpenWrt (5 GHz) on phy1-ap0\n\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("Signal: -48 dBm (avg -49) | Noise: -92 dBm | SNR: 44 dB"),
+            "{text}"
+        );
+        assert!(
+            text.contains("TX: 1201 Mbps | MCS 11 | NSS 2 | 80 MHz | HE"),
+            "{text}"
+        );
+        assert!(text.contains("Retries: 18342 | Failed: 27"), "{text}");
+        assert!(
+            text.contains("Capabilities: WiFi 6 (HT, VHT, HE, MFP)"),
+            "{text}"
+        );
+        assert!(
+            text.ends_with("Roaming: 802.11k, 802.11v, 802.11r"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn lists_mlo_links() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/ubus/snapshot-mt7996/hostapd.phy2-ap0.get_clients.json");
+        let clients: HostapdClients =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+        assert_eq!(
+            format_links(&clients.clients["7e:91:0c:5a:e2:33"]),
+            "MLO: 7e:91:0c:5a:e2:30 via 5 GHz -47 dBm, 6 GHz -51 dBm"
+        );
+    }
+}
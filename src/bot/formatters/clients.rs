@@ -1,79 +1,145 @@
 //! WiFi clients formatters.
 
-use crate::domain::RouterInfo;
-use crate::domain::ubus::{WifiClient, WifiInterface, WirelessStatus};
+use crate::domain::router::InterfaceClients;
+use crate::domain::ubus::WifiClient;
+use crate::domain::{ClientQuery, Partial, RouterInfo};
 
-use super::super::messages::{CLIENTS_HEADER, NO_DEVICES};
-use super::utils::{format_error, format_speed, wifi_mode};
+use super::super::history::BandHistory;
+use super::super::messages::{CLIENTS_HEADER, NO_DEVICES, NO_MATCHING_DEVICES};
+use super::utils::{format_band, format_error, format_speed, format_warnings, wifi_mode};
 
-pub async fn format_wifi_clients<R: RouterInfo>(router: &R) -> String {
-    let wireless = match router.wireless_status().await {
+/// Only interfaces `query` can match are asked for their clients.
+pub async fn format_wifi_clients<R: RouterInfo>(
+    router: &R,
+    query: &ClientQuery,
+    history: &BandHistory,
+) -> String {
+    let mut wireless = match router.wireless_status().await {
         Ok(w) => w,
         Err(e) => return format_error(&e),
     };
-
-    let (lines, total) = collect_clients_info(router, &wireless).await;
-    format_clients_output(lines, total)
-}
-
-pub async fn collect_clients_info<R: RouterInfo>(
-    router: &R,
-    wireless: &WirelessStatus,
-) -> (Vec<String>, usize) {
-    let mut lines = Vec::new();
-    let mut total_clients = 0;
-
-    for radio in wireless.0.values() {
-        for iface in &radio.interfaces {
-            if let Some((iface_lines, count)) =
-                collect_interface_clients(router, iface, &radio.config.band).await
-            {
-                lines.extend(iface_lines);
-                total_clients += count;
-            }
-        }
+    for radio in wireless.0.values_mut() {
+        let band = &radio.config.band;
+        radio
+            .interfaces
+            .retain(|iface| query.matches_interface(band, &iface.config.ssid));
     }
 
-    (lines, total_clients)
+    let clients = router.all_clients(&wireless).await;
+    history.record(&clients.value);
+    format_clients_output(&clients, query)
 }
 
-async fn collect_interface_clients<R: RouterInfo>(
-    router: &R,
-    iface: &WifiInterface,
-    band: &str,
-) -> Option<(Vec<String>, usize)> {
-    let clients_info = router.wifi_clients(&iface.ifname).await.ok()?;
-    let count = clients_info.clients.len();
-
-    if count == 0 {
-        return None;
-    }
-
-    let ssid = &iface.config.ssid;
-    let mut lines = vec![format!("\n{ssid} ({band}) - {count} devices")];
-
-    for (mac, client) in &clients_info.clients {
-        lines.push(format_client_info(mac, client));
+fn format_interface_clients(
+    iface: &InterfaceClients,
+    clients: &[(&String, &WifiClient)],
+) -> Vec<String> {
+    let count = clients.len();
+    let mut lines = vec![format!(
+        "\n{} ({}) - {count} devices",
+        iface.ssid,
+        format_band(&iface.band)
+    )];
+
+    for (mac, client) in clients {
+        lines.push(format_client_info(mac, client, &iface.band));
     }
-
-    Some((lines, count))
+    lines
 }
 
-pub fn format_client_info(mac: &str, client: &WifiClient) -> String {
+pub fn format_client_info(mac: &str, client: &WifiClient, band: &str) -> String {
     let speed = format_speed(client.rate.tx);
-    let mode = wifi_mode(client);
-    format!("\n  {mac}\n    {speed} | {mode} | {}dBm", client.signal)
+    let mode = wifi_mode(client, band);
+    let signal = client
+        .signal
+        .map_or_else(|| "? dBm".to_string(), |s| format!("{s}dBm"));
+    format!("\n  {mac}\n    {speed} | {mode} | {signal}")
 }
 
-pub fn format_clients_output(mut lines: Vec<String>, total: usize) -> String {
+pub fn format_clients_output(
+    clients: &Partial<Vec<InterfaceClients>>,
+    query: &ClientQuery,
+) -> String {
     let mut result = vec![CLIENTS_HEADER.to_string()];
-
-    if total == 0 {
+    let selected: Vec<_> = clients
+        .value
+        .iter()
+        .map(|iface| (iface, iface.select(query)))
+        .filter(|(_, clients)| !clients.is_empty())
+        .collect();
+    let total: usize = selected.iter().map(|(_, clients)| clients.len()).sum();
+
+    if total == 0 && query.is_filtered() {
+        result.push(format!("\n\n{NO_MATCHING_DEVICES}"));
+    } else if total == 0 {
         result.push(format!("\n\n{NO_DEVICES}"));
     } else {
         result.push(format!("\nTotal: {total}"));
-        result.append(&mut lines);
+        for (iface, clients) in &selected {
+            result.extend(format_interface_clients(iface, clients));
+        }
     }
+    result.push(format_warnings(&clients.warnings));
 
     result.join("")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::domain::RouterError;
+    use crate::domain::ubus::HostapdClients;
+
+    use super::*;
+
+    fn clients(release: &str, iface: &str) -> HostapdClients {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/ubus")
+            .join(release)
+            .join(format!("hostapd.{iface}.get_clients.json"));
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn formats_clients_across_releases() {
+        let mt76 = clients("24.10.0-mt76", "phy1-ap0");
+        let (mac, client) = mt76.clients.iter().next().unwrap();
+        assert_eq!(
+            format_client_info(mac, client, "5g"),
+            "\n  3c:22:fb:8a:11:d4\n    2402 Mbps | WiFi 6 | -44dBm"
+        );
+
+        let brcmfmac = clients("22.03.6-brcmfmac", "wlan0");
+        let (mac, client) = brcmfmac.clients.iter().next().unwrap();
+        assert!(format_client_info(mac, client, "5g").ends_with("0 Mbps | WiFi 5 | ? dBm"));
+
+        let snapshot = clients("snapshot-mt7996", "phy2-ap0");
+        let info = |mac: &str| format_client_info(mac, &snapshot.clients[mac], "6g");
+        assert!(info("7e:91:0c:5a:e2:33").ends_with("4804 Mbps | WiFi 7 MLO | -51dBm"));
+        assert!(info("f4:6a:dd:19:b0:8e").ends_with("1729 Mbps | WiFi 6E | -63dBm"));
+    }
+
+    #[test]
+    fn lists_failed_interfaces_as_warnings() {
+        let clients = Partial {
+            value: vec![InterfaceClients {
+                ifname: "phy1-ap0".to_string(),
+                ssid: "home".to_string(),
+                band: "5g".to_string(),
+                clients: clients("24.10.0-mt76", "phy1-ap0"),
+            }],
+            warnings: vec![RouterError::from_status(
+                "hostapd.phy0-ap0",
+                "get_clients",
+                4,
+                "Command failed: Not found",
+            )],
+        };
+
+        let text = format_clients_output(&clients, &ClientQuery::default());
+
+        assert!(text.starts_with("Connected devices\nTotal: 1\nhome (5 GHz) - 1 devices"));
+        assert!(text.ends_with("\n\nWarning: hostapd.phy0-ap0: not running"));
+    }
+}
@@ -3,11 +3,21 @@
 //! Pure functions that format router data into human-readable strings.
 //! These formatters are platform-agnostic and can be used with any messenger.
 
+mod client;
 mod clients;
+mod scan;
+mod settings;
+mod share;
 mod status;
+mod survey;
 mod utils;
 mod wifi;
 
+pub use client::format_client_details;
 pub use clients::format_wifi_clients;
+pub use scan::{format_channel_change, format_scan};
+pub use settings::format_wifi_change;
+pub use share::format_share;
 pub use status::format_status;
+pub use survey::format_survey;
 pub use wifi::format_wifi_status;
@@ -0,0 +1,196 @@
+//! Neighbour scan and channel change formatters.
+
+use std::collections::BTreeMap;
+
+use crate::domain::channel::ChannelScore;
+use crate::domain::ubus::{RadioInfo, ScanList, ScanNetwork};
+use crate::domain::{ChannelChange, ChannelPlan, RouterError, RouterInfo};
+
+use super::super::messages::{ERROR_PREFIX, HIDDEN_SSID, NO_NETWORKS_HEARD, SCAN_HEADER};
+use super::utils::{format_band, format_error};
+
+/// Networks named per channel; the rest are counted.
+const NAMES_PER_CHANNEL: usize = 3;
+
+/// Scans every radio that is up, or the one named or on the band `target`,
+/// and offers a move for each radio with a quieter channel.
+pub async fn format_scan<R: RouterInfo>(
+    router: &R,
+    target: Option<&str>,
+) -> (String, Vec<ChannelChange>) {
+    let wireless = match router.wireless_status().await {
+        Ok(w) => w,
+        Err(e) => return (format_error(&e), Vec::new()),
+    };
+    let mut radios: Vec<_> = wireless
+        .0
+        .iter()
+        .filter(|(name, radio)| target.is_none_or(|t| *name == t || radio.config.band == t))
+        .collect();
+    radios.sort_by_key(|(name, _)| *name);
+    if radios.is_empty() {
+        let target = target.unwrap_or_default();
+        return (format!("{ERROR_PREFIX}: no radio '{target}'"), Vec::new());
+    }
+
+    let scans =
+        futures::future::join_all(radios.iter().map(|(_, radio)| scan(router, radio))).await;
+
+    let mut lines = vec![SCAN_HEADER.to_string()];
+    let mut changes = Vec::new();
+    for ((name, radio), scan) in radios.into_iter().zip(scans) {
+        lines.push(String::new());
+        match scan {
+            Some(Ok(list)) => {
+                let plan = plan(radio, &list.results);
+                lines.extend(format_radio_scan(name, radio, &list.results, &plan));
+                if let Some(best) = plan.recommendation() {
+                    changes.push(ChannelChange {
+                        radio: name.clone(),
+                        channel: best.channel,
+                    });
+                }
+            }
+            Some(Err(e)) => lines.push(format!("{name}: {}", format_error(&e))),
+            None => lines.push(format!("{name}: off")),
+        }
+    }
+    (lines.join("\n"), changes)
+}
+
+/// Moves a radio after checking the channel suits its band and width.
+pub async fn format_channel_change<R: RouterInfo>(router: &R, change: &ChannelChange) -> String {
+    let wireless = match router.wireless_status().await {
+        Ok(w) => w,
+        Err(e) => return format_error(&e),
+    };
+    let Some(radio) = wireless.0.get(&change.radio) else {
+        return format!("{ERROR_PREFIX}: no radio '{}'", change.radio);
+    };
+    let plan = plan(radio, &[]);
+    if !plan.allows(change.channel) {
+        return format!(
+            "{ERROR_PREFIX}: channel {} does not suit {}",
+            change.channel, change.radio
+        );
+    }
+
+    match router.set_channel(&change.radio, change.channel).await {
+        Ok(()) => {
+            let dfs = plan
+                .ranked
+                .iter()
+                .any(|s| s.channel == change.channel && s.dfs);
+            let mut text = format!(
+                "{} moves to channel {}; its clients reconnect in a few seconds",
+                change.radio, change.channel
+            );
+            if dfs {
+                text.push_str(", after a radar check of a minute or more");
+            }
+            text
+        }
+        Err(e) => format_error(&e),
+    }
+}
+
+/// `None` for radios with no interface up to scan from.
+async fn scan<R: RouterInfo>(
+    router: &R,
+    radio: &RadioInfo,
+) -> Option<Result<ScanList, RouterError>> {
+    let iface = radio
+        .interfaces
+        .iter()
+        .find(|i| !i.ifname.is_empty())
+        .filter(|_| radio.up && !radio.disabled)?;
+    Some(router.wifi_scan(&iface.ifname).await)
+}
+
+fn plan(radio: &RadioInfo, neighbours: &[ScanNetwork]) -> ChannelPlan {
+    let config = &radio.config;
+    ChannelPlan::new(
+        &config.band,
+        config.channel_width().unwrap_or(20),
+        config.channel.parse().ok(),
+        neighbours,
+    )
+}
+
+fn format_radio_scan(
+    name: &str,
+    radio: &RadioInfo,
+    neighbours: &[ScanNetwork],
+    plan: &ChannelPlan,
+) -> Vec<String> {
+    let config = &radio.config;
+    let mut channel = config.channel.clone();
+    if let Some(width) = config.channel_width() {
+        channel.push_str(&format!(" | {width} MHz"));
+    }
+    let mut lines = vec![format!(
+        "{name} ({}), channel {channel}: {} networks",
+        format_band(&config.band),
+        neighbours.len()
+    )];
+
+    let mut by_channel: BTreeMap<u32, Vec<&ScanNetwork>> = BTreeMap::new();
+    for network in neighbours {
+        by_channel.entry(network.channel).or_default().push(network);
+    }
+    if by_channel.is_empty() {
+        lines.push(format!("  {NO_NETWORKS_HEARD}"));
+    }
+    for (channel, mut networks) in by_channel {
+        networks.sort_by_key(|n| std::cmp::Reverse(n.signal));
+        let mut names: Vec<String> = networks
+            .iter()
+            .take(NAMES_PER_CHANNEL)
+            .map(|n| format_network(n))
+            .collect();
+        if networks.len() > NAMES_PER_CHANNEL {
+            names.push(format!("+{} more", networks.len() - NAMES_PER_CHANNEL));
+        }
+        lines.push(format!("  {channel}: {}", names.join(", ")));
+    }
+
+    lines.push(format_advice(plan));
+    lines
+}
+
+fn format_network(network: &ScanNetwork) -> String {
+    let ssid = match network.ssid.as_str() {
+        "" => HIDDEN_SSID,
+        ssid => ssid,
+    };
+    match network.signal {
+        Some(signal) => format!("{signal} dBm {ssid}"),
+        None => ssid.to_string(),
+    }
+}
+
+fn format_advice(plan: &ChannelPlan) -> String {
+    match (plan.recommendation(), &plan.current) {
+        (Some(best), Some(current)) => {
+            format!(
+                "  Best: {} instead of {}",
+                describe(best),
+                describe(current)
+            )
+        }
+        (Some(best), None) => format!(
+            "  Best: {}; the channel is chosen automatically now",
+            describe(best)
+        ),
+        (None, Some(current)) => format!("  Channel {} is already the quietest", current.channel),
+        (None, None) => "  No channel to recommend for this band".to_string(),
+    }
+}
+
+fn describe(score: &ChannelScore) -> String {
+    let dfs = if score.dfs { "DFS, " } else { "" };
+    format!(
+        "{} ({dfs}{} networks, interference {})",
+        score.channel, score.networks, score.score
+    )
+}
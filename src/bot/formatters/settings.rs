@@ -0,0 +1,31 @@
+//! Access point change formatter.
+
+use crate::domain::wifi_settings::change_wifi;
+use crate::domain::{ApplyTiming, RouterInfo, WifiSetting};
+
+use super::super::messages::ERROR_PREFIX;
+
+/// Applies `setting` to the access points named `ssid` and says what changed.
+pub async fn format_wifi_change<R: RouterInfo>(
+    router: &R,
+    ssid: &str,
+    setting: &WifiSetting,
+    timing: ApplyTiming,
+) -> String {
+    let ifnames = match change_wifi(router, ssid, setting, timing).await {
+        Ok(ifnames) => ifnames.join(", "),
+        Err(e) => return format!("{ERROR_PREFIX}: {e}"),
+    };
+    match setting {
+        WifiSetting::Ssid(new) => {
+            format!("'{ssid}' is now '{new}' on {ifnames}; clients have to join it again")
+        }
+        WifiSetting::Password(_) => format!(
+            "Passphrase of '{ssid}' changed on {ifnames}; clients have to reconnect with the new one"
+        ),
+        WifiSetting::Encryption(encryption) => format!(
+            "'{ssid}' now uses {} on {ifnames}; clients reconnect in a few seconds",
+            encryption.as_uci()
+        ),
+    }
+}
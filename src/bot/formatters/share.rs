@@ -0,0 +1,39 @@
+//! WiFi sharing formatter.
+
+use crate::domain::RouterInfo;
+use crate::domain::ubus::WifiInterface;
+
+use super::super::messages::ERROR_PREFIX;
+use super::super::qr::wifi_payload;
+use super::utils::format_error;
+
+/// The caption and `WIFI:` text for the access point named `ssid`, matched
+/// exactly or else ignoring case; errors come ready to send.
+pub async fn format_share<R: RouterInfo>(
+    router: &R,
+    ssid: &str,
+) -> Result<(String, String), String> {
+    let wireless = router
+        .wireless_status()
+        .await
+        .map_err(|e| format_error(&e))?;
+    let mut access_points: Vec<&WifiInterface> = wireless
+        .0
+        .values()
+        .flat_map(|radio| &radio.interfaces)
+        .filter(|i| matches!(i.config.mode.as_str(), "" | "ap"))
+        .collect();
+    access_points.sort_by(|a, b| a.section.cmp(&b.section));
+
+    let iface = access_points
+        .iter()
+        .find(|i| i.config.ssid == ssid)
+        .or_else(|| {
+            access_points
+                .iter()
+                .find(|i| i.config.ssid.eq_ignore_ascii_case(ssid))
+        })
+        .ok_or_else(|| format!("{ERROR_PREFIX}: no access point is named '{ssid}'"))?;
+    let payload = wifi_payload(&iface.config).map_err(|e| format!("{ERROR_PREFIX}: {e}"))?;
+    Ok((format!("Scan to join {}", iface.config.ssid), payload))
+}
@@ -1,11 +1,10 @@
 //! Router status formatters.
 
-use crate::domain::ubus::MemoryInfo;
-use crate::domain::{RouterInfo, RouterStatus};
+use crate::domain::ubus::{BoardInfo, MemoryInfo, SystemInfo};
+use crate::domain::{Partial, RouterInfo, RouterStatus};
 
-use super::utils::{format_error, format_uptime};
+use super::utils::{BYTES_IN_MB, format_error, format_uptime, format_warnings};
 
-const BYTES_IN_MB: u64 = 1024 * 1024;
 const LOAD_DIVISOR: f64 = 100.0;
 
 pub async fn format_status<R: RouterInfo>(router: &R) -> String {
@@ -15,26 +14,38 @@ pub async fn format_status<R: RouterInfo>(router: &R) -> String {
     }
 }
 
-pub fn format_router_status(status: &RouterStatus) -> String {
-    let header = format!(
+pub fn format_router_status(status: &Partial<RouterStatus>) -> String {
+    let sections: Vec<String> = [
+        status.value.board.as_ref().map(format_board),
+        status.value.system.as_ref().map(format_system),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    sections.join("\n\n") + &format_warnings(&status.warnings)
+}
+
+fn format_board(board: &BoardInfo) -> String {
+    format!(
         "[{}]\n{} | {}\n{}",
-        status.board.hostname,
-        status.board.release.distribution,
-        status.board.release.version,
-        status.board.model
-    );
+        board.hostname, board.release.distribution, board.release.version, board.model
+    )
+}
 
-    let uptime = format_uptime(status.system.uptime);
-    let memory = format_memory(&status.system.memory);
-    let load = format_load(&status.system.load);
+fn format_system(system: &SystemInfo) -> String {
+    let uptime = format_uptime(system.uptime);
+    let memory = format_memory(&system.memory);
+    let load = format_load(&system.load);
 
-    format!("{header}\n\nUptime: {uptime}\n{memory}\nLoad: {load}")
+    format!("Uptime: {uptime}\n{memory}\nLoad: {load}")
 }
 
 fn format_memory(mem: &MemoryInfo) -> String {
-    let used_mb = (mem.total - mem.available) / BYTES_IN_MB;
+    let used = mem.total - mem.usable();
+    let used_mb = used / BYTES_IN_MB;
     let total_mb = mem.total / BYTES_IN_MB;
-    let percent = ((mem.total - mem.available) * 100) / mem.total;
+    let percent = (used * 100) / mem.total.max(1);
     format!("RAM: {used_mb} / {total_mb} MB ({percent}%)")
 }
 
@@ -46,3 +57,35 @@ fn format_load(load: &[u32; 3]) -> String {
         load[2] as f64 / LOAD_DIVISOR
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::RouterError;
+
+    use super::*;
+
+    #[test]
+    fn renders_what_succeeded_and_warns_about_the_rest() {
+        let status = Partial {
+            value: RouterStatus {
+                system: Some(SystemInfo {
+                    uptime: 3600,
+                    ..SystemInfo::default()
+                }),
+                board: None,
+            },
+            warnings: vec![RouterError::from_status(
+                "system",
+                "board",
+                6,
+                "Command failed: Permission denied",
+            )],
+        };
+
+        assert_eq!(
+            format_router_status(&status),
+            "Uptime: 1h 0m\nRAM: 0 / 0 MB (0%)\nLoad: 0.00 0.00 0.00\n\n\
+             Warning: system board: permission denied"
+        );
+    }
+}
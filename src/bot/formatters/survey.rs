@@ -0,0 +1,56 @@
+//! Channel use formatters.
+
+use crate::domain::{Airtime, RouterInfo};
+
+use super::super::airtime::{AirtimeLog, Reading, sample};
+use super::super::messages::CHANNEL_USE_HEADER;
+use super::utils::{format_band, format_error, format_uptime};
+
+/// Takes a fresh reading of every radio and sets it against the history.
+pub async fn format_survey<R: RouterInfo>(router: &R, log: &AirtimeLog) -> String {
+    let wireless = match router.wireless_status().await {
+        Ok(w) => w,
+        Err(e) => return format_error(&e),
+    };
+
+    let mut lines = vec![CHANNEL_USE_HEADER.to_string()];
+    for (name, reading) in sample(router, &wireless, log).await {
+        let config = &wireless.0[&name].config;
+        let radio = format!(
+            "{name} ({}), channel {}",
+            format_band(&config.band),
+            config.channel
+        );
+        lines.push(String::new());
+        match reading {
+            Reading::Measured(airtime) => {
+                lines.push(format!("{radio}: {}", format_airtime(&airtime)));
+                if let Some(summary) = log.summary(&name) {
+                    lines.push(format!(
+                        "  Peak: {}% {} ago | Average: {}% over {} samples",
+                        summary.peak.airtime.busy,
+                        format_uptime(summary.peak.at.elapsed().as_secs()),
+                        summary.average_busy,
+                        summary.samples
+                    ));
+                }
+            }
+            Reading::Off => lines.push(format!("{radio}: off")),
+            Reading::Unavailable => lines.push(format!("{radio}: no counters from the driver")),
+            Reading::Failed(e) => lines.push(format!("{radio}: {}", format_error(&e))),
+        }
+    }
+    lines.join("\n")
+}
+
+/// `busy 38% | rx 24% | tx 4% | noise -91 dBm`
+pub fn format_airtime(airtime: &Airtime) -> String {
+    let mut text = format!(
+        "busy {}% | rx {}% | tx {}%",
+        airtime.busy, airtime.rx, airtime.tx
+    );
+    if let Some(noise) = airtime.noise {
+        text.push_str(&format!(" | noise {noise} dBm"));
+    }
+    text
+}
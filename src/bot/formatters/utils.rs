@@ -5,18 +5,48 @@ use std::time::Duration;
 use crate::domain::ubus::WifiClient;
 use crate::domain::{RouterError, WifiMode};
 
-const BITS_PER_MBPS: f64 = 1_000_000.0;
+const KBITS_PER_MBPS: f64 = 1000.0;
+pub const BYTES_IN_MB: u64 = 1024 * 1024;
+const MB_IN_GB: f64 = 1024.0;
 const SECONDS_IN_MINUTE: u64 = 60;
 const SECONDS_IN_HOUR: u64 = 3600;
 const SECONDS_IN_DAY: u64 = 86400;
 
-pub fn format_speed(bps: u64) -> String {
-    let mbps = bps as f64 / BITS_PER_MBPS;
+/// Formats a hostapd link rate, which is in kbit/s.
+pub fn format_speed(kbps: u64) -> String {
+    let mbps = kbps as f64 / KBITS_PER_MBPS;
     format!("{mbps:.0} Mbps")
 }
 
-pub fn wifi_mode(client: &WifiClient) -> &'static str {
-    WifiMode::from_client(client).as_str()
+pub fn format_bytes(bytes: u64) -> String {
+    let mb = bytes as f64 / BYTES_IN_MB as f64;
+    if mb >= MB_IN_GB {
+        format!("{:.1} GB", mb / MB_IN_GB)
+    } else {
+        format!("{mb:.1} MB")
+    }
+}
+
+/// The station's standard, marked `MLO` when it uses several links.
+pub fn wifi_mode(client: &WifiClient, band: &str) -> String {
+    let mode = WifiMode::from_client(client, band);
+    if client.is_multi_link() {
+        format!("{mode} MLO")
+    } else {
+        mode.to_string()
+    }
+}
+
+/// `2.4 GHz` for `2g` and so on; unknown bands are shown as they are.
+pub fn format_band(band: &str) -> &str {
+    match band {
+        "2g" => "2.4 GHz",
+        "5g" => "5 GHz",
+        "6g" => "6 GHz",
+        "60g" => "60 GHz",
+        "" => "?",
+        other => other,
+    }
 }
 
 pub fn format_uptime(seconds: u64) -> String {
@@ -35,3 +65,15 @@ pub fn format_uptime(seconds: u64) -> String {
 pub fn format_error(error: &RouterError) -> String {
     format!("{}: {error}", super::super::messages::ERROR_PREFIX)
 }
+
+/// One line per part that could not be fetched, after a blank line.
+pub fn format_warnings(warnings: &[RouterError]) -> String {
+    if warnings.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<String> = warnings
+        .iter()
+        .map(|e| format!("{}: {e}", super::super::messages::WARNING_PREFIX))
+        .collect();
+    format!("\n\n{}", lines.join("\n"))
+}
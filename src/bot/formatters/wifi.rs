@@ -3,34 +3,43 @@
 use crate::domain::RouterInfo;
 use crate::domain::ubus::{RadioInfo, WirelessStatus};
 
+use super::super::airtime::AirtimeLog;
 use super::super::messages::{RADIO_OFF, RADIO_ON, WIFI_STATUS};
-use super::utils::format_error;
+use super::survey::format_airtime;
+use super::utils::{format_band, format_error};
 
-pub async fn format_wifi_status<R: RouterInfo>(router: &R) -> String {
+pub async fn format_wifi_status<R: RouterInfo>(router: &R, airtime: &AirtimeLog) -> String {
     match router.wireless_status().await {
-        Ok(wireless) => format_wireless_status(&wireless),
+        Ok(wireless) => format_wireless_status(&wireless, airtime),
         Err(e) => format_error(&e),
     }
 }
 
-pub fn format_wireless_status(wireless: &WirelessStatus) -> String {
+/// Channel use is the latest sample, if any; `/wifi` does not wait for one.
+pub fn format_wireless_status(wireless: &WirelessStatus, airtime: &AirtimeLog) -> String {
     let mut lines = vec![WIFI_STATUS.to_string()];
 
     for (name, radio) in &wireless.0 {
-        lines.push(format_radio_status(name, radio));
+        lines.push(format_radio_status(name, radio, airtime));
     }
 
     lines.join("")
 }
 
-fn format_radio_status(name: &str, radio: &RadioInfo) -> String {
+fn format_radio_status(name: &str, radio: &RadioInfo, airtime: &AirtimeLog) -> String {
     let status = if radio.up && !radio.disabled {
         RADIO_ON
     } else {
         RADIO_OFF
     };
-    let band = &radio.config.band;
-    let channel = &radio.config.channel;
+    let band = format_band(&radio.config.band);
+    let mut channel = radio.config.channel.clone();
+    if let Some(width) = radio.config.channel_width() {
+        channel.push_str(&format!(" | {width} MHz"));
+    }
+    if let Some(mode) = radio.config.mode() {
+        channel.push_str(&format!(" | {mode}"));
+    }
 
     let mut result = String::new();
     for iface in &radio.interfaces {
@@ -38,6 +47,12 @@ fn format_radio_status(name: &str, radio: &RadioInfo) -> String {
         result.push_str(&format!(
             "\n[{status}] {ssid} ({band})\n    Radio: {name} | Channel: {channel}"
         ));
+        if let Some(sample) = airtime.latest(name) {
+            result.push_str(&format!(
+                "\n    Airtime: {}",
+                format_airtime(&sample.airtime)
+            ));
+        }
     }
 
     result
@@ -1,9 +1,57 @@
 //! Universal command handlers.
 
-use crate::domain::RouterInfo;
+use std::borrow::Cow;
 
-use super::formatters::{format_status, format_wifi_clients, format_wifi_status};
-use super::messages::{HELP_HEADER, HELP_TEXT, PONG};
+use crate::domain::{
+    ApplyTiming, ChannelChange, ClientQuery, ClientSort, Encryption, LogError, LogLevelControl,
+    LogLevelState, LogQuery, LogSource, RouterInfo, Secret, UpdateOutcome, Updater, WifiMode,
+    WifiSetting,
+};
+
+use super::airtime::AirtimeLog;
+use super::formatters::{
+    format_channel_change, format_client_details, format_scan, format_share, format_status,
+    format_survey, format_wifi_change, format_wifi_clients, format_wifi_status,
+};
+use super::history::BandHistory;
+use super::messages::{
+    CLIENT_USAGE, CLIENTS_USAGE, ERROR_PREFIX, HELP_HEADER, HELP_TEXT, LOG_LEVEL_RESET,
+    LOG_TRUNCATED, NO_LOG_LINES, PONG, RESTARTING, UNKNOWN_BUTTON, UPDATE_ROLLBACK, UPDATE_USAGE,
+    WIFI_SET_USAGE,
+};
+use super::qr::qr_png;
+
+const DEFAULT_LOG_LINES: usize = 50;
+const MAX_LOG_LINES: usize = 1000;
+/// Longer replies go out as a document; Telegram caps messages at 4096 chars.
+const MAX_TEXT_REPLY: usize = 3500;
+const MAX_DOCUMENT_REPLY: usize = 256 * 1024;
+const BANDS: [&str; 4] = ["2g", "5g", "6g", "60g"];
+/// Callback data of a channel change button: `channel:<radio>:<channel>`.
+const CHANNEL_BUTTON: &str = "channel";
+const WIFI_SET_COMMAND: &str = "/wifi_set";
+/// The `/wifi_set` option whose value must not linger in logs or the chat.
+const WIFI_SET_PASSWORD: &str = "password";
+
+/// `/clients` arguments; bad ones are kept as an error to reply with.
+pub type ClientArgs = Result<ClientQuery, String>;
+
+/// `/share` arguments; bad ones are kept as an error to reply with.
+pub type ShareArgs = Result<ShareRequest, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareRequest {
+    pub ssid: String,
+    /// Another chat to send the code to: a numeric id or `@channel`.
+    pub chat: Option<String>,
+}
+
+/// A reply that is short text, a text file or a picture.
+pub enum Reply {
+    Text(String),
+    Document { file_name: String, content: String },
+    Photo { caption: String, png: Vec<u8> },
+}
 
 pub fn ping_response() -> String {
     PONG.to_string()
@@ -17,10 +65,550 @@ pub async fn status_response<R: RouterInfo>(router: &R) -> String {
     format_status(router).await
 }
 
-pub async fn wifi_response<R: RouterInfo>(router: &R) -> String {
-    format_wifi_status(router).await
+pub async fn wifi_response<R: RouterInfo>(router: &R, airtime: &AirtimeLog) -> String {
+    format_wifi_status(router, airtime).await
+}
+
+pub async fn survey_response<R: RouterInfo>(router: &R, airtime: &AirtimeLog) -> String {
+    format_survey(router, airtime).await
+}
+
+pub async fn clients_response<R: RouterInfo>(
+    router: &R,
+    args: &ClientArgs,
+    history: &BandHistory,
+) -> String {
+    match args {
+        Ok(query) => format_wifi_clients(router, query, history).await,
+        Err(e) => format!("{ERROR_PREFIX}: {e}\n{CLIENTS_USAGE}"),
+    }
+}
+
+pub async fn client_response<R: RouterInfo>(
+    router: &R,
+    args: &str,
+    history: &BandHistory,
+) -> String {
+    match args.trim() {
+        "" => CLIENT_USAGE.to_string(),
+        target => format_client_details(router, target, history).await,
+    }
+}
+
+/// Scans all radios, or those named or on the band in `args`; a button is
+/// offered for every channel change worth making.
+pub async fn scan_response<R: RouterInfo>(router: &R, args: &str) -> (String, Vec<ChannelChange>) {
+    let target = match args.trim() {
+        "" => None,
+        target => Some(parse_band(target).unwrap_or_else(|_| target.to_string())),
+    };
+    format_scan(router, target.as_deref()).await
+}
+
+/// Applies the channel change a `/scan` button carries.
+pub async fn channel_button_response<R: RouterInfo>(router: &R, data: &str) -> String {
+    match parse_channel_button(data) {
+        Some(change) => format_channel_change(router, &change).await,
+        None => UNKNOWN_BUTTON.to_string(),
+    }
+}
+
+/// The label and callback data of the button offering `change`.
+pub fn channel_button(change: &ChannelChange) -> (String, String) {
+    (
+        format!("Use channel {} on {}", change.channel, change.radio),
+        format!("{CHANNEL_BUTTON}:{}:{}", change.radio, change.channel),
+    )
+}
+
+fn parse_channel_button(data: &str) -> Option<ChannelChange> {
+    let rest = data.strip_prefix(CHANNEL_BUTTON)?.strip_prefix(':')?;
+    let (radio, channel) = rest.rsplit_once(':')?;
+    Some(ChannelChange {
+        radio: radio.to_string(),
+        channel: channel.parse().ok()?,
+    })
+}
+
+/// A QR code guests can scan to join `ssid`.
+pub async fn share_response<R: RouterInfo>(router: &R, ssid: &str) -> Reply {
+    let (caption, payload) = match format_share(router, ssid).await {
+        Ok(share) => share,
+        Err(e) => return Reply::Text(e),
+    };
+    match qr_png(&payload) {
+        Ok(png) => Reply::Photo { caption, png },
+        Err(e) => Reply::Text(format!("{ERROR_PREFIX}: {e}")),
+    }
+}
+
+/// Runs `/wifi_set <ssid> password|ssid|encryption <value>`.
+pub async fn wifi_set_response<R: RouterInfo>(
+    router: &R,
+    args: &str,
+    timing: ApplyTiming,
+) -> String {
+    match parse_wifi_setting(args) {
+        Ok((ssid, setting)) => format_wifi_change(router, &ssid, &setting, timing).await,
+        Err(e) => format!("{ERROR_PREFIX}: {e}\n{WIFI_SET_USAGE}"),
+    }
+}
+
+/// Whether `/wifi_set` arguments hold a passphrase, so the message holding
+/// them should be deleted.
+pub fn carries_password(args: &str) -> bool {
+    split_wifi_setting(args)
+        .is_ok_and(|(_, option, _)| option.eq_ignore_ascii_case(WIFI_SET_PASSWORD))
+}
+
+/// A command's text as it may be logged: `/wifi_set` passphrases are masked.
+pub fn loggable_command(text: &str) -> Cow<'_, str> {
+    let (command, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = command.split('@').next().unwrap_or_default();
+    if !name.eq_ignore_ascii_case(WIFI_SET_COMMAND) || !carries_password(args) {
+        return Cow::Borrowed(text);
+    }
+    let ssid = split_wifi_setting(args)
+        .map(|(ssid, _, _)| ssid)
+        .unwrap_or_default();
+    let masked = Secret::new(());
+    Cow::Owned(format!("{command} \"{ssid}\" {WIFI_SET_PASSWORD} {masked}"))
+}
+
+pub async fn logs_response(logs: &dyn LogSource, args: &str) -> Reply {
+    match parse_log_query(args) {
+        Ok(query) => log_reply(logs.bot_log(&query).await, "bot.log"),
+        Err(e) => Reply::Text(format!("{ERROR_PREFIX}: {e}")),
+    }
+}
+
+pub async fn syslog_response(logs: &dyn LogSource, args: &str) -> Reply {
+    match parse_log_query(args) {
+        Ok(query) => log_reply(logs.system_log(&query).await, "syslog.txt"),
+        Err(e) => Reply::Text(format!("{ERROR_PREFIX}: {e}")),
+    }
+}
+
+fn log_reply(result: Result<Vec<String>, LogError>, file_name: &str) -> Reply {
+    match result {
+        Ok(lines) if lines.is_empty() => Reply::Text(NO_LOG_LINES.to_string()),
+        Ok(lines) => {
+            let text = lines.join("\n");
+            if text.len() <= MAX_TEXT_REPLY {
+                Reply::Text(text)
+            } else {
+                Reply::Document {
+                    file_name: file_name.to_string(),
+                    content: keep_tail(text, MAX_DOCUMENT_REPLY),
+                }
+            }
+        }
+        Err(e) => Reply::Text(format!("{ERROR_PREFIX}: {e}")),
+    }
+}
+
+/// Shows the filter, resets it, or sets a temporary one.
+///
+/// Directives may be separated by spaces: `/loglevel debug teloxide=warn`.
+pub fn loglevel_response(control: &dyn LogLevelControl, args: &str) -> String {
+    let args = args.trim();
+    let result = match args {
+        "" => Ok(control.state()),
+        LOG_LEVEL_RESET => control.reset(),
+        directives => control.set(&directives.split_whitespace().collect::<Vec<_>>().join(",")),
+    };
+
+    match result {
+        Ok(state) => format_log_level(&state),
+        Err(e) => format!("{ERROR_PREFIX}: {e}"),
+    }
+}
+
+fn format_log_level(state: &LogLevelState) -> String {
+    let mut text = format!("Log filter: {}", state.filter);
+    if state.filter != state.configured {
+        text.push_str(&format!("\nConfigured: {}", state.configured));
+    }
+    if let Some(after) = state.revert_in {
+        text.push_str(&format!("\nReverts in {:.0}s", after.as_secs_f64()));
+    }
+    text
+}
+
+/// Runs `/update` or `/update rollback`.
+///
+/// Returns the reply and whether the process should restart once it is sent.
+pub async fn update_response(updater: &dyn Updater, args: &str) -> (String, bool) {
+    let result = match args.trim() {
+        "" => updater.update().await,
+        UPDATE_ROLLBACK => updater.rollback().await,
+        _ => return (UPDATE_USAGE.to_string(), false),
+    };
+
+    match result {
+        Ok(UpdateOutcome::Installed { from, to }) => {
+            (format!("Updated {from} → {to}. {RESTARTING}"), true)
+        }
+        Ok(UpdateOutcome::UpToDate { version }) => (format!("Already running {version}"), false),
+        Ok(UpdateOutcome::RolledBack) => (format!("Rolled back. {RESTARTING}"), true),
+        Err(e) => (format!("{ERROR_PREFIX}: {e}"), false),
+    }
 }
 
-pub async fn clients_response<R: RouterInfo>(router: &R) -> String {
-    format_wifi_clients(router).await
+/// Parses `[n] [filter]`; the filter is the rest of the line.
+///
+/// A leading number is the count, also accepted as `n=<lines>`. A filter that
+/// is itself a number goes after the count or in quotes: `/logs 20 404`,
+/// `/logs "404"`.
+pub fn parse_log_query(args: &str) -> Result<LogQuery, String> {
+    let args = args.trim();
+    let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+
+    let count = first
+        .strip_prefix("n=")
+        .or((!first.is_empty() && first.bytes().all(|b| b.is_ascii_digit())).then_some(first));
+    let (lines, pattern) = match count {
+        Some(count) => match count.parse::<usize>() {
+            Ok(0) => return Err("line count must be positive".to_string()),
+            Ok(n) if n > MAX_LOG_LINES => {
+                return Err(format!("at most {MAX_LOG_LINES} lines can be requested"));
+            }
+            Ok(n) => (n, rest.trim()),
+            Err(_) => return Err(format!("invalid line count '{count}'")),
+        },
+        None => (DEFAULT_LOG_LINES, args),
+    };
+    let pattern = pattern
+        .strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(pattern);
+
+    Ok(LogQuery {
+        lines,
+        pattern: (!pattern.is_empty()).then(|| pattern.to_string()),
+    })
+}
+
+/// Parses `[sort=..] [band=..] [ssid=..] [weak] [mode=..]` in any order.
+///
+/// Values with spaces can be quoted: `ssid="Home WiFi"`.
+pub fn parse_client_query(args: &str) -> Result<ClientQuery, String> {
+    let mut query = ClientQuery::default();
+
+    for word in split_quoted(args)? {
+        let (key, value) = match word.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (word.as_str(), None),
+        };
+        match (key.to_lowercase().as_str(), value) {
+            ("weak", None) => query.weak = true,
+            ("sort", Some(value)) => query.sort = parse_client_sort(value)?,
+            ("band", Some(value)) => query.band = Some(parse_band(value)?),
+            ("ssid", Some(value)) if !value.is_empty() => query.ssid = Some(value.to_string()),
+            ("mode", Some(value)) => query.mode = Some(parse_wifi_mode(value)?),
+            _ => return Err(format!("unknown option '{word}'")),
+        }
+    }
+    Ok(query)
+}
+
+fn parse_client_sort(value: &str) -> Result<ClientSort, String> {
+    match value.to_lowercase().as_str() {
+        "signal" => Ok(ClientSort::Signal),
+        "rate" => Ok(ClientSort::Rate),
+        "traffic" => Ok(ClientSort::Traffic),
+        "name" => Ok(ClientSort::Name),
+        _ => Err(format!("cannot sort by '{value}'")),
+    }
+}
+
+/// Accepts `5g`, `5`, `5GHz` and `2.4` alike.
+fn parse_band(value: &str) -> Result<String, String> {
+    let lower = value.to_lowercase();
+    let number = lower.trim_end_matches("ghz").trim_end_matches('g');
+    let band = match number {
+        "2.4" => "2g".to_string(),
+        n => format!("{n}g"),
+    };
+    if BANDS.contains(&band.as_str()) {
+        Ok(band)
+    } else {
+        Err(format!("unknown band '{value}'"))
+    }
+}
+
+/// Matches `legacy`, `wifi6`, `WiFi 6` and the like.
+fn parse_wifi_mode(value: &str) -> Result<WifiMode, String> {
+    let wanted: String = value.split_whitespace().collect();
+    WifiMode::ALL
+        .into_iter()
+        .find(|mode| mode.as_str().replace(' ', "").eq_ignore_ascii_case(&wanted))
+        .ok_or_else(|| format!("unknown mode '{value}'"))
+}
+
+/// Parses `<ssid> [chat]`; SSIDs with spaces must be quoted.
+pub fn parse_share_request(args: &str) -> Result<ShareRequest, String> {
+    let mut words = split_quoted(args)?.into_iter();
+    let (Some(ssid), chat, None) = (words.next(), words.next(), words.next()) else {
+        return Err("expected an SSID and at most a chat".to_string());
+    };
+    if let Some(chat) = &chat
+        && chat.parse::<i64>().is_err()
+        && !chat.starts_with('@')
+    {
+        return Err(format!("'{chat}' is neither a chat id nor an @channel"));
+    }
+    Ok(ShareRequest { ssid, chat })
+}
+
+/// Parses `<ssid> password|ssid|encryption <value>`.
+fn parse_wifi_setting(args: &str) -> Result<(String, WifiSetting), String> {
+    let (ssid, option, value) = split_wifi_setting(args)?;
+    let setting = match option.to_lowercase().as_str() {
+        WIFI_SET_PASSWORD => WifiSetting::Password(Secret::new(unquote(value).to_string())),
+        "ssid" => WifiSetting::Ssid(unquote(value).to_string()),
+        "encryption" => WifiSetting::Encryption(parse_encryption(value)?),
+        _ => return Err(format!("cannot set '{option}'")),
+    };
+    Ok((ssid, setting))
+}
+
+/// Drops one pair of double quotes around `value`; quotes inside it stay.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Splits `/wifi_set` arguments into the SSID, which may be quoted, the
+/// option, and its value: the rest of the line, so passphrases keep their
+/// spaces and any quotes inside them.
+fn split_wifi_setting(args: &str) -> Result<(String, &str, &str), String> {
+    let args = args.trim_start();
+    let (ssid, rest) = match args.strip_prefix('"') {
+        Some(quoted) => quoted
+            .split_once('"')
+            .ok_or_else(|| "unterminated quote".to_string())?,
+        None => args.split_once(char::is_whitespace).unwrap_or((args, "")),
+    };
+    let rest = rest.trim_start();
+    let (option, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let value = value.trim();
+    if ssid.is_empty() || option.is_empty() || value.is_empty() {
+        return Err("missing arguments".to_string());
+    }
+    Ok((ssid.to_string(), option, value))
+}
+
+/// Takes UCI names, as well as `wpa2`, `wpa3`, `wpa2/wpa3` and `open`.
+fn parse_encryption(value: &str) -> Result<Encryption, String> {
+    let uci = match value.to_lowercase().as_str() {
+        "open" => "none".to_string(),
+        "wpa2" => "psk2".to_string(),
+        "wpa/wpa2" => "psk-mixed".to_string(),
+        "wpa3" => "sae".to_string(),
+        "wpa2/wpa3" => "sae-mixed".to_string(),
+        other => other.to_string(),
+    };
+    Encryption::ALL
+        .into_iter()
+        .find(|e| e.as_uci() == uci)
+        .ok_or_else(|| format!("unknown encryption '{value}'"))
+}
+
+/// Splits on whitespace, keeping double-quoted runs together.
+fn split_quoted(args: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+
+    for c in args.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Drops whole lines from the front until `text` fits in `max` bytes.
+fn keep_tail(text: String, max: usize) -> String {
+    if text.len() <= max {
+        return text;
+    }
+    let budget = max.saturating_sub(LOG_TRUNCATED.len() + 1);
+    let mut start = text.len() - budget;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let tail = match text[start..].split_once('\n') {
+        Some((_, tail)) => tail,
+        None => &text[start..],
+    };
+    format!("{LOG_TRUNCATED}\n{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_channel_buttons() {
+        let change = ChannelChange {
+            radio: "radio1".to_string(),
+            channel: 149,
+        };
+        let (label, data) = channel_button(&change);
+
+        assert_eq!(label, "Use channel 149 on radio1");
+        assert_eq!(parse_channel_button(&data), Some(change));
+        assert_eq!(parse_channel_button("channel:radio1:auto"), None);
+        assert_eq!(parse_channel_button("other:radio1:149"), None);
+    }
+
+    #[test]
+    fn parses_wifi_settings_and_masks_passphrases() {
+        let (ssid, setting) = parse_wifi_setting(r#""Home WiFi" password  "my pass" "#).unwrap();
+        assert_eq!(ssid, "Home WiFi");
+        assert_eq!(
+            setting,
+            WifiSetting::Password(Secret::new("my pass".to_string()))
+        );
+        assert_eq!(
+            parse_wifi_setting(r#"Home password say "hi" now"#)
+                .unwrap()
+                .1,
+            WifiSetting::Password(Secret::new(r#"say "hi" now"#.to_string()))
+        );
+        assert_eq!(
+            parse_wifi_setting(r#"Home ssid "New Name""#).unwrap().1,
+            WifiSetting::Ssid("New Name".to_string())
+        );
+        assert_eq!(
+            parse_wifi_setting("Home encryption WPA2/WPA3").unwrap().1,
+            WifiSetting::Encryption(Encryption::SaeMixed)
+        );
+        assert!(parse_wifi_setting("Home encryption wep").is_err());
+        assert!(parse_wifi_setting("Home channel 6").is_err());
+        assert!(parse_wifi_setting("Home password").is_err());
+
+        assert!(carries_password("Home PASSWORD hunter22"));
+        assert!(!carries_password("Home ssid password"));
+        assert_eq!(
+            loggable_command("/wifi_set@bot Home password hunter22"),
+            r#"/wifi_set@bot "Home" password [REDACTED]"#
+        );
+        assert_eq!(loggable_command("/logs 20 password"), "/logs 20 password");
+    }
+
+    #[tokio::test]
+    async fn documents_share_and_wifi_set_in_their_own_places() {
+        assert!(help_response().contains("\n/share <ssid> [chat]"));
+
+        let reply = wifi_set_response(
+            &crate::testing::FakeRouter::default(),
+            "Home channel 6",
+            ApplyTiming::default(),
+        )
+        .await;
+        let usage = reply.lines().skip(1).collect::<Vec<_>>().join("\n");
+        assert!(
+            usage.starts_with("Usage: /wifi_set <ssid> password"),
+            "{reply}"
+        );
+    }
+
+    #[test]
+    fn parses_share_requests() {
+        let request = parse_share_request(r#""Guest WiFi" -1001234"#).unwrap();
+        assert_eq!(request.ssid, "Guest WiFi");
+        assert_eq!(request.chat.as_deref(), Some("-1001234"));
+        assert_eq!(
+            parse_share_request("Guest @family")
+                .unwrap()
+                .chat
+                .as_deref(),
+            Some("@family")
+        );
+        assert_eq!(parse_share_request("Guest").unwrap().chat, None);
+        assert!(parse_share_request("").is_err());
+        assert!(parse_share_request("Guest WiFi family").is_err());
+        assert!(parse_share_request("Guest family").is_err());
+    }
+
+    #[test]
+    fn parses_log_arguments() {
+        let query = |args| parse_log_query(args).unwrap();
+
+        assert_eq!(query("").lines, DEFAULT_LOG_LINES);
+        assert_eq!(query("").pattern, None);
+        assert_eq!(query("20").lines, 20);
+        assert_eq!(query("20").pattern, None);
+        assert_eq!(
+            query("20  dnsmasq dhcp").pattern.as_deref(),
+            Some("dnsmasq dhcp")
+        );
+        assert_eq!(query("n=20").lines, 20);
+        assert_eq!(query("hostapd").lines, DEFAULT_LOG_LINES);
+        assert_eq!(query("hostapd").pattern.as_deref(), Some("hostapd"));
+        assert_eq!(query("5 404").lines, 5);
+        assert_eq!(query("5 404").pattern.as_deref(), Some("404"));
+        assert_eq!(query("\"404\"").lines, DEFAULT_LOG_LINES);
+        assert_eq!(query("\"404\"").pattern.as_deref(), Some("404"));
+        assert_eq!(query("\"not found\"").pattern.as_deref(), Some("not found"));
+        assert!(parse_log_query("0").is_err());
+        assert!(parse_log_query("100000").is_err());
+        assert!(parse_log_query("n=all").is_err());
+    }
+
+    #[test]
+    fn parses_client_arguments() {
+        assert_eq!(parse_client_query("  ").unwrap(), ClientQuery::default());
+
+        let query =
+            parse_client_query(r#"sort=rate band=5GHz ssid="Home WiFi" weak mode=legacy"#).unwrap();
+        assert_eq!(query.sort, ClientSort::Rate);
+        assert_eq!(query.band.as_deref(), Some("5g"));
+        assert_eq!(query.ssid.as_deref(), Some("Home WiFi"));
+        assert!(query.weak);
+        assert_eq!(query.mode, Some(WifiMode::Legacy));
+
+        assert_eq!(
+            parse_client_query("band=2.4").unwrap().band.as_deref(),
+            Some("2g")
+        );
+        assert_eq!(
+            parse_client_query("mode=wifi6").unwrap().mode,
+            Some(WifiMode::Wifi6)
+        );
+        assert!(parse_client_query("sort=vibes").is_err());
+        assert!(parse_client_query("band=4g").is_err());
+        assert!(parse_client_query("strong").is_err());
+        assert!(parse_client_query("weak=yes").is_err());
+        assert!(parse_client_query("ssid=\"Home").is_err());
+    }
+
+    #[test]
+    fn caps_large_output_at_line_boundary() {
+        let text: String = (0..100).map(|i| format!("line {i:03}\n")).collect();
+
+        let capped = keep_tail(text, 60);
+
+        assert!(capped.len() <= 60, "{}", capped.len());
+        assert!(capped.starts_with(LOG_TRUNCATED));
+        assert!(capped.ends_with("line 099\n"));
+        assert!(capped.lines().skip(1).all(|l| l.starts_with("line ")));
+    }
 }
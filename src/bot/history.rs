@@ -0,0 +1,124 @@
+//! Bands each station has been seen on.
+//!
+//! Only what `/clients` and `/client` observe is recorded, and only in memory,
+//! so the history starts over whenever the bot does.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::domain::router::InterfaceClients;
+
+/// Band changes kept per station.
+const MAX_SIGHTINGS: usize = 8;
+/// Stations remembered; the longest unseen is forgotten first.
+const MAX_STATIONS: usize = 256;
+
+/// A station arriving on a band and SSID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sighting {
+    pub band: String,
+    pub ssid: String,
+    pub since: Instant,
+}
+
+struct StationHistory {
+    sightings: Vec<Sighting>,
+    last_seen: Instant,
+}
+
+#[derive(Default)]
+pub struct BandHistory {
+    stations: Mutex<HashMap<String, StationHistory>>,
+}
+
+impl BandHistory {
+    pub fn record(&self, interfaces: &[InterfaceClients]) {
+        let now = Instant::now();
+        let mut stations = self.stations.lock().unwrap();
+
+        for iface in interfaces {
+            for mac in iface.clients.clients.keys() {
+                let station = stations.entry(mac.clone()).or_insert(StationHistory {
+                    sightings: Vec::new(),
+                    last_seen: now,
+                });
+                station.last_seen = now;
+                let moved = station
+                    .sightings
+                    .last()
+                    .is_none_or(|s| s.band != iface.band || s.ssid != iface.ssid);
+                if moved {
+                    station.sightings.push(Sighting {
+                        band: iface.band.clone(),
+                        ssid: iface.ssid.clone(),
+                        since: now,
+                    });
+                }
+                if station.sightings.len() > MAX_SIGHTINGS {
+                    station.sightings.remove(0);
+                }
+            }
+        }
+
+        while stations.len() > MAX_STATIONS {
+            let stalest = stations
+                .iter()
+                .min_by_key(|(_, station)| station.last_seen)
+                .map(|(mac, _)| mac.clone());
+            if let Some(mac) = stalest {
+                stations.remove(&mac);
+            }
+        }
+    }
+
+    /// Oldest first.
+    pub fn sightings(&self, mac: &str) -> Vec<Sighting> {
+        self.stations
+            .lock()
+            .unwrap()
+            .get(mac)
+            .map(|station| station.sightings.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::ubus::HostapdClients;
+
+    use super::*;
+
+    fn iface(band: &str, macs: &[&str]) -> InterfaceClients {
+        InterfaceClients {
+            ifname: format!("wlan-{band}"),
+            ssid: "Home".to_string(),
+            band: band.to_string(),
+            clients: HostapdClients {
+                clients: macs
+                    .iter()
+                    .map(|mac| (mac.to_string(), Default::default()))
+                    .collect(),
+                ..HostapdClients::default()
+            },
+        }
+    }
+
+    #[test]
+    fn records_band_changes_only() {
+        let history = BandHistory::default();
+        let phone = "aa:bb:cc:00:00:01";
+
+        history.record(&[iface("2g", &[phone])]);
+        history.record(&[iface("2g", &[phone])]);
+        history.record(&[iface("5g", &[phone])]);
+
+        let bands: Vec<_> = history
+            .sightings(phone)
+            .into_iter()
+            .map(|s| s.band)
+            .collect();
+        assert_eq!(bands, ["2g", "5g"]);
+        assert!(history.sightings("aa:bb:cc:00:00:02").is_empty());
+    }
+}
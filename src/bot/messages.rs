@@ -5,7 +5,30 @@ pub const HELP_HEADER: &str = "Available commands:";
 pub const WIFI_STATUS: &str = "WiFi Status";
 pub const CLIENTS_HEADER: &str = "Connected devices";
 pub const NO_DEVICES: &str = "No connected devices";
+pub const NO_MATCHING_DEVICES: &str = "No matching devices";
+pub const NOT_CONNECTED: &str = "not connected";
+pub const CLIENT_USAGE: &str = "Usage: /client <mac|hostname>";
+pub const CLIENTS_USAGE: &str = "Usage: /clients [sort=signal|rate|traffic|name] [band=5g] \
+[ssid=name] [weak] [mode=legacy|wifi4|wifi5|wifi6|wifi6e|wifi7]";
+pub const CHANNEL_USE_HEADER: &str = "Channel use";
+pub const SCAN_HEADER: &str = "Neighbouring networks";
+pub const NO_NETWORKS_HEARD: &str = "No networks heard";
+pub const HIDDEN_SSID: &str = "(hidden)";
+pub const UNKNOWN_BUTTON: &str = "This button is no longer valid";
+pub const WIFI_SET_USAGE: &str = "Usage: /wifi_set <ssid> password <passphrase> | \
+ssid <new name> | encryption <psk2|psk-mixed|sae|sae-mixed|owe|none>";
+pub const SHARE_USAGE: &str = "Usage: /share <ssid> [chat id|@channel]";
+pub const PASSWORD_NOT_DELETED: &str =
+    "Unable to delete your message with the passphrase; delete it yourself";
 pub const ERROR_PREFIX: &str = "Error";
+pub const WARNING_PREFIX: &str = "Warning";
+pub const ADMIN_ONLY: &str = "This command is for admins only";
+pub const NO_LOG_LINES: &str = "No matching log lines";
+pub const LOG_TRUNCATED: &str = "[earlier lines truncated]";
+pub const LOG_LEVEL_RESET: &str = "reset";
+pub const UPDATE_ROLLBACK: &str = "rollback";
+pub const UPDATE_USAGE: &str = "Usage: /update [rollback]";
+pub const RESTARTING: &str = "Restarting...";
 
 pub const RADIO_ON: &str = "ON";
 pub const RADIO_OFF: &str = "OFF";
@@ -14,5 +37,14 @@ pub const HELP_TEXT: &str = "\
 /ping — Check connection
 /status — Router status
 /wifi — WiFi status
-/clients — Connected devices
+/clients [sort=..] [band=..] [ssid=..] [weak] [mode=..] — Connected devices
+/client <mac|hostname> — Everything known about one device
+/scan [radio|band] — Neighbouring networks and the quietest channel
+/survey — Channel busy time and noise per radio, with peaks
+/wifi_set <ssid> password|ssid|encryption <value> — Change an access point (admin)
+/share <ssid> [chat] — WiFi QR code for guests, here or in another chat
+/logs [n] [filter] — Tail of the bot log (admin)
+/syslog [n] [filter] — Tail of the system log (admin)
+/loglevel [filter|reset] — Show or change the log level (admin)
+/update [rollback] — Install the latest release or undo it (admin)
 /help — Show commands";
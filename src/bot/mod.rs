@@ -1,60 +1,101 @@
 //! Bot module - multi-messenger architecture.
 
+mod airtime;
 mod auth;
 mod commands;
 pub mod factory;
 mod formatters;
 mod handlers;
+mod history;
 mod messages;
+mod qr;
+mod supervisor;
 pub mod telegram;
 
+use std::sync::Arc;
+
 use futures::future::join_all;
+use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
 
 use crate::domain::ShutdownSignal;
 use crate::domain::messenger::Bot;
 
-type BotHandle = (
-    JoinHandle<anyhow::Result<()>>,
-    tokio::sync::mpsc::Sender<()>,
-);
+pub use supervisor::{BotState, BotStates, RestartPolicy};
+
+type BotHandle = (JoinHandle<()>, mpsc::Sender<()>);
 
 pub struct BotManager {
-    bots: Vec<Box<dyn Bot>>,
+    bots: Vec<(Arc<dyn Bot>, watch::Sender<BotState>)>,
+    policy: RestartPolicy,
 }
 
 impl BotManager {
-    pub fn new() -> Self {
-        Self { bots: Vec::new() }
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            bots: Vec::new(),
+            policy,
+        }
     }
 
     pub fn add<B: Bot + 'static>(&mut self, bot: B) {
-        self.bots.push(Box::new(bot));
+        let (state, _) = watch::channel(BotState::Stopped);
+        self.bots.push((Arc::new(bot), state));
     }
 
-    pub async fn run_all(self, mut shutdown_rx: ShutdownSignal) {
+    /// Follows each bot's state, also once `run_all` has taken the manager.
+    pub fn states(&self) -> BotStates {
+        BotStates::new(
+            self.bots
+                .iter()
+                .map(|(bot, state)| (bot.name().to_string(), state.subscribe()))
+                .collect(),
+        )
+    }
+
+    /// Runs all bots until shutdown.
+    ///
+    /// Returns an error if a bot exhausts its restart budget.
+    pub async fn run_all(self, mut shutdown_rx: ShutdownSignal) -> anyhow::Result<()> {
         if self.bots.is_empty() {
             tracing::warn!("No bots configured");
-            return;
+            shutdown_rx.recv().await;
+            return Ok(());
         }
 
         tracing::info!("Starting {} bot(s)", self.bots.len());
 
-        let handles = self.spawn_all();
-        wait_for_shutdown(&mut shutdown_rx).await;
+        let (failures_tx, mut failures_rx) = mpsc::channel(1);
+        let handles = self.spawn_all(&failures_tx);
+        drop(failures_tx);
+
+        let result = tokio::select! {
+            _ = wait_for_shutdown(&mut shutdown_rx) => Ok(()),
+            Some(error) = failures_rx.recv() => Err(error),
+        };
         stop_all(handles).await;
 
         tracing::info!("All bots stopped");
+        result
     }
 
-    fn spawn_all(self) -> Vec<BotHandle> {
-        self.bots.into_iter().map(spawn_bot).collect()
+    fn spawn_all(self, failures_tx: &mpsc::Sender<anyhow::Error>) -> Vec<BotHandle> {
+        let policy = self.policy;
+        self.bots
+            .into_iter()
+            .map(|(bot, state)| spawn_bot(bot, policy, failures_tx.clone(), state))
+            .collect()
     }
 }
 
-fn spawn_bot(bot: Box<dyn Bot>) -> BotHandle {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    let handle = tokio::spawn(async move { bot.run(rx).await });
+fn spawn_bot(
+    bot: Arc<dyn Bot>,
+    policy: RestartPolicy,
+    failures_tx: mpsc::Sender<anyhow::Error>,
+    state: watch::Sender<BotState>,
+) -> BotHandle {
+    let (tx, rx) = mpsc::channel(1);
+    let handle = tokio::spawn(supervisor::supervise(bot, policy, rx, failures_tx, state));
     (handle, tx)
 }
 
@@ -70,6 +111,6 @@ async fn stop_all(handles: Vec<BotHandle>) {
 
 impl Default for BotManager {
     fn default() -> Self {
-        Self::new()
+        Self::new(RestartPolicy::default())
     }
 }
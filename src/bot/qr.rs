@@ -0,0 +1,176 @@
+//! WiFi QR codes, as PNG images.
+//!
+//! Phones join a network from a `WIFI:` code straight from the camera app.
+
+use std::io::Write;
+
+use flate2::Crc;
+use flate2::write::ZlibEncoder;
+use qrcodegen::{QrCode, QrCodeEcc};
+
+use crate::domain::ubus::WifiInterfaceConfig;
+
+/// Pixels per module.
+const SCALE: usize = 8;
+/// Light modules around the code, as the standard asks.
+const QUIET_ZONE: usize = 4;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The `WIFI:T:WPA;S:<ssid>;P:<key>;;` text for an access point.
+pub fn wifi_payload(config: &WifiInterfaceConfig) -> Result<String, String> {
+    let ssid = &config.ssid;
+    let encryption = config.encryption.split('+').next().unwrap_or_default();
+    let needs_key = match encryption {
+        "" | "none" | "owe" => false,
+        "psk" | "psk2" | "psk-mixed" | "sae" | "sae-mixed" => true,
+        "wpa" | "wpa2" | "wpa3" | "wpa-mixed" | "wpa3-mixed" => {
+            return Err(format!("'{ssid}' uses enterprise logins, not a passphrase"));
+        }
+        other => return Err(format!("'{ssid}' uses {other}, which cannot be shared")),
+    };
+
+    let mut payload = if needs_key {
+        let key = config.key.as_ref().map(|k| k.expose().as_str());
+        let key = key
+            .filter(|k| !k.is_empty())
+            .ok_or_else(|| format!("'{ssid}' has no passphrase to share"))?;
+        format!("WIFI:T:WPA;S:{};P:{};", escape(ssid), escape(key))
+    } else {
+        format!("WIFI:T:nopass;S:{};", escape(ssid))
+    };
+    if is_hidden(config) {
+        payload.push_str("H:true;");
+    }
+    payload.push(';');
+    Ok(payload)
+}
+
+/// `text` as a black on white QR code.
+pub fn qr_png(text: &str) -> Result<Vec<u8>, String> {
+    let qr = QrCode::encode_text(text, QrCodeEcc::Medium)
+        .map_err(|_| "too long for a QR code".to_string())?;
+    let modules = qr.size() as usize + 2 * QUIET_ZONE;
+    let size = modules * SCALE;
+
+    let mut pixels = Vec::with_capacity((size + 1) * size);
+    for y in 0..size {
+        // Filter type 0: the row as is.
+        pixels.push(0);
+        let qr_y = (y / SCALE) as i32 - QUIET_ZONE as i32;
+        for x in 0..size {
+            let qr_x = (x / SCALE) as i32 - QUIET_ZONE as i32;
+            pixels.push(if qr.get_module(qr_x, qr_y) {
+                0x00
+            } else {
+                0xff
+            });
+        }
+    }
+    Ok(png(size as u32, &pixels))
+}
+
+/// Escapes the characters `WIFI:` codes reserve.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn is_hidden(config: &WifiInterfaceConfig) -> bool {
+    match config.extra.get("hidden") {
+        Some(serde_json::Value::Bool(hidden)) => *hidden,
+        Some(serde_json::Value::String(hidden)) => hidden == "1",
+        Some(serde_json::Value::Number(hidden)) => hidden.as_u64() == Some(1),
+        _ => false,
+    }
+}
+
+/// An 8-bit greyscale PNG `size` pixels square from filtered `rows`.
+fn png(size: u32, rows: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&size.to_be_bytes());
+    header.extend_from_slice(&size.to_be_bytes());
+    // Bit depth 8, greyscale, deflate, adaptive filtering, no interlace.
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    // Writes to a `Vec` cannot fail.
+    encoder.write_all(rows).unwrap();
+    let data = encoder.finish().unwrap();
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &data);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+    use serde_json::json;
+
+    use super::*;
+
+    fn config(value: serde_json::Value) -> WifiInterfaceConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn builds_escaped_payloads() {
+        let home = config(json!({"ssid": "Home; Net", "encryption": "sae-mixed", "key": "a:b\\c"}));
+        assert_eq!(
+            wifi_payload(&home).unwrap(),
+            r"WIFI:T:WPA;S:Home\; Net;P:a\:b\\c;;"
+        );
+
+        let guest = config(json!({"ssid": "Guest", "encryption": "none", "hidden": "1"}));
+        assert_eq!(
+            wifi_payload(&guest).unwrap(),
+            "WIFI:T:nopass;S:Guest;H:true;;"
+        );
+
+        assert!(wifi_payload(&config(json!({"ssid": "Work", "encryption": "wpa2"}))).is_err());
+        assert!(wifi_payload(&config(json!({"ssid": "Home", "encryption": "psk2"}))).is_err());
+    }
+
+    #[test]
+    fn renders_a_valid_png() {
+        let png = qr_png("WIFI:T:WPA;S:Home;P:secret123;;").unwrap();
+
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        let size = u32::from_be_bytes(png[16..20].try_into().unwrap()) as usize;
+        assert_eq!(size % SCALE, 0);
+
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels.len(), (size + 1) * size);
+        // White quiet zone, then the black corner of a finder pattern.
+        let row = (QUIET_ZONE * SCALE) * (size + 1);
+        assert_eq!(pixels[row + 1], 0xff);
+        assert_eq!(pixels[row + 1 + QUIET_ZONE * SCALE], 0x00);
+        assert!(png.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+    }
+}
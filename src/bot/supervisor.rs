@@ -0,0 +1,417 @@
+//! Bot supervision: restarts failed bots with exponential backoff.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::select_all;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+use crate::domain::ShutdownSignal;
+use crate::domain::messenger::Bot;
+use crate::infrastructure::config::RestartConfig;
+
+/// A run lasting at least this long resets the failure counter.
+const STABLE_RUN: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_failures: u32,
+}
+
+impl RestartPolicy {
+    pub fn from_config(config: &RestartConfig) -> Self {
+        Self {
+            base_delay: config.delay,
+            max_delay: config.max_delay,
+            max_failures: config.max_failures,
+        }
+    }
+
+    /// Exponential backoff with equal jitter: half fixed, half random.
+    fn backoff(&self, failures: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::from_config(&RestartConfig::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotState {
+    Running,
+    Backoff { failures: u32, delay: Duration },
+    Stopped,
+    Failed,
+}
+
+impl std::fmt::Display for BotState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BotState::Running => write!(f, "running"),
+            BotState::Backoff { failures, delay } => {
+                write!(f, "restarting in {delay:.1?} (failure #{failures})")
+            }
+            BotState::Stopped => write!(f, "stopped"),
+            BotState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// The latest state of each bot, by name.
+#[derive(Clone)]
+pub struct BotStates(Vec<(String, watch::Receiver<BotState>)>);
+
+impl BotStates {
+    pub(super) fn new(states: Vec<(String, watch::Receiver<BotState>)>) -> Self {
+        Self(states)
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, name: &str) -> Option<BotState> {
+        self.0
+            .iter()
+            .find(|(bot, _)| bot == name)
+            .map(|(_, state)| *state.borrow())
+    }
+
+    /// Resolves once every bot has been running for `period` without a break.
+    ///
+    /// Never resolves if the bots have stopped for good.
+    pub async fn running_for(&self, period: Duration) {
+        let mut states: Vec<_> = self.0.iter().map(|(_, state)| state.clone()).collect();
+        if states.is_empty() {
+            return tokio::time::sleep(period).await;
+        }
+        loop {
+            for state in &mut states {
+                if state.wait_for(|s| *s == BotState::Running).await.is_err() {
+                    return std::future::pending().await;
+                }
+            }
+            if !states
+                .iter_mut()
+                .all(|state| *state.borrow_and_update() == BotState::Running)
+            {
+                continue;
+            }
+
+            let change = select_all(states.iter_mut().map(|state| Box::pin(state.changed())));
+            tokio::select! {
+                _ = tokio::time::sleep(period) => return,
+                (result, _, _) = change => if result.is_err() {
+                    return std::future::pending().await;
+                },
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BotStates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "no bots");
+        }
+        for (i, (name, state)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name} {}", *state.borrow())?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `bot` until shutdown, restarting it whenever it exits on its own.
+///
+/// Gives up after `max_failures` consecutive failures and reports the last
+/// error through `failures_tx`. Every change of state is published on `state`.
+///
+/// A panic counts as a failure, which relies on the release profile
+/// unwinding: with `panic = "abort"` it would take the whole process down.
+pub async fn supervise(
+    bot: Arc<dyn Bot>,
+    policy: RestartPolicy,
+    mut shutdown: ShutdownSignal,
+    failures_tx: mpsc::Sender<anyhow::Error>,
+    state: watch::Sender<BotState>,
+) {
+    let name = bot.name().to_string();
+    let mut failures = 0;
+
+    loop {
+        report(&name, &state, BotState::Running);
+        let started = Instant::now();
+
+        let reason = match run_once(Arc::clone(&bot), &mut shutdown).await {
+            RunOutcome::Shutdown => break,
+            RunOutcome::Failed(reason) => reason,
+        };
+
+        if started.elapsed() >= STABLE_RUN {
+            failures = 0;
+        }
+        failures += 1;
+        tracing::error!(bot = %name, failures, "Bot exited: {reason:#}");
+
+        if failures > policy.max_failures {
+            report(&name, &state, BotState::Failed);
+            let error = reason.context(format!("bot {name} failed {failures} times in a row"));
+            let _ = failures_tx.send(error).await;
+            return;
+        }
+
+        let delay = policy.backoff(failures);
+        report(&name, &state, BotState::Backoff { failures, delay });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.recv() => break,
+        }
+    }
+
+    report(&name, &state, BotState::Stopped);
+}
+
+enum RunOutcome {
+    Shutdown,
+    Failed(anyhow::Error),
+}
+
+async fn run_once(bot: Arc<dyn Bot>, shutdown: &mut ShutdownSignal) -> RunOutcome {
+    let (tx, rx) = mpsc::channel(1);
+    let mut handle = tokio::spawn(async move { bot.run(rx).await });
+
+    tokio::select! {
+        result = &mut handle => RunOutcome::Failed(match result {
+            Ok(Ok(())) => anyhow::anyhow!("exited unexpectedly"),
+            Ok(Err(e)) => e,
+            Err(e) => anyhow::anyhow!("panicked: {e}"),
+        }),
+        _ = shutdown.recv() => {
+            let _ = tx.send(()).await;
+            if let Err(e) = handle.await {
+                tracing::error!("Bot panicked during shutdown: {e}");
+            }
+            RunOutcome::Shutdown
+        }
+    }
+}
+
+fn report(name: &str, tx: &watch::Sender<BotState>, state: BotState) {
+    tx.send_replace(state);
+    match state {
+        BotState::Failed => tracing::error!(bot = name, "Bot state: {state}"),
+        BotState::Backoff { .. } => tracing::warn!(bot = name, "Bot state: {state}"),
+        _ => tracing::info!(bot = name, "Bot state: {state}"),
+    }
+}
+
+/// Random factor in `[0, 1)` without pulling in an RNG crate.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    enum Step {
+        Fail,
+        Panic,
+        FailAfter(Duration),
+        Wait,
+    }
+
+    /// Takes the next step on each run and repeats the last one.
+    struct Scripted {
+        steps: Vec<Step>,
+        runs: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Bot for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        async fn run(&self, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
+            let run = self.runs.fetch_add(1, Ordering::Relaxed);
+            match self.steps[run.min(self.steps.len() - 1)] {
+                Step::Fail => anyhow::bail!("run {run} failed"),
+                Step::Panic => panic!("run {run} panicked"),
+                Step::FailAfter(after) => {
+                    tokio::time::sleep(after).await;
+                    anyhow::bail!("run {run} failed late")
+                }
+                Step::Wait => {
+                    shutdown.recv().await;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    struct Supervised {
+        bot: Arc<Scripted>,
+        shutdown: mpsc::Sender<()>,
+        failures: mpsc::Receiver<anyhow::Error>,
+        state: watch::Receiver<BotState>,
+        task: tokio::task::JoinHandle<()>,
+    }
+
+    fn policy(max_failures: u32) -> RestartPolicy {
+        RestartPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(8),
+            max_failures,
+        }
+    }
+
+    fn supervised(steps: Vec<Step>, max_failures: u32) -> Supervised {
+        let bot = Arc::new(Scripted {
+            steps,
+            runs: AtomicUsize::new(0),
+        });
+        let (shutdown, shutdown_rx) = mpsc::channel(1);
+        let (failures_tx, failures) = mpsc::channel(1);
+        let (state_tx, state) = watch::channel(BotState::Stopped);
+        let task = tokio::spawn(supervise(
+            bot.clone(),
+            policy(max_failures),
+            shutdown_rx,
+            failures_tx,
+            state_tx,
+        ));
+        Supervised {
+            bot,
+            shutdown,
+            failures,
+            state,
+            task,
+        }
+    }
+
+    impl Supervised {
+        fn runs(&self) -> usize {
+            self.bot.runs.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = policy(10);
+        for (failures, full) in [(1, 1), (2, 2), (3, 4), (4, 8), (5, 8), (40, 8)] {
+            let full = Duration::from_secs(full);
+            for _ in 0..20 {
+                let delay = policy.backoff(failures);
+                assert!(delay >= full / 2 && delay < full, "{failures}: {delay:?}");
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_an_unbroken_running_period() {
+        let (state, rx) = watch::channel(BotState::Running);
+        let states = BotStates::new(vec![("bot".to_string(), rx)]);
+        let start = Instant::now();
+        let healthy = tokio::spawn(async move {
+            states.running_for(Duration::from_secs(60)).await;
+            start.elapsed()
+        });
+
+        tokio::time::sleep(Duration::from_secs(50)).await;
+        state.send_replace(BotState::Backoff {
+            failures: 1,
+            delay: Duration::from_secs(10),
+        });
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        state.send_replace(BotState::Running);
+
+        assert_eq!(healthy.await.unwrap(), Duration::from_secs(120));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_the_failure_budget() {
+        let mut s = supervised(vec![Step::Fail], 2);
+
+        let error = s.failures.recv().await.unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            "bot scripted failed 3 times in a row: run 2 failed"
+        );
+        assert_eq!(s.runs(), 3);
+        s.task.await.unwrap();
+        assert_eq!(*s.state.borrow(), BotState::Failed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stable_run_resets_the_failure_count() {
+        let long = STABLE_RUN + Duration::from_secs(1);
+        let mut s = supervised(vec![Step::Fail, Step::FailAfter(long), Step::Fail], 1);
+
+        let error = s.failures.recv().await.unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            "bot scripted failed 2 times in a row: run 2 failed"
+        );
+        assert_eq!(s.runs(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restarts_a_panicking_bot() {
+        let mut s = supervised(vec![Step::Panic, Step::Wait], 1);
+
+        s.state
+            .wait_for(|state| matches!(state, BotState::Backoff { failures: 1, .. }))
+            .await
+            .unwrap();
+        s.state
+            .wait_for(|state| *state == BotState::Running)
+            .await
+            .unwrap();
+        assert_eq!(s.runs(), 2);
+
+        s.shutdown.send(()).await.unwrap();
+        s.task.await.unwrap();
+        assert_eq!(*s.state.borrow(), BotState::Stopped);
+        assert!(s.failures.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn states_follow_the_bots() {
+        let s = supervised(vec![Step::Wait], 1);
+        let states = BotStates::new(vec![("scripted".to_string(), s.state.clone())]);
+
+        let mut state = s.state.clone();
+        state
+            .wait_for(|state| *state == BotState::Running)
+            .await
+            .unwrap();
+        assert_eq!(states.get("scripted"), Some(BotState::Running));
+        assert_eq!(states.get("other"), None);
+        assert_eq!(states.to_string(), "scripted running");
+
+        s.shutdown.send(()).await.unwrap();
+        s.task.await.unwrap();
+        assert_eq!(states.to_string(), "scripted stopped");
+    }
+}
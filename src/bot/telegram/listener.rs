@@ -0,0 +1,97 @@
+//! An update listener that beats the heartbeat as it makes progress.
+//!
+//! The listener's stream is polled each time a `getUpdates` call returns,
+//! a retry delay ends or an update is handed on, and never while a call hangs
+//! or the dispatcher stops taking updates. Beating there, rather than on a
+//! timer, lets the watchdog see both.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use teloxide::stop::StopToken;
+use teloxide::types::AllowedUpdate;
+use teloxide::update_listeners::{AsUpdateStream, UpdateListener};
+
+use crate::domain::Heartbeat;
+
+pub struct BeatingListener<L> {
+    inner: L,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl<L> BeatingListener<L> {
+    pub fn new(inner: L, heartbeat: Option<Heartbeat>) -> Self {
+        Self { inner, heartbeat }
+    }
+}
+
+impl<L: UpdateListener> UpdateListener for BeatingListener<L> {
+    type Err = L::Err;
+
+    fn stop_token(&mut self) -> StopToken {
+        self.inner.stop_token()
+    }
+
+    fn hint_allowed_updates(&mut self, hint: &mut dyn Iterator<Item = AllowedUpdate>) {
+        self.inner.hint_allowed_updates(hint);
+    }
+}
+
+impl<'a, L: AsUpdateStream<'a>> AsUpdateStream<'a> for BeatingListener<L> {
+    type StreamErr = L::StreamErr;
+    type Stream = Beating<L::Stream>;
+
+    fn as_stream(&'a mut self) -> Self::Stream {
+        Beating {
+            inner: Box::pin(self.inner.as_stream()),
+            heartbeat: self.heartbeat.clone(),
+        }
+    }
+}
+
+/// Beats whenever it is polled, then polls `inner`.
+pub struct Beating<S> {
+    inner: Pin<Box<S>>,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl<S: Stream> Stream for Beating<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.beat();
+        }
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{StreamExt, stream};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn beats_only_when_polled() {
+        let heartbeat = Heartbeat::new(Duration::from_millis(20));
+        let mut stream = Beating {
+            inner: Box::pin(stream::iter([1, 2]).chain(stream::pending())),
+            heartbeat: Some(heartbeat.clone()),
+        };
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(heartbeat.is_stale());
+        assert_eq!(stream.next().await, Some(1));
+        assert!(!heartbeat.is_stale());
+
+        // Stuck: waiting on the stream is no progress.
+        assert_eq!(stream.next().await, Some(2));
+        assert!(futures::poll!(stream.next()).is_pending());
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(heartbeat.is_stale());
+    }
+}
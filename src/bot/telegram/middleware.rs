@@ -4,7 +4,10 @@ use std::sync::Arc;
 
 use teloxide::prelude::*;
 
-use crate::domain::messenger::{AuthFilter, UserId};
+use crate::domain::messenger::{AuthFilter, Role, UserId};
+
+use super::super::commands::Command;
+use super::super::handlers;
 
 pub fn auth_filter<A: AuthFilter + 'static>(auth: Arc<A>) -> impl Fn(Message) -> bool + Clone {
     move |msg: Message| {
@@ -15,13 +18,50 @@ pub fn auth_filter<A: AuthFilter + 'static>(auth: Arc<A>) -> impl Fn(Message) ->
     }
 }
 
+/// Matches commands sent by users without the role they need; `share` is
+/// the role `/share` needs.
+pub fn role_denied_filter<A: AuthFilter + 'static>(
+    auth: Arc<A>,
+    share: Role,
+) -> impl Fn(Command, Message) -> bool + Clone {
+    move |cmd: Command, msg: Message| {
+        let role = cmd.role(share);
+        !msg.from
+            .as_ref()
+            .is_some_and(|u| auth.has_role(UserId::new(u.id.0), role))
+    }
+}
+
+pub fn button_auth_filter<A: AuthFilter + 'static>(
+    auth: Arc<A>,
+) -> impl Fn(CallbackQuery) -> bool + Clone {
+    move |query: CallbackQuery| auth.is_allowed(UserId::new(query.from.id.0))
+}
+
+/// Matches buttons pressed by users who are not admins.
+pub fn button_admin_denied_filter<A: AuthFilter + 'static>(
+    auth: Arc<A>,
+) -> impl Fn(CallbackQuery) -> bool + Clone {
+    move |query: CallbackQuery| !auth.is_admin(UserId::new(query.from.id.0))
+}
+
 pub fn logging_filter() -> impl Fn(Message) -> bool + Clone {
     |msg: Message| {
         if let Some(user) = &msg.from {
             let username = user.username.as_deref().unwrap_or("unknown");
-            let text = msg.text().unwrap_or("[no text]");
+            let text = handlers::loggable_command(msg.text().unwrap_or("[no text]"));
             tracing::info!(user_id = user.id.0, username, "Command: {text}");
         }
         true
     }
 }
+
+pub fn button_logging_filter() -> impl Fn(CallbackQuery) -> bool + Clone {
+    |query: CallbackQuery| {
+        let user = &query.from;
+        let username = user.username.as_deref().unwrap_or("unknown");
+        let data = query.data.as_deref().unwrap_or("[no data]");
+        tracing::info!(user_id = user.id.0, username, "Button: {data}");
+        true
+    }
+}
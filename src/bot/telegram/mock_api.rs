@@ -0,0 +1,349 @@
+//! A fake Telegram Bot API server for end-to-end tests.
+//!
+//! Serves `getUpdates` from updates queued by the test and records every other
+//! call the bot makes, so tests can assert on the replies.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The username `getMe` reports; commands may be addressed to it.
+pub const BOT_USERNAME: &str = "tb_router_bot";
+/// How long to wait for the bot before failing a test.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Upper bound on long polling, so a stopping bot is not held up.
+const MAX_POLL: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Calls made by the update listener rather than by handlers.
+const PLUMBING: &[&str] = &["getMe", "getWebhookInfo", "deleteWebhook", "getUpdates"];
+
+/// A recorded Bot API call.
+///
+/// Multipart fields (file uploads) are flattened into `params` as strings.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub params: Value,
+}
+
+impl Request {
+    pub fn chat_id(&self) -> i64 {
+        chat_id(&self.params)
+    }
+
+    pub fn text(&self) -> &str {
+        self.params["text"].as_str().unwrap_or_default()
+    }
+}
+
+pub struct MockBotApi {
+    url: reqwest::Url,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    updates: Mutex<Vec<Value>>,
+    requests: Mutex<VecDeque<Request>>,
+    next_id: AtomicI64,
+}
+
+impl MockBotApi {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let state = Arc::new(State {
+            next_id: AtomicI64::new(1),
+            ..State::default()
+        });
+
+        let server = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, Arc::clone(&server)));
+            }
+        });
+
+        Self {
+            url: url.parse().unwrap(),
+            state,
+        }
+    }
+
+    pub fn url(&self) -> reqwest::Url {
+        self.url.clone()
+    }
+
+    /// Queues a private text message from `user_id`.
+    pub fn send_text(&self, user_id: u64, text: &str) {
+        let id = self.state.next_id();
+        self.state.updates.lock().unwrap().push(json!({
+            "update_id": id,
+            "message": {
+                "message_id": id,
+                "date": 1_760_880_000,
+                "chat": {"id": user_id, "type": "private", "first_name": "User"},
+                "from": user(user_id),
+                "text": text,
+            },
+        }));
+    }
+
+    /// Queues `user_id` pressing a button with `data` under one of the bot's
+    /// messages in their private chat.
+    pub fn press_button(&self, user_id: u64, data: &str) {
+        let id = self.state.next_id();
+        self.state.updates.lock().unwrap().push(json!({
+            "update_id": id,
+            "callback_query": {
+                "id": id.to_string(),
+                "from": user(user_id),
+                "message": {
+                    "message_id": id,
+                    "date": 1_760_880_000,
+                    "chat": {"id": user_id, "type": "private", "first_name": "User"},
+                    "from": {"id": 1, "is_bot": true, "first_name": "tb-router"},
+                    "text": "",
+                },
+                "chat_instance": user_id.to_string(),
+                "data": data,
+            },
+        }));
+    }
+
+    /// Waits for the next call a handler made.
+    pub async fn next_request(&self) -> Request {
+        self.poll_request(REPLY_TIMEOUT)
+            .await
+            .expect("the bot made no request in time")
+    }
+
+    /// Fails if a handler calls the API within `wait`.
+    pub async fn assert_silent(&self, wait: Duration) {
+        if let Some(request) = self.poll_request(wait).await {
+            panic!("unexpected {} {}", request.method, request.params);
+        }
+    }
+
+    async fn poll_request(&self, wait: Duration) -> Option<Request> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(request) = self.state.requests.lock().unwrap().pop_front() {
+                return Some(request);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl State {
+    fn next_id(&self) -> i64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn handle(&self, method: &str, params: Value) -> Value {
+        if !PLUMBING.contains(&method) {
+            self.requests.lock().unwrap().push_back(Request {
+                method: method.to_string(),
+                params: params.clone(),
+            });
+        }
+
+        match method {
+            "getMe" => ok(json!({
+                "id": 1,
+                "is_bot": true,
+                "first_name": "tb-router",
+                "username": BOT_USERNAME,
+                "can_join_groups": false,
+                "can_read_all_group_messages": false,
+                "supports_inline_queries": false,
+                "has_main_web_app": false,
+            })),
+            "getWebhookInfo" => ok(json!({
+                "url": "",
+                "has_custom_certificate": false,
+                "pending_update_count": 0,
+            })),
+            "deleteWebhook" | "answerCallbackQuery" | "deleteMessage" => ok(json!(true)),
+            "getUpdates" => ok(Value::Array(self.wait_for_updates(&params).await)),
+            "sendMessage" | "sendDocument" | "sendPhoto" | "editMessageText" => {
+                ok(self.message(&params))
+            }
+            _ => json!({
+                "ok": false,
+                "error_code": 404,
+                "description": "Not Found: method not found",
+            }),
+        }
+    }
+
+    /// Long polling: returns as soon as updates past `offset` are queued.
+    async fn wait_for_updates(&self, params: &Value) -> Vec<Value> {
+        let offset = params["offset"].as_i64().unwrap_or(0);
+        let timeout = Duration::from_secs(params["timeout"].as_u64().unwrap_or(0));
+        let deadline = Instant::now() + timeout.min(MAX_POLL);
+        loop {
+            let pending: Vec<Value> = self
+                .updates
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|u| u["update_id"].as_i64().unwrap_or(0) >= offset)
+                .cloned()
+                .collect();
+            if !pending.is_empty() || Instant::now() >= deadline {
+                return pending;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// The message the bot would get back for a send or edit.
+    fn message(&self, params: &Value) -> Value {
+        let message_id = params["message_id"]
+            .as_i64()
+            .unwrap_or_else(|| self.next_id());
+        json!({
+            "message_id": message_id,
+            "date": 1_760_880_000,
+            "chat": {"id": chat_id(params), "type": "private", "first_name": "User"},
+            "text": params["text"].as_str().unwrap_or_default(),
+        })
+    }
+}
+
+fn user(id: u64) -> Value {
+    json!({
+        "id": id,
+        "is_bot": false,
+        "first_name": "User",
+        "username": format!("user{id}"),
+    })
+}
+
+/// JSON requests carry numbers, multipart ones strings.
+fn chat_id(params: &Value) -> i64 {
+    match &params["chat_id"] {
+        Value::Number(n) => n.as_i64().unwrap_or_default(),
+        Value::String(s) => s.parse().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+fn ok(result: Value) -> Value {
+    json!({"ok": true, "result": result})
+}
+
+/// Answers one HTTP/1.1 request and closes the connection.
+async fn serve(mut stream: TcpStream, state: Arc<State>) {
+    let Some((head, body)) = read_request(&mut stream).await else {
+        return;
+    };
+    let path = head.split_whitespace().nth(1).unwrap_or("/");
+    let method = camel_case(path.rsplit('/').next().unwrap_or_default());
+    let params = match header(&head, "content-type") {
+        Some(t) if t.starts_with("multipart/form-data") => multipart_fields(t, &body),
+        _ => serde_json::from_slice(&body).unwrap_or(Value::Null),
+    };
+
+    let response = state.handle(&method, params).await.to_string();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<(String, Vec<u8>)> {
+    let mut data = Vec::new();
+    let mut buf = [0; 8192];
+    let head_end = loop {
+        if let Some(end) = find(&data, b"\r\n\r\n") {
+            break end;
+        }
+        let len = stream.read(&mut buf).await.ok().filter(|&n| n > 0)?;
+        data.extend_from_slice(&buf[..len]);
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).into_owned();
+    let length: usize = header(&head, "content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = data.split_off(head_end + 4);
+    while body.len() < length {
+        let len = stream.read(&mut buf).await.ok().filter(|&n| n > 0)?;
+        body.extend_from_slice(&buf[..len]);
+    }
+    Some((head, body))
+}
+
+/// Method names are case-insensitive; teloxide sends `SendMessage`.
+fn camel_case(method: &str) -> String {
+    let mut chars = method.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Collects `multipart/form-data` fields into a JSON object of strings.
+fn multipart_fields(content_type: &str, body: &[u8]) -> Value {
+    let Some((_, boundary)) = content_type.split_once("boundary=") else {
+        return Value::Null;
+    };
+    let body = String::from_utf8_lossy(body);
+    let delimiter = format!("--{}", boundary.trim_matches('"'));
+
+    let mut fields = serde_json::Map::new();
+    for part in body.split(delimiter.as_str()) {
+        let Some((headers, content)) = part.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let name = headers
+            .split("name=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next());
+        if let Some(name) = name {
+            let content = content.strip_suffix("\r\n").unwrap_or(content);
+            fields.insert(name.to_string(), Value::String(content.to_string()));
+        }
+    }
+
+    // Files are sent as separate parts referenced by `attach://<name>`.
+    let attached: Vec<(String, String)> = fields
+        .iter()
+        .filter_map(|(key, value)| {
+            let name = value.as_str()?.strip_prefix("attach://")?;
+            Some((key.clone(), name.to_string()))
+        })
+        .collect();
+    for (key, name) in attached {
+        if let Some(file) = fields.remove(&name) {
+            fields.insert(key, file);
+        }
+    }
+    Value::Object(fields)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -1,16 +1,35 @@
 //! Telegram bot implementation.
 
+mod listener;
 mod middleware;
+#[cfg(test)]
+mod mock_api;
+mod redact;
+#[cfg(test)]
+mod tests;
 
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
+use teloxide::dispatching::ShutdownToken;
 use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Recipient};
+use teloxide::update_listeners;
 
-use crate::domain::messenger::{AuthFilter, Bot};
-use crate::domain::{RouterInfo, ShutdownSignal};
+use crate::domain::messenger::{AuthFilter, Bot, Role};
+use crate::domain::{
+    ApplyTiming, Heartbeat, LogLevelControl, LogSource, RouterInfo, Secret, ShutdownSignal, Updater,
+};
 
+use listener::BeatingListener;
+use redact::RedactingErrorHandler;
+
+use super::airtime::{self, AirtimeLog};
 use super::commands::Command;
-use super::handlers;
+use super::handlers::{self, ClientArgs, Reply, ShareArgs};
+use super::history::BandHistory;
+use super::messages::{ADMIN_ONLY, ERROR_PREFIX, PASSWORD_NOT_DELETED, SHARE_USAGE};
 
 pub struct TelegramBot<R, A>
 where
@@ -18,8 +37,21 @@ where
     A: AuthFilter + 'static,
 {
     bot: teloxide::Bot,
+    token: Secret<String>,
     router: Arc<R>,
     auth: Arc<A>,
+    logs: Arc<dyn LogSource>,
+    log_level: Arc<dyn LogLevelControl>,
+    updater: Arc<dyn Updater>,
+    history: Arc<BandHistory>,
+    airtime: Arc<AirtimeLog>,
+    /// How often channel use is sampled in the background, if at all.
+    sample_every: Option<Duration>,
+    /// How long `/wifi_set` waits for the interfaces before undoing a change.
+    apply_timing: ApplyTiming,
+    /// Who may run `/share`.
+    share_role: Role,
+    heartbeat: Option<Heartbeat>,
 }
 
 impl<R, A> TelegramBot<R, A>
@@ -27,26 +59,102 @@ where
     R: RouterInfo + 'static,
     A: AuthFilter + 'static,
 {
-    pub fn new(token: &str, router: Arc<R>, auth: A) -> Self {
+    pub fn new(
+        token: Secret<String>,
+        router: Arc<R>,
+        auth: A,
+        logs: Arc<dyn LogSource>,
+        log_level: Arc<dyn LogLevelControl>,
+        updater: Arc<dyn Updater>,
+    ) -> Self {
         Self {
-            bot: teloxide::Bot::new(token),
+            bot: teloxide::Bot::new(token.expose()),
+            token,
             router,
             auth: Arc::new(auth),
+            logs,
+            log_level,
+            updater,
+            history: Arc::new(BandHistory::default()),
+            airtime: Arc::new(AirtimeLog::default()),
+            sample_every: None,
+            apply_timing: ApplyTiming::default(),
+            share_role: Role::default(),
+            heartbeat: None,
         }
     }
 
+    /// Samples channel use every `interval` (unless zero) and keeps `window`
+    /// of it.
+    pub fn with_airtime_sampling(mut self, interval: Duration, window: Duration) -> Self {
+        self.airtime = Arc::new(AirtimeLog::new(window));
+        self.sample_every = (!interval.is_zero()).then_some(interval);
+        self
+    }
+
+    pub fn with_apply_timing(mut self, timing: ApplyTiming) -> Self {
+        self.apply_timing = timing;
+        self
+    }
+
+    pub fn with_share_role(mut self, role: Role) -> Self {
+        self.share_role = role;
+        self
+    }
+
+    /// Beats `heartbeat` each time the update listener makes progress.
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Talks to another Bot API server, such as the tests' fake one.
+    #[cfg(test)]
+    fn with_api_url(mut self, url: reqwest::Url) -> Self {
+        self.bot = self.bot.set_api_url(url);
+        self
+    }
+
     fn build_handler(&self) -> teloxide::dispatching::UpdateHandler<teloxide::RequestError> {
         let auth = Arc::clone(&self.auth);
 
-        Update::filter_message()
+        let commands = Update::filter_message()
             .filter(middleware::logging_filter())
-            .filter(middleware::auth_filter(auth))
+            .filter(middleware::auth_filter(Arc::clone(&auth)))
             .filter_command::<Command>()
+            .branch(
+                dptree::filter(middleware::role_denied_filter(
+                    Arc::clone(&auth),
+                    self.share_role,
+                ))
+                .endpoint(telegram_admin_only),
+            )
             .branch(dptree::case![Command::Ping].endpoint(telegram_ping))
             .branch(dptree::case![Command::Help].endpoint(telegram_help))
             .branch(dptree::case![Command::Status].endpoint(telegram_status::<R>))
             .branch(dptree::case![Command::Wifi].endpoint(telegram_wifi::<R>))
-            .branch(dptree::case![Command::Clients].endpoint(telegram_clients::<R>))
+            .branch(dptree::case![Command::Clients(args)].endpoint(telegram_clients::<R>))
+            .branch(dptree::case![Command::Client(args)].endpoint(telegram_client::<R>))
+            .branch(dptree::case![Command::Scan(args)].endpoint(telegram_scan::<R>))
+            .branch(dptree::case![Command::Survey].endpoint(telegram_survey::<R>))
+            .branch(dptree::case![Command::Share(args)].endpoint(telegram_share::<R>))
+            .branch(dptree::case![Command::WifiSet(args)].endpoint(telegram_wifi_set::<R>))
+            .branch(dptree::case![Command::Logs(args)].endpoint(telegram_logs))
+            .branch(dptree::case![Command::Syslog(args)].endpoint(telegram_syslog))
+            .branch(dptree::case![Command::LogLevel(args)].endpoint(telegram_loglevel))
+            .branch(dptree::case![Command::Update(args)].endpoint(telegram_update));
+
+        // Every button changes the router, so only admins may press one.
+        let buttons = Update::filter_callback_query()
+            .filter(middleware::button_logging_filter())
+            .filter(middleware::button_auth_filter(Arc::clone(&auth)))
+            .branch(
+                dptree::filter(middleware::button_admin_denied_filter(auth))
+                    .endpoint(telegram_button_admin_only),
+            )
+            .endpoint(telegram_channel_button::<R>);
+
+        dptree::entry().branch(commands).branch(buttons)
     }
 }
 
@@ -56,20 +164,50 @@ where
     R: RouterInfo + 'static,
     A: AuthFilter + 'static,
 {
-    async fn run(self: Box<Self>, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn run(&self, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
         tracing::info!("Starting Telegram bot");
 
         let handler = self.build_handler();
-        let mut dispatcher = Dispatcher::builder(self.bot, handler)
-            .dependencies(dptree::deps![self.router])
+        let mut dispatcher = Dispatcher::builder(self.bot.clone(), handler)
+            .dependencies(dptree::deps![
+                Arc::clone(&self.router),
+                Arc::clone(&self.logs),
+                Arc::clone(&self.log_level),
+                Arc::clone(&self.updater),
+                Arc::clone(&self.history),
+                Arc::clone(&self.airtime),
+                self.apply_timing
+            ])
+            .error_handler(RedactingErrorHandler::new(
+                self.token.clone(),
+                "An error from the update handler",
+            ))
             .build();
+        let shutdown_token = dispatcher.shutdown_token();
+
+        let listener = update_listeners::polling_default(self.bot.clone()).await;
+        let listener = BeatingListener::new(listener, self.heartbeat.clone());
+        let error_handler =
+            RedactingErrorHandler::new(self.token.clone(), "An error from the update listener");
+        let dispatch = dispatcher.try_dispatch_with_listener(listener, error_handler);
+        tokio::pin!(dispatch);
 
         tokio::select! {
-            _ = dispatcher.dispatch() => {
+            _ = keep_sampling(self.router.as_ref(), &self.airtime, self.sample_every) => {}
+            result = &mut dispatch => {
+                result.map_err(|e| {
+                    let e = self.token.redact(&e.to_string());
+                    anyhow::anyhow!("failed to start Telegram dispatcher: {e}")
+                })?;
                 tracing::info!("Telegram dispatcher finished");
             }
             _ = shutdown.recv() => {
                 tracing::info!("Telegram bot received shutdown signal");
+                stop_dispatcher(&shutdown_token, dispatch).await;
             }
         }
 
@@ -78,6 +216,39 @@ where
     }
 }
 
+/// Never returns; records channel use every `interval`.
+async fn keep_sampling<R: RouterInfo>(router: &R, log: &AirtimeLog, interval: Option<Duration>) {
+    let Some(interval) = interval else {
+        return std::future::pending().await;
+    };
+
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        match router.wireless_status().await {
+            Ok(wireless) => {
+                for (radio, reading) in airtime::sample(router, &wireless, log).await {
+                    if let airtime::Reading::Failed(e) = reading {
+                        tracing::debug!("No channel use sample for {radio}: {e}");
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("No channel use samples: {e}"),
+        }
+    }
+}
+
+/// Lets in-flight handlers finish before the dispatcher is dropped.
+async fn stop_dispatcher<F: Future>(token: &ShutdownToken, dispatch: Pin<&mut F>) {
+    match token.shutdown() {
+        Ok(done) => {
+            futures::future::join(dispatch, done).await;
+        }
+        Err(_) => tracing::debug!("Telegram dispatcher was not running"),
+    }
+}
+
 async fn telegram_ping(bot: teloxide::Bot, msg: Message) -> Result<(), teloxide::RequestError> {
     bot.send_message(msg.chat.id, handlers::ping_response())
         .await?;
@@ -104,8 +275,20 @@ async fn telegram_wifi<R: RouterInfo>(
     bot: teloxide::Bot,
     msg: Message,
     router: Arc<R>,
+    airtime: Arc<AirtimeLog>,
+) -> Result<(), teloxide::RequestError> {
+    let response = handlers::wifi_response(router.as_ref(), &airtime).await;
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn telegram_survey<R: RouterInfo>(
+    bot: teloxide::Bot,
+    msg: Message,
+    router: Arc<R>,
+    airtime: Arc<AirtimeLog>,
 ) -> Result<(), teloxide::RequestError> {
-    let response = handlers::wifi_response(router.as_ref()).await;
+    let response = handlers::survey_response(router.as_ref(), &airtime).await;
     bot.send_message(msg.chat.id, response).await?;
     Ok(())
 }
@@ -114,8 +297,207 @@ async fn telegram_clients<R: RouterInfo>(
     bot: teloxide::Bot,
     msg: Message,
     router: Arc<R>,
+    args: ClientArgs,
+    history: Arc<BandHistory>,
+) -> Result<(), teloxide::RequestError> {
+    let response = handlers::clients_response(router.as_ref(), &args, &history).await;
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn telegram_client<R: RouterInfo>(
+    bot: teloxide::Bot,
+    msg: Message,
+    router: Arc<R>,
+    args: String,
+    history: Arc<BandHistory>,
+) -> Result<(), teloxide::RequestError> {
+    let response = handlers::client_response(router.as_ref(), &args, &history).await;
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn telegram_scan<R: RouterInfo>(
+    bot: teloxide::Bot,
+    msg: Message,
+    router: Arc<R>,
+    args: String,
+) -> Result<(), teloxide::RequestError> {
+    let (response, changes) = handlers::scan_response(router.as_ref(), &args).await;
+    let buttons = changes.iter().map(|change| {
+        let (label, data) = handlers::channel_button(change);
+        [InlineKeyboardButton::callback(label, data)]
+    });
+    let mut request = bot.send_message(msg.chat.id, response);
+    if !changes.is_empty() {
+        request = request.reply_markup(InlineKeyboardMarkup::new(buttons));
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn telegram_channel_button<R: RouterInfo>(
+    bot: teloxide::Bot,
+    query: CallbackQuery,
+    router: Arc<R>,
+) -> Result<(), teloxide::RequestError> {
+    bot.answer_callback_query(query.id.clone()).await?;
+    let Some(message) = &query.message else {
+        return Ok(());
+    };
+    let data = query.data.as_deref().unwrap_or_default();
+    let response = handlers::channel_button_response(router.as_ref(), data).await;
+    bot.send_message(message.chat().id, response).await?;
+    Ok(())
+}
+
+/// Sends the code here, or to the chat asked for and confirms it here.
+async fn telegram_share<R: RouterInfo>(
+    bot: teloxide::Bot,
+    msg: Message,
+    router: Arc<R>,
+    args: ShareArgs,
+) -> Result<(), teloxide::RequestError> {
+    let request = match args {
+        Ok(request) => request,
+        Err(e) => {
+            let response = format!("{ERROR_PREFIX}: {e}\n{SHARE_USAGE}");
+            bot.send_message(msg.chat.id, response).await?;
+            return Ok(());
+        }
+    };
+    let reply = handlers::share_response(router.as_ref(), &request.ssid).await;
+    let Some(chat) = request
+        .chat
+        .filter(|_| matches!(reply, Reply::Photo { .. }))
+    else {
+        return send_reply(&bot, msg.chat.id, reply).await;
+    };
+
+    let recipient = match chat.parse() {
+        Ok(id) => Recipient::Id(ChatId(id)),
+        Err(_) => Recipient::ChannelUsername(chat.clone()),
+    };
+    let response = match send_reply(&bot, recipient, reply).await {
+        Ok(()) => format!("Sent to {chat}"),
+        // Only API errors: the others can carry request URLs, token and all.
+        Err(teloxide::RequestError::Api(e)) => {
+            format!("{ERROR_PREFIX}: unable to send to {chat}: {e}")
+        }
+        Err(e) => return Err(e),
+    };
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+/// Deletes a command carrying a passphrase before anything else, so neither
+/// a slow change nor a failed reply leaves it in the chat.
+async fn telegram_wifi_set<R: RouterInfo>(
+    bot: teloxide::Bot,
+    msg: Message,
+    router: Arc<R>,
+    args: String,
+    timing: ApplyTiming,
+) -> Result<(), teloxide::RequestError> {
+    let mut not_deleted = false;
+    if handlers::carries_password(&args)
+        && let Err(e) = bot.delete_message(msg.chat.id, msg.id).await
+    {
+        tracing::warn!("Unable to delete a message with a WiFi passphrase: {e}");
+        not_deleted = true;
+    }
+
+    let response = handlers::wifi_set_response(router.as_ref(), &args, timing).await;
+    bot.send_message(msg.chat.id, response).await?;
+    if not_deleted {
+        bot.send_message(msg.chat.id, PASSWORD_NOT_DELETED).await?;
+    }
+    Ok(())
+}
+
+async fn telegram_logs(
+    bot: teloxide::Bot,
+    msg: Message,
+    args: String,
+    logs: Arc<dyn LogSource>,
+) -> Result<(), teloxide::RequestError> {
+    let reply = handlers::logs_response(logs.as_ref(), &args).await;
+    send_reply(&bot, msg.chat.id, reply).await
+}
+
+async fn telegram_syslog(
+    bot: teloxide::Bot,
+    msg: Message,
+    args: String,
+    logs: Arc<dyn LogSource>,
+) -> Result<(), teloxide::RequestError> {
+    let reply = handlers::syslog_response(logs.as_ref(), &args).await;
+    send_reply(&bot, msg.chat.id, reply).await
+}
+
+async fn telegram_loglevel(
+    bot: teloxide::Bot,
+    msg: Message,
+    args: String,
+    log_level: Arc<dyn LogLevelControl>,
+) -> Result<(), teloxide::RequestError> {
+    let response = handlers::loglevel_response(log_level.as_ref(), &args);
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+async fn telegram_update(
+    bot: teloxide::Bot,
+    msg: Message,
+    args: String,
+    updater: Arc<dyn Updater>,
 ) -> Result<(), teloxide::RequestError> {
-    let response = handlers::clients_response(router.as_ref()).await;
+    let (response, restart) = handlers::update_response(updater.as_ref(), &args).await;
     bot.send_message(msg.chat.id, response).await?;
+
+    if restart && let Err(e) = updater.restart() {
+        bot.send_message(msg.chat.id, format!("Restart it manually: {e}"))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn telegram_admin_only(
+    bot: teloxide::Bot,
+    msg: Message,
+) -> Result<(), teloxide::RequestError> {
+    bot.send_message(msg.chat.id, ADMIN_ONLY).await?;
+    Ok(())
+}
+
+async fn telegram_button_admin_only(
+    bot: teloxide::Bot,
+    query: CallbackQuery,
+) -> Result<(), teloxide::RequestError> {
+    bot.answer_callback_query(query.id)
+        .text(ADMIN_ONLY)
+        .show_alert(true)
+        .await?;
+    Ok(())
+}
+
+async fn send_reply(
+    bot: &teloxide::Bot,
+    chat: impl Into<Recipient>,
+    reply: Reply,
+) -> Result<(), teloxide::RequestError> {
+    match reply {
+        Reply::Text(text) => {
+            bot.send_message(chat, text).await?;
+        }
+        Reply::Document { file_name, content } => {
+            let file = InputFile::memory(content.into_bytes()).file_name(file_name);
+            bot.send_document(chat, file).await?;
+        }
+        Reply::Photo { caption, png } => {
+            let file = InputFile::memory(png).file_name("wifi.png");
+            bot.send_photo(chat, file).caption(caption).await?;
+        }
+    }
     Ok(())
 }
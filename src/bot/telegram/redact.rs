@@ -0,0 +1,62 @@
+//! Error logging with the bot token scrubbed out.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use teloxide::error_handlers::ErrorHandler;
+
+use crate::domain::Secret;
+
+/// Logs errors like teloxide's `LoggingErrorHandler`, minus the token.
+///
+/// Request URLs embed the token, and not every error path strips them.
+pub struct RedactingErrorHandler {
+    token: Secret<String>,
+    text: &'static str,
+}
+
+impl RedactingErrorHandler {
+    pub fn new(token: Secret<String>, text: &'static str) -> Arc<Self> {
+        Arc::new(Self { token, text })
+    }
+
+    fn describe<E: Debug>(&self, error: &E) -> String {
+        self.token.redact(&format!("{error:?}"))
+    }
+}
+
+impl<E: Debug> ErrorHandler<E> for RedactingErrorHandler {
+    fn handle_error(self: Arc<Self>, error: E) -> BoxFuture<'static, ()> {
+        let error = self.describe(&error);
+        tracing::error!("{}: {error}", self.text);
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::RequestError;
+    use teloxide::requests::Requester;
+
+    use super::*;
+
+    const TOKEN: &str = "123456:TEST-TOKEN";
+
+    #[tokio::test]
+    async fn strips_the_token_from_request_errors() {
+        let handler = RedactingErrorHandler::new(Secret::new(TOKEN.to_string()), "test");
+
+        let url = format!("http://127.0.0.1:1/bot{TOKEN}/GetMe");
+        let io = std::io::Error::other(format!("cannot reach {url}"));
+        let described = handler.describe(&RequestError::Io(Arc::new(io)));
+        assert!(!described.contains(TOKEN), "{described}");
+        assert!(described.contains("/bot[REDACTED]/GetMe"), "{described}");
+
+        let bot = teloxide::Bot::new(TOKEN).set_api_url("http://127.0.0.1:1".parse().unwrap());
+        let error = bot.get_me().await.unwrap_err();
+        let described = handler.describe(&error);
+        assert!(matches!(error, RequestError::Network(_)), "{described}");
+        assert!(!described.contains(TOKEN), "{described}");
+    }
+}
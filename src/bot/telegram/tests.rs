@@ -0,0 +1,476 @@
+//! End-to-end tests against the fake Bot API server and the fixture router.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::domain::messenger::Bot;
+use crate::domain::types::ShutdownSender;
+use crate::domain::{
+    ApplyTiming, LogError, LogLevelControl, LogLevelState, LogQuery, LogSource, Secret,
+    UpdateError, UpdateOutcome, Updater,
+};
+use crate::infrastructure::router::FixtureRouter;
+
+use super::super::auth::UserWhitelist;
+use super::super::messages::{ADMIN_ONLY, PONG};
+use super::TelegramBot;
+use super::mock_api::{BOT_USERNAME, MockBotApi, Request};
+
+const TOKEN: &str = "123456:TEST-TOKEN";
+const ADMIN: u64 = 1;
+const USER: u64 = 2;
+const STRANGER: u64 = 3;
+const SILENCE: Duration = Duration::from_millis(300);
+
+struct Harness {
+    api: MockBotApi,
+    shutdown: ShutdownSender,
+    bot: JoinHandle<anyhow::Result<()>>,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        Self::with_router_latency(Duration::ZERO).await
+    }
+
+    async fn with_router_latency(latency: Duration) -> Self {
+        let api = MockBotApi::start().await;
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/router");
+        let router = FixtureRouter::new(&fixtures, latency).unwrap();
+        let auth = UserWhitelist::from_iter([ADMIN, USER]).with_admins([ADMIN]);
+
+        let bot = TelegramBot::new(
+            Secret::new(TOKEN.to_string()),
+            Arc::new(router),
+            auth,
+            Arc::new(FakeLogs),
+            Arc::new(FakeLogLevel::default()),
+            Arc::new(FakeUpdater),
+        )
+        .with_api_url(api.url())
+        .with_apply_timing(ApplyTiming {
+            settle: Duration::ZERO,
+            timeout: Duration::from_millis(100),
+        });
+
+        let (shutdown, shutdown_rx) = mpsc::channel(1);
+        let bot = tokio::spawn(async move { bot.run(shutdown_rx).await });
+        Self { api, shutdown, bot }
+    }
+
+    /// Sends `text` as `user` and returns the bot's single reply.
+    async fn ask(&self, user: u64, text: &str) -> Request {
+        self.api.send_text(user, text);
+        let reply = self.api.next_request().await;
+        assert_eq!(reply.chat_id(), user as i64, "{reply:?}");
+        reply
+    }
+
+    async fn ask_text(&self, user: u64, text: &str) -> String {
+        let reply = self.ask(user, text).await;
+        assert_eq!(reply.method, "sendMessage", "{reply:?}");
+        reply.text().to_string()
+    }
+
+    async fn stop(self) {
+        let _ = self.shutdown.send(()).await;
+        tokio::time::timeout(Duration::from_secs(5), self.bot)
+            .await
+            .expect("the bot did not stop in time")
+            .unwrap()
+            .unwrap();
+    }
+}
+
+struct FakeLogs;
+
+#[async_trait]
+impl LogSource for FakeLogs {
+    async fn bot_log(&self, query: &LogQuery) -> Result<Vec<String>, LogError> {
+        let text: String = (0..1000).map(|i| format!("bot line {i:03}\n")).collect();
+        Ok(query.select(&text))
+    }
+
+    async fn system_log(&self, _query: &LogQuery) -> Result<Vec<String>, LogError> {
+        Err(LogError::Timeout { cmd: "logread" })
+    }
+}
+
+#[derive(Default)]
+struct FakeLogLevel {
+    filter: Mutex<Option<String>>,
+}
+
+impl LogLevelControl for FakeLogLevel {
+    fn state(&self) -> LogLevelState {
+        let filter = self.filter.lock().unwrap().clone();
+        LogLevelState {
+            filter: filter.unwrap_or_else(|| "info".to_string()),
+            configured: "info".to_string(),
+            revert_in: None,
+        }
+    }
+
+    fn set(&self, filter: &str) -> Result<LogLevelState, LogError> {
+        *self.filter.lock().unwrap() = Some(filter.to_string());
+        Ok(self.state())
+    }
+
+    fn reset(&self) -> Result<LogLevelState, LogError> {
+        *self.filter.lock().unwrap() = None;
+        Ok(self.state())
+    }
+}
+
+struct FakeUpdater;
+
+#[async_trait]
+impl Updater for FakeUpdater {
+    async fn update(&self) -> Result<UpdateOutcome, UpdateError> {
+        Ok(UpdateOutcome::UpToDate {
+            version: "0.1.0".to_string(),
+        })
+    }
+
+    async fn rollback(&self) -> Result<UpdateOutcome, UpdateError> {
+        Err(UpdateError::NoBackup)
+    }
+
+    fn restart(&self) -> Result<(), UpdateError> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn answers_basic_commands() {
+    let bot = Harness::start().await;
+
+    assert_eq!(bot.ask_text(USER, "/ping").await, PONG);
+    assert_eq!(
+        bot.ask_text(USER, &format!("/ping@{BOT_USERNAME}")).await,
+        PONG
+    );
+    assert!(bot.ask_text(USER, "/help").await.contains("/clients"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn ignores_users_not_on_the_whitelist() {
+    let bot = Harness::start().await;
+
+    bot.api.send_text(STRANGER, "/ping");
+    bot.api.send_text(STRANGER, "/logs");
+    bot.api.assert_silent(SILENCE).await;
+    assert_eq!(bot.ask_text(USER, "/ping").await, PONG);
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn renders_router_data() {
+    let bot = Harness::start().await;
+
+    let status = bot.ask_text(USER, "/status").await;
+    assert!(status.starts_with("[OpenWrt]\nOpenWrt | 23.05.5\nGL.iNet GL-MT3000"));
+    assert!(status.contains("Uptime: 3d 4h 0m"), "{status}");
+
+    let wifi = bot.ask_text(USER, "/wifi").await;
+    assert!(
+        wifi.contains("[ON] OpenWrt (2.4 GHz)\n    Radio: radio0 | Channel: 1 | 20 MHz | WiFi 6")
+    );
+    assert!(wifi.contains("[ON] OpenWrt (5 GHz)\n    Radio: radio1 | Channel: 36 | 80 MHz"));
+
+    let clients = bot.ask_text(USER, "/clients").await;
+    assert!(clients.contains("Total: 3"), "{clients}");
+    assert!(clients.contains("3c:22:fb:8a:11:d4"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn filters_and_sorts_clients() {
+    let bot = Harness::start().await;
+
+    let clients = bot.ask_text(USER, "/clients band=5 sort=name").await;
+    assert!(
+        clients.contains("Total: 2\nOpenWrt (5 GHz) - 2 devices"),
+        "{clients}"
+    );
+    let first = clients.find("3c:22:fb:8a:11:d4").unwrap();
+    assert!(first < clients.find("f0:2f:74:90:c3:7b").unwrap());
+    assert!(!clients.contains("a4:83:e7:1c:52:0e"));
+
+    let clients = bot.ask_text(USER, "/clients mode=wifi4").await;
+    assert!(clients.contains("Total: 1\nOpenWrt (2.4 GHz)"), "{clients}");
+    let clients = bot.ask_text(USER, "/clients ssid=Guest").await;
+    assert!(clients.ends_with("No matching devices"), "{clients}");
+
+    let error = bot.ask_text(USER, "/clients band=9g").await;
+    assert!(error.starts_with("Error: unknown band '9g'\nUsage: /clients"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn shows_one_client_in_detail() {
+    let bot = Harness::start().await;
+
+    let by_name = bot.ask_text(USER, "/client Pixel-7").await;
+    assert!(
+        by_name.starts_with("Client 3c:22:fb:8a:11:d4\nName: pixel-7"),
+        "{by_name}"
+    );
+    assert!(
+        by_name.contains("Connected: 2h 13m | Inactive: 40 ms"),
+        "{by_name}"
+    );
+    assert!(
+        by_name.contains("Bands: OpenWrt 5 GHz (0m ago)"),
+        "{by_name}"
+    );
+
+    let by_mac = bot.ask_text(USER, "/client A4-83-E7-1C-52-0E").await;
+    assert!(
+        by_mac.contains("Network: OpenWrt (2.4 GHz) on phy0-ap0"),
+        "{by_mac}"
+    );
+    assert!(by_mac.contains("Roaming: none"), "{by_mac}");
+
+    assert_eq!(
+        bot.ask_text(USER, "/client printer").await,
+        "00:1b:a9:4c:77:01: not connected\nName: printer\nIP: 192.168.1.40"
+    );
+    assert!(
+        bot.ask_text(USER, "/client toaster")
+            .await
+            .starts_with("Error: no client")
+    );
+    assert!(
+        bot.ask_text(USER, "/client")
+            .await
+            .starts_with("Usage: /client")
+    );
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn recommends_channels_and_applies_them_for_admins() {
+    let bot = Harness::start().await;
+
+    let reply = bot.ask(USER, "/scan").await;
+    let scan = reply.text();
+    assert!(
+        scan.contains(
+            "radio0 (2.4 GHz), channel 1 | 20 MHz: 4 networks\n  \
+             1: -48 dBm FRITZ!Box 7530 XY, -49 dBm (hidden)\n"
+        ),
+        "{scan}"
+    );
+    assert!(
+        scan.contains(
+            "  Best: 6 (1 networks, interference 14) instead of 1 (2 networks, interference 93)"
+        ),
+        "{scan}"
+    );
+    assert!(
+        scan.contains("  Best: 149 (0 networks, interference 0) instead of 36"),
+        "{scan}"
+    );
+    let buttons = &reply.params["reply_markup"]["inline_keyboard"];
+    assert_eq!(buttons[1][0]["text"], "Use channel 149 on radio1");
+    let data = buttons[1][0]["callback_data"].as_str().unwrap();
+
+    bot.api.press_button(USER, data);
+    let denied = bot.api.next_request().await;
+    assert_eq!(denied.method, "answerCallbackQuery");
+    assert_eq!(denied.params["text"], ADMIN_ONLY);
+    bot.api.assert_silent(SILENCE).await;
+
+    bot.api.press_button(ADMIN, data);
+    assert_eq!(bot.api.next_request().await.method, "answerCallbackQuery");
+    let applied = bot.api.next_request().await;
+    assert_eq!(
+        applied.text(),
+        "radio1 moves to channel 149; its clients reconnect in a few seconds"
+    );
+
+    bot.api.press_button(ADMIN, "channel:radio1:140");
+    bot.api.next_request().await;
+    assert_eq!(
+        bot.api.next_request().await.text(),
+        "Error: channel 140 does not suit radio1"
+    );
+
+    let scan = bot.ask_text(USER, "/scan 2.4").await;
+    assert!(!scan.contains("radio1"), "{scan}");
+    assert!(
+        bot.ask_text(USER, "/scan radio9")
+            .await
+            .starts_with("Error: no radio 'radio9'")
+    );
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn changes_access_points_and_deletes_passphrases() {
+    let bot = Harness::start().await;
+
+    let denied = bot
+        .ask_text(USER, "/wifi_set OpenWrt encryption wpa3")
+        .await;
+    assert_eq!(denied, ADMIN_ONLY);
+
+    // The passphrase leaves the chat before the change is even made.
+    bot.api
+        .send_text(ADMIN, r#"/wifi_set OpenWrt password "correct horse""#);
+    let deleted = bot.api.next_request().await;
+    assert_eq!(deleted.method, "deleteMessage");
+    assert_eq!(deleted.chat_id(), ADMIN as i64);
+    assert_eq!(
+        bot.api.next_request().await.text(),
+        "Passphrase of 'OpenWrt' changed on phy0-ap0, phy1-ap0; \
+         clients have to reconnect with the new one"
+    );
+
+    bot.api.send_text(ADMIN, "/wifi_set OpenWrt password short");
+    assert_eq!(bot.api.next_request().await.method, "deleteMessage");
+    assert_eq!(
+        bot.api.next_request().await.text(),
+        "Error: WPA2/WPA3 passphrases are 8 to 63 printable ASCII characters"
+    );
+
+    let changed = bot
+        .ask_text(ADMIN, "/wifi_set OpenWrt encryption wpa3")
+        .await;
+    assert!(
+        changed.starts_with("'OpenWrt' now uses sae on"),
+        "{changed}"
+    );
+    bot.api.assert_silent(SILENCE).await;
+    assert!(
+        bot.ask_text(ADMIN, "/wifi_set Guest ssid Visitors")
+            .await
+            .starts_with("Error: no access point is named 'Guest'")
+    );
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn shares_wifi_as_a_qr_code() {
+    let bot = Harness::start().await;
+
+    let reply = bot.ask(USER, "/share openwrt").await;
+    assert_eq!(reply.method, "sendPhoto");
+    assert_eq!(reply.params["caption"], "Scan to join OpenWrt");
+    let photo = reply.params["photo"].as_str().unwrap();
+    assert!(photo.contains("PNG"), "{photo:?}");
+
+    assert_eq!(
+        bot.ask_text(USER, "/share OpenWrt -100500").await,
+        ADMIN_ONLY
+    );
+    assert!(
+        bot.ask_text(USER, "/share Guest")
+            .await
+            .starts_with("Error: no access point is named 'Guest'")
+    );
+
+    bot.api.send_text(ADMIN, "/share OpenWrt -100500");
+    let sent = bot.api.next_request().await;
+    assert_eq!(
+        (sent.method.as_str(), sent.chat_id()),
+        ("sendPhoto", -100500)
+    );
+    assert_eq!(bot.api.next_request().await.text(), "Sent to -100500");
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn reports_channel_use() {
+    let bot = Harness::start().await;
+
+    let wifi = bot.ask_text(USER, "/wifi").await;
+    assert!(!wifi.contains("Airtime"), "{wifi}");
+
+    let survey = bot.ask_text(USER, "/survey").await;
+    assert!(
+        survey.starts_with(
+            "Channel use\n\n\
+             radio0 (2.4 GHz), channel 1: busy 38% | rx 24% | tx 4% | noise -91 dBm\n  \
+             Peak: 38% 0m ago | Average: 38% over 1 samples\n\n\
+             radio1 (5 GHz), channel 36: busy 11% | rx 6% | tx 3% | noise -95 dBm"
+        ),
+        "{survey}"
+    );
+
+    let wifi = bot.ask_text(USER, "/wifi").await;
+    assert!(
+        wifi.contains("Channel: 1 | 20 MHz | WiFi 6\n    Airtime: busy 38% | rx 24% | tx 4%"),
+        "{wifi}"
+    );
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn admin_commands_need_an_admin() {
+    let bot = Harness::start().await;
+
+    for command in ["/logs", "/syslog", "/loglevel debug", "/update"] {
+        assert_eq!(bot.ask_text(USER, command).await, ADMIN_ONLY, "{command}");
+    }
+    bot.api.assert_silent(SILENCE).await;
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn admins_run_admin_commands() {
+    let bot = Harness::start().await;
+
+    assert_eq!(
+        bot.ask_text(ADMIN, "/logs 2").await,
+        "bot line 998\nbot line 999"
+    );
+    assert!(bot.ask_text(ADMIN, "/syslog").await.starts_with("Error: "));
+    assert!(
+        bot.ask_text(ADMIN, "/loglevel debug teloxide=warn")
+            .await
+            .starts_with("Log filter: debug,teloxide=warn\nConfigured: info")
+    );
+    assert_eq!(
+        bot.ask_text(ADMIN, "/update").await,
+        "Already running 0.1.0"
+    );
+
+    let reply = bot.ask(ADMIN, "/logs 1000").await;
+    assert_eq!(reply.method, "sendDocument");
+    let document = reply.params["document"].as_str().unwrap();
+    assert!(document.starts_with("bot line 000\n"));
+    assert!(document.ends_with("bot line 999"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+async fn shutdown_lets_running_commands_finish() {
+    let bot = Harness::with_router_latency(Duration::from_millis(500)).await;
+
+    bot.api.send_text(USER, "/wifi");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    bot.shutdown.send(()).await.unwrap();
+
+    let reply = bot.api.next_request().await;
+    assert!(reply.text().contains("Radio: radio0"), "{reply:?}");
+    bot.stop().await;
+}
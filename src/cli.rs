@@ -0,0 +1,118 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: tb-router [--config <path>] [command]
+
+Commands:
+  run                Run the bot (default)
+  check-config       Validate the configuration and exit
+  install-service    Write the procd init script to /etc/init.d/tb-router
+
+Options:
+  -c, --config <path>    TOML config file (default: /etc/tb-router/config.toml)
+  -h, --help             Show this help
+  -V, --version          Show version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    CheckConfig,
+    InstallService,
+    Help,
+    Version,
+}
+
+/// Bad arguments; shown together with the usage text.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}\n\n{USAGE}")]
+pub struct UsageError(String);
+
+#[derive(Debug)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub command: Command,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, UsageError> {
+        let mut args = args.into_iter();
+        let mut config = None;
+        let mut command = None;
+
+        while let Some(arg) = args.next() {
+            let parsed = match arg.as_str() {
+                "-c" | "--config" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| UsageError(format!("{arg} requires a path")))?;
+                    config = Some(PathBuf::from(path));
+                    continue;
+                }
+                s if s.starts_with("--config=") => {
+                    config = Some(PathBuf::from(&s["--config=".len()..]));
+                    continue;
+                }
+                "-h" | "--help" => Command::Help,
+                "-V" | "--version" => Command::Version,
+                "run" => Command::Run,
+                "check-config" => Command::CheckConfig,
+                "install-service" => Command::InstallService,
+                other => return Err(UsageError(format!("unexpected argument '{other}'"))),
+            };
+
+            if command.replace(parsed).is_some() {
+                return Err(UsageError("only one command may be given".to_string()));
+            }
+        }
+
+        Ok(Self {
+            config,
+            command: command.unwrap_or(Command::Run),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, UsageError> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_commands_and_config_paths() {
+        let cli = parse(&[]).unwrap();
+        assert_eq!(cli.command, Command::Run);
+        assert_eq!(cli.config, None);
+
+        let cli = parse(&["-c", "/tmp/a.toml", "check-config"]).unwrap();
+        assert_eq!(cli.command, Command::CheckConfig);
+        assert_eq!(cli.config, Some(PathBuf::from("/tmp/a.toml")));
+
+        let cli = parse(&["install-service", "--config=/tmp/b.toml"]).unwrap();
+        assert_eq!(cli.command, Command::InstallService);
+        assert_eq!(cli.config, Some(PathBuf::from("/tmp/b.toml")));
+
+        assert_eq!(parse(&["--help"]).unwrap().command, Command::Help);
+        assert_eq!(parse(&["-V"]).unwrap().command, Command::Version);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for (args, message) in [
+            (&["--config"][..], "--config requires a path"),
+            (&["start"][..], "unexpected argument 'start'"),
+            (
+                &["run", "check-config"][..],
+                "only one command may be given",
+            ),
+        ] {
+            let error = parse(args).unwrap_err().to_string();
+            assert!(error.starts_with(message), "{error}");
+            assert!(error.ends_with(USAGE));
+        }
+    }
+}
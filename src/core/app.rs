@@ -1,44 +1,204 @@
 //! Application orchestration.
 
-use crate::bot::BotManager;
-use crate::domain::SignalHandler;
+use futures::FutureExt;
+use futures::future::{self, BoxFuture};
+use tokio::task::{JoinError, JoinHandle};
+
+use crate::bot::{BotManager, BotStates};
+use crate::domain::signal::SignalEvent;
 use crate::domain::types::{ShutdownSender, ShutdownSignal};
-use crate::infrastructure::{Config, LogGuard};
+use crate::domain::{Heartbeat, SignalHandler};
+use crate::infrastructure::{Config, LogGuard, Probation};
+
+use super::{Loader, Services, watchdog};
 
-pub struct App<S: SignalHandler> {
-    _log_guard: LogGuard,
+pub struct App<S: SignalHandler, L: Loader> {
+    log_guard: LogGuard,
     signal_handler: S,
-    bot_manager: BotManager,
+    loader: L,
+    config: Config,
+    services: Services,
+    probation: Option<Probation>,
 }
 
-impl<S: SignalHandler> App<S> {
-    pub fn new(
-        config: &Config,
-        signal_handler: S,
-        bot_manager: BotManager,
-    ) -> anyhow::Result<Self> {
-        let log_guard = crate::infrastructure::init_logging(config)?;
+impl<S: SignalHandler, L: Loader> App<S, L> {
+    pub fn new(loader: L, signal_handler: S) -> anyhow::Result<Self> {
+        let config = loader.load_config()?;
+        let log_guard = crate::infrastructure::init_logging(&config)?;
+        log_warnings(&config);
+
+        let heartbeat = Heartbeat::new(config.service.heartbeat);
+        watchdog::spawn(heartbeat.clone())?;
+        let services = Services {
+            log_level: log_guard.level().clone(),
+            heartbeat,
+        };
 
         Ok(Self {
-            _log_guard: log_guard,
+            log_guard,
             signal_handler,
-            bot_manager,
+            loader,
+            config,
+            services,
+            probation: None,
         })
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
+    /// Clears the update `probation` once the bots have run long enough.
+    pub fn with_probation(mut self, probation: Option<Probation>) -> Self {
+        self.probation = probation;
+        self
+    }
+
+    /// Runs until a shutdown signal arrives or a bot gives up.
+    ///
+    /// A bot that exhausts its restart budget makes this return an error, so
+    /// the process exits non-zero and the service manager can restart it.
+    pub async fn run(mut self) -> anyhow::Result<()> {
         tracing::info!("Application started");
 
+        let mut running = RunningBots::start(self.loader.build_bots(&self.config, &self.services)?);
+        let mut health = self.health_check(&running);
+
+        let result = loop {
+            tokio::select! {
+                event = self.signal_handler.next_event() => match event {
+                    SignalEvent::Shutdown(kind) => {
+                        tracing::info!("Received {kind}, stopping...");
+                        break running.stop().await;
+                    }
+                    SignalEvent::Reload => {
+                        tracing::info!("Received SIGHUP, reloading configuration...");
+                        running = self.reload(running).await;
+                        health = self.health_check(&running);
+                    }
+                    SignalEvent::MoreVerbose => self.bump_log_level(1),
+                    SignalEvent::LessVerbose => self.bump_log_level(-1),
+                },
+                result = running.finished() => break result,
+                () = &mut health => {
+                    if let Some(probation) = self.probation.take() {
+                        probation.passed().await;
+                    }
+                    health = future::pending().boxed();
+                }
+            }
+        };
+
+        if let (Ok(()), Some(probation)) = (&result, self.probation.take()) {
+            probation.interrupted().await;
+        }
+
+        match &result {
+            Ok(()) => tracing::info!("Shutdown complete"),
+            Err(e) => tracing::error!("Giving up: {e:#}"),
+        }
+        result
+    }
+
+    /// Swaps in a freshly loaded configuration and bot set.
+    ///
+    /// Everything is built and validated before the running bots are touched,
+    /// so an invalid config leaves the current one in place. Running bots are
+    /// stopped gracefully and finish their in-flight commands first. Once they
+    /// are stopped the new bots start whatever else goes wrong. Log outputs
+    /// keep their startup settings, with a warning for each change set aside.
+    async fn reload(&mut self, running: RunningBots) -> RunningBots {
+        let (config, bots) = match self.prepare_reload() {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                tracing::error!("Reload rejected, keeping current configuration: {e:#}");
+                return running;
+            }
+        };
+
+        if let Err(e) = running.stop().await {
+            tracing::error!("Old bots did not stop cleanly: {e:#}");
+        }
+        if let Err(e) = self.log_guard.reload_filter(&config) {
+            tracing::error!("Keeping the current log filter: {e:#}");
+        }
+        self.services
+            .heartbeat
+            .set_timeout(config.service.heartbeat);
+        self.services.heartbeat.beat();
+        self.config = config;
+
+        tracing::info!("Configuration reloaded");
+        RunningBots::start(bots)
+    }
+
+    /// Resolves once every bot has run for `update.health_timeout`, or never
+    /// if no update is on probation.
+    fn health_check(&self, running: &RunningBots) -> BoxFuture<'static, ()> {
+        if self.probation.is_none() {
+            return future::pending().boxed();
+        }
+        let states = running.states.clone();
+        let period = self.config.update.health_timeout;
+        async move { states.running_for(period).await }.boxed()
+    }
+
+    fn bump_log_level(&self, steps: isize) {
+        match self.services.log_level.bump(steps) {
+            Ok(state) => match state.revert_in {
+                Some(after) => tracing::info!(
+                    "Log filter set to '{}' for {:.0}s",
+                    state.filter,
+                    after.as_secs_f64()
+                ),
+                None => tracing::info!("Log filter set to '{}'", state.filter),
+            },
+            Err(e) => tracing::error!("{e}"),
+        }
+    }
+
+    fn prepare_reload(&self) -> anyhow::Result<(Config, BotManager)> {
+        let mut config = self.loader.load_config()?;
+        for key in config.log.keep_startup_settings(&self.config.log) {
+            config
+                .warnings
+                .push(format!("{key} takes effect after a restart"));
+        }
+        let bots = self.loader.build_bots(&config, &self.services)?;
+        log_warnings(&config);
+        Ok((config, bots))
+    }
+}
+
+struct RunningBots {
+    handle: JoinHandle<anyhow::Result<()>>,
+    shutdown_tx: ShutdownSender,
+    states: BotStates,
+}
+
+impl RunningBots {
+    fn start(manager: BotManager) -> Self {
         let (shutdown_tx, shutdown_rx) = create_shutdown_channel();
-        let handle = tokio::spawn(self.bot_manager.run_all(shutdown_rx));
+        let states = manager.states();
+        let handle = tokio::spawn(manager.run_all(shutdown_rx));
+        Self {
+            handle,
+            shutdown_tx,
+            states,
+        }
+    }
 
-        wait_for_signal(&self.signal_handler).await;
+    /// Resolves only if the bots stop on their own.
+    async fn finished(&mut self) -> anyhow::Result<()> {
+        flatten((&mut self.handle).await)
+    }
 
-        let _ = shutdown_tx.send(()).await;
-        await_completion(handle).await;
+    async fn stop(self) -> anyhow::Result<()> {
+        tracing::info!("Stopping bots: {}", self.states);
+        let _ = self.shutdown_tx.send(()).await;
+        flatten(self.handle.await)
+    }
+}
 
-        tracing::info!("Shutdown complete");
-        Ok(())
+fn log_warnings(config: &Config) {
+    for warning in &config.warnings {
+        tracing::warn!("Config: {warning}");
     }
 }
 
@@ -46,14 +206,167 @@ fn create_shutdown_channel() -> (ShutdownSender, ShutdownSignal) {
     tokio::sync::mpsc::channel(1)
 }
 
-async fn wait_for_signal<S: SignalHandler>(handler: &S) {
-    let signal = handler.wait_for_shutdown().await;
-    tracing::info!("Received {signal}, stopping...");
+fn flatten(result: Result<anyhow::Result<()>, JoinError>) -> anyhow::Result<()> {
+    result.map_err(|e| anyhow::anyhow!("bot manager panicked: {e}"))?
 }
 
-async fn await_completion(handle: tokio::task::JoinHandle<()>) {
-    match handle.await {
-        Ok(()) => tracing::info!("All bots stopped"),
-        Err(e) => tracing::error!("Bot manager panicked: {e}"),
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::domain::messenger::Bot;
+    use crate::infrastructure::config::LogOutput;
+
+    use super::*;
+
+    type Events = Arc<Mutex<Vec<String>>>;
+
+    struct NoSignals;
+
+    #[async_trait]
+    impl SignalHandler for NoSignals {
+        async fn next_event(&self) -> SignalEvent {
+            std::future::pending().await
+        }
+    }
+
+    /// Numbers each bot set it builds; `reject` fails the next config load
+    /// and `extra` is appended to the config it loads.
+    #[derive(Default)]
+    struct FakeLoader {
+        reject: AtomicBool,
+        built: AtomicU32,
+        events: Events,
+        extra: Mutex<String>,
+    }
+
+    impl Loader for FakeLoader {
+        fn load_config(&self) -> anyhow::Result<Config> {
+            if self.reject.load(Ordering::Relaxed) {
+                anyhow::bail!("invalid config");
+            }
+            let extra = self.extra.lock().unwrap();
+            Ok(Config::from_toml(&format!(
+                "[telegram]\ntoken = \"123:abc\"\nallowed_users = [1]\n{extra}"
+            ))?)
+        }
+
+        fn build_bots(&self, _: &Config, _: &Services) -> anyhow::Result<BotManager> {
+            let id = self.built.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut manager = BotManager::default();
+            manager.add(Probe {
+                id,
+                events: Arc::clone(&self.events),
+            });
+            Ok(manager)
+        }
+    }
+
+    struct Probe {
+        id: u32,
+        events: Events,
+    }
+
+    #[async_trait]
+    impl Bot for Probe {
+        fn name(&self) -> &str {
+            "probe"
+        }
+
+        async fn run(&self, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("start {}", self.id));
+            shutdown.recv().await;
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("stop {}", self.id));
+            Ok(())
+        }
+    }
+
+    /// An app whose log filter cannot be reloaded, with its first bots running.
+    async fn start() -> (App<NoSignals, FakeLoader>, RunningBots, Events) {
+        let loader = FakeLoader::default();
+        let config = loader.load_config().unwrap();
+        let log_guard = LogGuard::detached(&config);
+        let services = Services {
+            log_level: log_guard.level().clone(),
+            heartbeat: Heartbeat::new(Duration::ZERO),
+        };
+        let events = Arc::clone(&loader.events);
+        let running = RunningBots::start(loader.build_bots(&config, &services).unwrap());
+        let app = App {
+            log_guard,
+            signal_handler: NoSignals,
+            loader,
+            config,
+            services,
+            probation: None,
+        };
+        wait_for(&events, &["start 1"]).await;
+        (app, running, events)
+    }
+
+    async fn wait_for(events: &Events, expected: &[&str]) {
+        for _ in 0..100 {
+            if *events.lock().unwrap() == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*events.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn rejected_reload_keeps_the_running_bots() {
+        let (mut app, running, events) = start().await;
+
+        app.loader.reject.store(true, Ordering::Relaxed);
+        let running = app.reload(running).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(*events.lock().unwrap(), ["start 1"]);
+
+        running.stop().await.unwrap();
+        wait_for(&events, &["start 1", "stop 1"]).await;
+    }
+
+    #[tokio::test]
+    async fn accepted_reload_swaps_the_bots_even_if_the_log_filter_fails() {
+        let (mut app, running, events) = start().await;
+
+        let running = app.reload(running).await;
+        wait_for(&events, &["start 1", "stop 1", "start 2"]).await;
+
+        running.stop().await.unwrap();
+        wait_for(&events, &["start 1", "stop 1", "start 2", "stop 2"]).await;
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_the_log_outputs_it_started_with() {
+        let (mut app, running, events) = start().await;
+
+        *app.loader.extra.lock().unwrap() =
+            "[log]\ndir = \"/tmp/elsewhere\"\noutput = \"both\"\nfilter = \"debug\"\n".into();
+        let running = app.reload(running).await;
+        wait_for(&events, &["start 1", "stop 1", "start 2"]).await;
+
+        assert_eq!(app.config.log.dir, None);
+        assert_eq!(app.config.log.output, LogOutput::File);
+        assert_eq!(app.config.log.filter, "debug");
+        assert_eq!(
+            app.config.warnings,
+            [
+                "log.dir takes effect after a restart",
+                "log.output takes effect after a restart"
+            ]
+        );
+        running.stop().await.unwrap();
     }
 }
@@ -0,0 +1,21 @@
+//! Configuration loading and bot assembly.
+
+use crate::bot::BotManager;
+use crate::domain::Heartbeat;
+use crate::infrastructure::{Config, LogLevel};
+
+/// Process-wide handles that bots may use; they outlive reloads.
+#[derive(Clone)]
+pub struct Services {
+    pub log_level: LogLevel,
+    pub heartbeat: Heartbeat,
+}
+
+/// Builds the reloadable parts of the application.
+///
+/// Called once at startup and again on every reload request.
+pub trait Loader: Send + Sync {
+    fn load_config(&self) -> anyhow::Result<Config>;
+
+    fn build_bots(&self, config: &Config, services: &Services) -> anyhow::Result<BotManager>;
+}
@@ -1,3 +1,6 @@
 mod app;
+mod loader;
+mod watchdog;
 
 pub use app::App;
+pub use loader::{Loader, Services};
@@ -0,0 +1,71 @@
+//! Exits the process when the bots stop sending heartbeats.
+//!
+//! Runs on its own thread, so it still fires when the async runtime is stuck.
+//! procd then restarts the service.
+
+use std::time::Duration;
+
+use crate::domain::Heartbeat;
+use crate::exit;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn spawn(heartbeat: Heartbeat) -> anyhow::Result<()> {
+    watch(heartbeat, CHECK_INTERVAL, |heartbeat| hung(heartbeat))
+}
+
+/// Checks `heartbeat` every `interval` and calls `on_hung` once it is stale.
+fn watch(
+    heartbeat: Heartbeat,
+    interval: Duration,
+    on_hung: impl FnOnce(&Heartbeat) + Send + 'static,
+) -> anyhow::Result<()> {
+    std::thread::Builder::new()
+        .name("watchdog".to_string())
+        .spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+                if heartbeat.is_stale() {
+                    return on_hung(&heartbeat);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Reports on stderr too: the log writer may not get to flush before exit.
+fn hung(heartbeat: &Heartbeat) -> ! {
+    let message = format!(
+        "No heartbeat for {}s, exiting for a restart",
+        heartbeat.age().as_secs()
+    );
+    tracing::error!("{message}");
+    eprintln!("{message}");
+    std::process::exit(exit::HUNG.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn fires_once_the_heartbeat_stops() {
+        let heartbeat = Heartbeat::new(Duration::from_millis(50));
+        let (tx, rx) = mpsc::channel();
+        watch(heartbeat.clone(), Duration::from_millis(5), move |hb| {
+            tx.send(hb.age()).unwrap();
+        })
+        .unwrap();
+
+        for _ in 0..10 {
+            heartbeat.beat();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(rx.try_recv().is_err());
+
+        let age = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(age > Duration::from_millis(50));
+    }
+}
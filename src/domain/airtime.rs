@@ -0,0 +1,76 @@
+//! Channel use computed from survey counters.
+
+use super::ubus::ChannelSurvey;
+
+/// Shares of the time a radio spent on its channel, in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Airtime {
+    /// Busy for any reason: our traffic, neighbours or interference.
+    pub busy: u8,
+    /// Receiving frames addressed to us.
+    pub rx: u8,
+    pub tx: u8,
+    pub noise: Option<i32>,
+}
+
+impl Airtime {
+    /// Channel use between two readings of the same channel. Without an
+    /// earlier reading, or if the counters were reset since, it covers
+    /// everything the later one counted.
+    pub fn between(earlier: Option<&ChannelSurvey>, later: &ChannelSurvey) -> Option<Self> {
+        let earlier = earlier.filter(|e| {
+            e.mhz == later.mhz
+                && e.active_time <= later.active_time
+                && e.busy_time <= later.busy_time
+                && e.rx_time <= later.rx_time
+                && e.tx_time <= later.tx_time
+        });
+        let since =
+            |counter: fn(&ChannelSurvey) -> u64| counter(later) - earlier.map_or(0, counter);
+
+        let active = since(|s| s.active_time);
+        if active == 0 {
+            return None;
+        }
+        let percent = |time: u64| (time.min(active) * 100 / active) as u8;
+        Some(Self {
+            busy: percent(since(|s| s.busy_time)),
+            rx: percent(since(|s| s.rx_time)),
+            tx: percent(since(|s| s.tx_time)),
+            noise: later.noise.filter(|&n| n != 0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(active: u64, busy: u64, rx: u64, tx: u64) -> ChannelSurvey {
+        ChannelSurvey {
+            mhz: 2412,
+            noise: Some(-91),
+            active_time: active,
+            busy_time: busy,
+            rx_time: rx,
+            tx_time: tx,
+            ..ChannelSurvey::default()
+        }
+    }
+
+    #[test]
+    fn measures_the_time_between_readings() {
+        let first = reading(10_000, 1_000, 500, 200);
+        let second = reading(20_000, 9_000, 4_500, 1_200);
+
+        let airtime = Airtime::between(Some(&first), &second).unwrap();
+        assert_eq!((airtime.busy, airtime.rx, airtime.tx), (80, 40, 10));
+        assert_eq!(airtime.noise, Some(-91));
+
+        let alone = Airtime::between(None, &second).unwrap();
+        assert_eq!(alone.busy, 45);
+        let after_reset = Airtime::between(Some(&second), &first).unwrap();
+        assert_eq!(after_reset.busy, 10);
+        assert_eq!(Airtime::between(Some(&first), &first), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Channel planning from neighbour scans.
+//!
+//! Every channel a radio could use, or block of channels for wide radios, is
+//! scored by how much of each neighbouring network falls inside it, weighted
+//! by how loud that network is. Lower is better; zero means nothing was heard.
+
+use super::ubus::{ScanNetwork, band_from_freq};
+
+/// Neighbours this quiet or quieter do not count.
+const NOISE_FLOOR_DBM: i32 = -95;
+/// Neighbours without a reported signal count as fairly weak.
+const UNKNOWN_SIGNAL_DBM: i32 = -85;
+
+/// The non-overlapping 2.4 GHz channels; wide radios there still pick one.
+const CHANNELS_2G: [u32; 3] = [1, 6, 11];
+/// Contiguous 5 GHz ranges; wide blocks never straddle two of them.
+const SEGMENTS_5G: [(u32, u32); 3] = [(36, 64), (100, 144), (149, 165)];
+const SEGMENT_6G: (u32, u32) = (1, 233);
+/// 5 GHz channels that need radar detection.
+const DFS_5G: (u32, u32) = (52, 144);
+
+/// Moving one radio to another channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelChange {
+    /// The UCI section, e.g. `radio1`.
+    pub radio: String,
+    pub channel: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelScore {
+    /// The primary channel to configure.
+    pub channel: u32,
+    pub score: u32,
+    /// Neighbours overlapping the channel's block.
+    pub networks: usize,
+    /// Needs a radar check, which keeps the radio off for a minute or more.
+    pub dfs: bool,
+}
+
+pub struct ChannelPlan {
+    /// Best first; DFS channels lose ties.
+    pub ranked: Vec<ChannelScore>,
+    /// The channel in use, unless it is `auto`.
+    pub current: Option<ChannelScore>,
+}
+
+impl ChannelPlan {
+    /// Scores the channels of `band` for a radio `width` MHz wide.
+    pub fn new(band: &str, width: u32, current: Option<u32>, neighbours: &[ScanNetwork]) -> Self {
+        let spans: Vec<_> = neighbours
+            .iter()
+            .filter_map(|n| Some((occupied(band, n)?, weight(n.signal))))
+            .collect();
+        let score = |block: &[u32]| {
+            let first = channel_freq(band, block[0])? - 10;
+            let last = channel_freq(band, block[block.len() - 1])? + 10;
+            let mut score = ChannelScore {
+                channel: block[0],
+                score: 0,
+                networks: 0,
+                dfs: band == "5g" && block.iter().any(|c| (DFS_5G.0..=DFS_5G.1).contains(c)),
+            };
+            for ((low, high), weight) in &spans {
+                let overlap = high.min(&last).saturating_sub(*low.max(&first));
+                if overlap > 0 {
+                    score.networks += 1;
+                    score.score += weight * overlap / (high - low);
+                }
+            }
+            Some(score)
+        };
+
+        let blocks = blocks(band, width);
+        let mut ranked: Vec<_> = blocks.iter().filter_map(|b| score(b)).collect();
+        ranked.sort_by_key(|s| (s.score, s.dfs, s.networks, s.channel));
+
+        let current = current.and_then(|channel| {
+            let block = blocks
+                .iter()
+                .find(|b| b.contains(&channel))
+                .cloned()
+                .unwrap_or_else(|| vec![channel]);
+            let mut current = score(&block)?;
+            current.channel = channel;
+            Some(current)
+        });
+        Self { ranked, current }
+    }
+
+    /// The channel worth moving to, if it beats the one in use.
+    pub fn recommendation(&self) -> Option<&ChannelScore> {
+        let best = self.ranked.first()?;
+        match &self.current {
+            Some(current) if current.score <= best.score => None,
+            _ => Some(best),
+        }
+    }
+
+    pub fn allows(&self, channel: u32) -> bool {
+        self.ranked.iter().any(|s| s.channel == channel)
+    }
+}
+
+/// Centre frequency in MHz of a 20 MHz channel on `band`.
+pub fn channel_freq(band: &str, channel: u32) -> Option<u32> {
+    match (band, channel) {
+        ("2g", 14) => Some(2484),
+        ("2g", 1..=13) => Some(2407 + 5 * channel),
+        ("5g", 32..=177) => Some(5000 + 5 * channel),
+        ("6g", 1..=233) => Some(5950 + 5 * channel),
+        _ => None,
+    }
+}
+
+/// Candidate channels grouped into blocks of `width`, first channel first.
+fn blocks(band: &str, width: u32) -> Vec<Vec<u32>> {
+    let segments: &[(u32, u32)] = match band {
+        "2g" => return CHANNELS_2G.iter().map(|&c| vec![c]).collect(),
+        "5g" => &SEGMENTS_5G,
+        "6g" => &[SEGMENT_6G],
+        _ => return Vec::new(),
+    };
+    let per_block = (width / 20).max(1) as usize;
+    segments
+        .iter()
+        .flat_map(|&(first, last)| {
+            let channels: Vec<u32> = (first..=last).step_by(4).collect();
+            channels
+                .chunks_exact(per_block)
+                .map(<[u32]>::to_vec)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The frequency range a neighbour transmits on, in MHz.
+fn occupied(band: &str, network: &ScanNetwork) -> Option<(u32, u32)> {
+    let primary = network
+        .mhz
+        .or_else(|| channel_freq(band, network.channel))?;
+    if band_from_freq(primary) != band {
+        return None;
+    }
+
+    let vht = network
+        .vht_operation
+        .as_ref()
+        .filter(|v| v.channel_width >= 80 && v.center_freq_1 > 0);
+    let (centre, width) = match vht {
+        // A second centre 8 channels from the first marks a 160 MHz network.
+        Some(v) if v.center_freq_2.abs_diff(v.center_freq_1) == 8 => {
+            (channel_freq(band, v.center_freq_2)?, 160)
+        }
+        Some(v) if v.channel_width == 160 => (channel_freq(band, v.center_freq_1)?, 160),
+        Some(v) => (channel_freq(band, v.center_freq_1)?, 80),
+        None => match network
+            .ht_operation
+            .as_ref()
+            .map(|h| h.secondary_channel_offset.as_str())
+        {
+            Some("above") => (primary + 10, 40),
+            Some("below") => (primary - 10, 40),
+            _ => (primary, 20),
+        },
+    };
+    Some((centre - width / 2, centre + width / 2))
+}
+
+fn weight(signal: Option<i32>) -> u32 {
+    (signal.unwrap_or(UNKNOWN_SIGNAL_DBM) - NOISE_FLOOR_DBM).max(0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn neighbours(value: serde_json::Value) -> Vec<ScanNetwork> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn avoids_loud_and_wide_neighbours_on_2g() {
+        let heard = neighbours(json!([
+            {"channel": 1, "signal": -40},
+            {"channel": 6, "signal": -60, "ht_operation": {"secondary_channel_offset": "above"}},
+        ]));
+
+        let plan = ChannelPlan::new("2g", 20, Some(1), &heard);
+
+        let order: Vec<_> = plan.ranked.iter().map(|s| (s.channel, s.score)).collect();
+        assert_eq!(order, [(11, 13), (6, 17), (1, 55)]);
+        assert_eq!(plan.recommendation().unwrap().channel, 11);
+    }
+
+    #[test]
+    fn scores_whole_blocks_and_prefers_non_dfs_on_5g() {
+        let heard = neighbours(json!([
+            {"channel": 44, "mhz": 5220, "signal": -55,
+             "vht_operation": {"channel_width": 80, "center_freq_1": 42}},
+            {"channel": 100, "signal": -90},
+        ]));
+
+        let plan = ChannelPlan::new("5g", 80, Some(40), &heard);
+
+        let current = plan.current.as_ref().unwrap();
+        assert_eq!(
+            (current.channel, current.score, current.networks),
+            (40, 40, 1)
+        );
+        let best = plan.recommendation().unwrap();
+        assert_eq!((best.channel, best.dfs), (149, false));
+        assert!(plan.ranked.iter().any(|s| s.channel == 52 && s.dfs));
+        assert!(plan.allows(132) && !plan.allows(140));
+    }
+
+    #[test]
+    fn keeps_a_channel_that_is_already_the_quietest() {
+        let heard = neighbours(json!([{"channel": 6, "signal": -50}]));
+
+        let plan = ChannelPlan::new("2g", 20, Some(1), &heard);
+        assert!(plan.recommendation().is_none());
+
+        let auto = ChannelPlan::new("2g", 20, None, &heard);
+        assert_eq!(auto.recommendation().unwrap().channel, 1);
+    }
+}
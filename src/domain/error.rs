@@ -1,7 +1,141 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+/// A failed router query, naming the ubus object and method involved.
+///
+/// Cloneable so one failure can be handed to every caller waiting on it.
+#[derive(Debug, Clone, Error)]
 pub enum RouterError {
+    #[error("unable to execute command {cmd}: {source}")]
+    Spawn {
+        cmd: &'static str,
+        #[source]
+        source: Arc<std::io::Error>,
+    },
+
+    /// The object is not registered, e.g. hostapd is not running for an
+    /// interface.
+    #[error("{object}: {}", not_found(object))]
+    NotFound { object: String, method: String },
+
+    #[error("{object} {method}: permission denied")]
+    PermissionDenied { object: String, method: String },
+
+    #[error("{object} {method}: no answer in time, the service may be restarting")]
+    Timeout { object: String, method: String },
+
+    #[error("{object} {method} failed with code {code}: {message}")]
+    Failed {
+        object: String,
+        method: String,
+        code: i32,
+        message: String,
+    },
+
+    #[error("{object} {method}: unexpected response: {source}")]
+    Json {
+        object: String,
+        method: String,
+        #[source]
+        source: Arc<serde_json::Error>,
+    },
+}
+
+impl RouterError {
+    /// Maps the exit status of `ubus call <object> <method>`.
+    pub fn from_status(object: &str, method: &str, code: i32, stderr: &str) -> Self {
+        let (object, method) = (object.to_string(), method.to_string());
+        match code {
+            UBUS_NOT_FOUND => Self::NotFound { object, method },
+            UBUS_PERMISSION_DENIED => Self::PermissionDenied { object, method },
+            UBUS_TIMEOUT => Self::Timeout { object, method },
+            _ => {
+                let message = stderr.trim();
+                Self::Failed {
+                    object,
+                    method,
+                    code,
+                    message: message
+                        .strip_prefix("Command failed: ")
+                        .unwrap_or(message)
+                        .to_string(),
+                }
+            }
+        }
+    }
+
+    /// Whether trying again shortly might succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout { .. }
+                | Self::Failed {
+                    code: UBUS_CONNECTION_FAILED,
+                    ..
+                }
+        )
+    }
+}
+
+/// `ubus` exit codes, from libubus' `enum ubus_msg_status`.
+pub const UBUS_NOT_FOUND: i32 = 4;
+const UBUS_PERMISSION_DENIED: i32 = 6;
+const UBUS_TIMEOUT: i32 = 7;
+const UBUS_CONNECTION_FAILED: i32 = 10;
+
+fn not_found(object: &str) -> &'static str {
+    if object.starts_with("hostapd.") {
+        "not running"
+    } else {
+        "not found"
+    }
+}
+
+/// A rejected or failed change to an access point.
+#[derive(Debug, Error)]
+pub enum WifiSettingsError {
+    #[error("no access point is named '{0}'")]
+    UnknownSsid(String),
+
+    #[error("SSIDs are 1 to 32 bytes, without control characters")]
+    Ssid,
+
+    #[error("WPA2 passphrases are 8 to 63 printable ASCII characters, or 64 hex digits")]
+    Wpa2Key,
+
+    #[error("WPA3 passphrases are at least 8 printable ASCII characters")]
+    Wpa3Key,
+
+    #[error("WPA2/WPA3 passphrases are 8 to 63 printable ASCII characters")]
+    MixedKey,
+
+    #[error("'{0}' has no passphrase yet; set one first")]
+    NoKey(String),
+
+    #[error(transparent)]
+    Router(#[from] RouterError),
+
+    #[error("{ifnames} did not come back up, so the old settings were restored")]
+    RolledBack { ifnames: String },
+
+    #[error("{ifnames} did not come back up, not even with the old settings")]
+    StillDown { ifnames: String },
+}
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("the bot does not write a log file (log.output is syslog)")]
+    NoLogFile,
+
+    #[error("unable to read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("unable to execute command {cmd}: {source}")]
     Spawn {
         cmd: &'static str,
@@ -16,10 +150,46 @@ pub enum RouterError {
         stderr: String,
     },
 
-    #[error("unable to parse JSON from {cmd}: {source}")]
-    Json {
-        cmd: &'static str,
+    #[error("the command {cmd} did not finish in time")]
+    Timeout { cmd: &'static str },
+
+    #[error("unable to change the log filter: {0}")]
+    Filter(String),
+}
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("updates are not configured (update.url)")]
+    NotConfigured,
+
+    #[error("unable to download {url}: {message}")]
+    Download { url: String, message: String },
+
+    #[error("{url} is larger than {max} bytes")]
+    TooLarge { url: String, max: u64 },
+
+    #[error("checksum mismatch: expected {expected}, got {actual}")]
+    Checksum { expected: String, actual: String },
+
+    #[error("the signature does not match the release manifest")]
+    Signature,
+
+    #[error("invalid release manifest: {0}")]
+    Manifest(String),
+
+    #[error("the published version {offered} is older than the running {current}")]
+    Downgrade { offered: String, current: String },
+
+    #[error("the downloaded binary does not run here: {0}")]
+    BadBinary(String),
+
+    #[error("there is no previous binary to roll back to")]
+    NoBackup,
+
+    #[error("unable to {action}: {source}")]
+    Io {
+        action: String,
         #[source]
-        source: serde_json::Error,
+        source: std::io::Error,
     },
 }
@@ -0,0 +1,89 @@
+//! Liveness signal from running bots to the watchdog.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct Heartbeat {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    start: Instant,
+    /// Milliseconds since `start` at the last beat.
+    last: AtomicU64,
+    /// Milliseconds without a beat before the process counts as hung; 0 = never.
+    timeout: AtomicU64,
+}
+
+impl Heartbeat {
+    pub fn new(timeout: Duration) -> Self {
+        let inner = Inner {
+            start: Instant::now(),
+            last: AtomicU64::new(0),
+            timeout: AtomicU64::new(millis(timeout)),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    pub fn beat(&self) {
+        let now = millis(self.inner.start.elapsed());
+        self.inner.last.store(now, Ordering::Relaxed);
+    }
+
+    pub fn age(&self) -> Duration {
+        let last = Duration::from_millis(self.inner.last.load(Ordering::Relaxed));
+        self.inner.start.elapsed().saturating_sub(last)
+    }
+
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.inner.timeout.store(millis(timeout), Ordering::Relaxed);
+    }
+
+    /// `None` while the watchdog is disabled.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.inner.timeout.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.timeout().is_some_and(|timeout| self.age() > timeout)
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goes_stale_after_the_timeout() {
+        let heartbeat = Heartbeat::new(Duration::from_millis(20));
+        assert!(!heartbeat.is_stale());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(heartbeat.is_stale());
+
+        heartbeat.beat();
+        assert!(!heartbeat.is_stale());
+    }
+
+    #[test]
+    fn never_goes_stale_without_a_timeout() {
+        let heartbeat = Heartbeat::new(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!heartbeat.is_stale());
+        assert_eq!(heartbeat.timeout(), None);
+
+        heartbeat.set_timeout(Duration::from_millis(1));
+        assert!(heartbeat.is_stale());
+    }
+}
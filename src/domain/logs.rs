@@ -0,0 +1,59 @@
+//! Access to the bot's own log and the system log, and runtime log levels.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::LogError;
+
+/// Which lines to return: the last `lines` that contain `pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogQuery {
+    pub lines: usize,
+    /// Case-insensitive substring; `None` matches every line.
+    pub pattern: Option<String>,
+}
+
+impl LogQuery {
+    pub fn matches(&self, line: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|p| line.to_lowercase().contains(&p.to_lowercase()))
+    }
+
+    /// Picks the matching tail of `text`, oldest line first.
+    pub fn select(&self, text: &str) -> Vec<String> {
+        let mut lines: Vec<String> = text
+            .lines()
+            .rev()
+            .filter(|line| self.matches(line))
+            .take(self.lines)
+            .map(str::to_string)
+            .collect();
+        lines.reverse();
+        lines
+    }
+}
+
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    async fn bot_log(&self, query: &LogQuery) -> Result<Vec<String>, LogError>;
+    async fn system_log(&self, query: &LogQuery) -> Result<Vec<String>, LogError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLevelState {
+    /// Filter in effect, in `EnvFilter` syntax.
+    pub filter: String,
+    /// Filter from the configuration, restored when an override expires.
+    pub configured: String,
+    /// Time until the override expires; `None` if there is none or it never does.
+    pub revert_in: Option<Duration>,
+}
+
+/// Temporarily overrides the log filter of the running process.
+pub trait LogLevelControl: Send + Sync {
+    fn state(&self) -> LogLevelState;
+    fn set(&self, filter: &str) -> Result<LogLevelState, LogError>;
+    fn reset(&self) -> Result<LogLevelState, LogError>;
+}
@@ -44,9 +44,28 @@ pub trait Messenger: Send + Sync {
 
 #[async_trait]
 pub trait Bot: Send + Sync {
-    async fn run(self: Box<Self>, shutdown: crate::domain::ShutdownSignal) -> anyhow::Result<()>;
+    fn name(&self) -> &str;
+
+    async fn run(&self, shutdown: crate::domain::ShutdownSignal) -> anyhow::Result<()>;
 }
 
 pub trait AuthFilter: Send + Sync {
     fn is_allowed(&self, user_id: UserId) -> bool;
+    fn is_admin(&self, user_id: UserId) -> bool;
+
+    fn has_role(&self, user_id: UserId, role: Role) -> bool {
+        match role {
+            Role::User => self.is_allowed(user_id),
+            Role::Admin => self.is_admin(user_id),
+        }
+    }
+}
+
+/// Who may run a command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Role {
+    /// Anyone on the allow list.
+    #[default]
+    User,
+    Admin,
 }
@@ -1,15 +1,29 @@
 //! Domain layer: traits, types, and error definitions.
 
+pub mod airtime;
+pub mod channel;
 pub mod error;
+pub mod heartbeat;
+pub mod logs;
 pub mod messenger;
 pub mod router;
+pub mod secret;
 pub mod signal;
 pub mod types;
 pub mod ubus;
+pub mod update;
 pub mod wifi_mode;
+pub mod wifi_settings;
 
-pub use error::RouterError;
-pub use router::{RouterInfo, RouterStatus};
+pub use airtime::Airtime;
+pub use channel::{ChannelChange, ChannelPlan};
+pub use error::{LogError, RouterError, UpdateError, WifiSettingsError};
+pub use heartbeat::Heartbeat;
+pub use logs::{LogLevelControl, LogLevelState, LogQuery, LogSource};
+pub use router::{ClientQuery, ClientSort, Partial, RouterInfo, RouterStatus};
+pub use secret::Secret;
 pub use signal::SignalHandler;
 pub use types::ShutdownSignal;
+pub use update::{UpdateOutcome, Updater};
 pub use wifi_mode::WifiMode;
+pub use wifi_settings::{ApplyTiming, Encryption, WifiSetting};
@@ -1,13 +1,116 @@
+use std::cmp::Reverse;
+
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 
 use super::{
-    RouterError,
-    ubus::{BoardInfo, HostapdClients, SystemInfo, WirelessStatus},
+    RouterError, WifiMode,
+    ubus::{
+        AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+        WifiClient, WirelessStatus,
+    },
+    wifi_settings::InterfaceOptions,
 };
 
+/// Interfaces queried at once by `all_clients`.
+const CLIENT_QUERIES: usize = 4;
+
+/// Stations below this signal count as weak.
+pub const WEAK_SIGNAL_DBM: i32 = -70;
+
+/// Whatever could be fetched, with the errors for the parts that could not.
+pub struct Partial<T> {
+    pub value: T,
+    pub warnings: Vec<RouterError>,
+}
+
+impl<T> Partial<T> {
+    fn keep<V>(&mut self, result: Result<V, RouterError>) -> Option<V> {
+        result.map_err(|e| self.warnings.push(e)).ok()
+    }
+}
+
 pub struct RouterStatus {
-    pub system: SystemInfo,
-    pub board: BoardInfo,
+    pub system: Option<SystemInfo>,
+    pub board: Option<BoardInfo>,
+}
+
+/// Stations on one access point interface.
+pub struct InterfaceClients {
+    pub ifname: String,
+    pub ssid: String,
+    pub band: String,
+    pub clients: HostapdClients,
+}
+
+impl InterfaceClients {
+    /// The stations `query` asks for, in its order; ties by MAC.
+    pub fn select(&self, query: &ClientQuery) -> Vec<(&String, &WifiClient)> {
+        let mut clients: Vec<_> = self
+            .clients
+            .clients
+            .iter()
+            .filter(|(_, client)| query.matches_client(client, &self.band))
+            .collect();
+        match query.sort {
+            ClientSort::Signal => clients.sort_by_key(|(mac, c)| (Reverse(c.signal), *mac)),
+            ClientSort::Rate => clients.sort_by_key(|(mac, c)| (Reverse(c.rate.tx), *mac)),
+            ClientSort::Traffic => {
+                clients.sort_by_key(|(mac, c)| (Reverse(c.bytes.rx + c.bytes.tx), *mac))
+            }
+            ClientSort::Name => clients.sort_by_key(|(mac, _)| *mac),
+        }
+        clients
+    }
+}
+
+/// Order of stations within an interface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientSort {
+    /// Strongest first; stations without a signal last.
+    #[default]
+    Signal,
+    /// Fastest transmit rate first.
+    Rate,
+    /// Most bytes moved first.
+    Traffic,
+    /// By MAC address.
+    Name,
+}
+
+/// Which stations to list, and in what order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientQuery {
+    pub sort: ClientSort,
+    /// `2g`, `5g`, ...; `None` matches every band.
+    pub band: Option<String>,
+    /// Case-insensitive; `None` matches every SSID.
+    pub ssid: Option<String>,
+    /// Only stations below `WEAK_SIGNAL_DBM`.
+    pub weak: bool,
+    pub mode: Option<WifiMode>,
+}
+
+impl ClientQuery {
+    pub fn matches_interface(&self, band: &str, ssid: &str) -> bool {
+        self.band.as_ref().is_none_or(|b| b == band)
+            && self
+                .ssid
+                .as_ref()
+                .is_none_or(|s| s.eq_ignore_ascii_case(ssid))
+    }
+
+    pub fn matches_client(&self, client: &WifiClient, band: &str) -> bool {
+        (!self.weak || client.signal.is_some_and(|s| s < WEAK_SIGNAL_DBM))
+            && self
+                .mode
+                .is_none_or(|mode| WifiMode::from_client(client, band) == mode)
+    }
+
+    /// Whether anything is filtered out, rather than only reordered.
+    pub fn is_filtered(&self) -> bool {
+        self.band.is_some() || self.ssid.is_some() || self.weak || self.mode.is_some()
+    }
 }
 
 #[async_trait]
@@ -15,10 +118,22 @@ pub trait SystemInfoProvider: Send + Sync {
     async fn system_info(&self) -> Result<SystemInfo, RouterError>;
     async fn board_info(&self) -> Result<BoardInfo, RouterError>;
 
-    async fn status(&self) -> Result<RouterStatus, RouterError> {
-        let system = self.system_info().await?;
-        let board = self.board_info().await?;
-        Ok(RouterStatus { system, board })
+    /// Fails only if neither part could be fetched.
+    async fn status(&self) -> Result<Partial<RouterStatus>, RouterError> {
+        let mut status = Partial {
+            value: RouterStatus {
+                system: None,
+                board: None,
+            },
+            warnings: Vec::new(),
+        };
+        status.value.system = status.keep(self.system_info().await);
+        status.value.board = status.keep(self.board_info().await);
+
+        if status.value.system.is_none() && status.value.board.is_none() {
+            return Err(status.warnings.remove(0));
+        }
+        Ok(status)
     }
 }
 
@@ -26,8 +141,191 @@ pub trait SystemInfoProvider: Send + Sync {
 pub trait WifiInfoProvider: Send + Sync {
     async fn wireless_status(&self) -> Result<WirelessStatus, RouterError>;
     async fn wifi_clients(&self, iface: &str) -> Result<HostapdClients, RouterError>;
+    /// Per-station link details from iwinfo, which hostapd does not report.
+    async fn wifi_stations(&self, iface: &str) -> Result<AssocList, RouterError>;
+    /// Networks the radio behind `iface` can hear; takes seconds.
+    async fn wifi_scan(&self, iface: &str) -> Result<ScanList, RouterError>;
+    /// Channel use counters of the radio behind `iface`.
+    async fn wifi_survey(&self, iface: &str) -> Result<SurveyList, RouterError>;
+
+    /// Clients of every interface that is up, queried a few at a time and
+    /// ordered by band, then SSID; failed interfaces become warnings.
+    async fn all_clients(&self, wireless: &WirelessStatus) -> Partial<Vec<InterfaceClients>> {
+        let mut ifaces: Vec<(String, String, String)> = Vec::new();
+        for radio in wireless.0.values() {
+            for iface in radio.interfaces.iter().filter(|i| !i.ifname.is_empty()) {
+                ifaces.push((
+                    radio.config.band.clone(),
+                    iface.config.ssid.clone(),
+                    iface.ifname.clone(),
+                ));
+            }
+        }
+        ifaces.sort_by(|a, b| {
+            (band_rank(&a.0), &a.0, &a.1, &a.2).cmp(&(band_rank(&b.0), &b.0, &b.1, &b.2))
+        });
+
+        let answers: Vec<_> = stream::iter(ifaces)
+            .map(|(band, ssid, ifname)| async move {
+                let clients = self.wifi_clients(&ifname).await;
+                (band, ssid, ifname, clients)
+            })
+            .buffered(CLIENT_QUERIES)
+            .collect()
+            .await;
+
+        let mut result = Partial {
+            value: Vec::new(),
+            warnings: Vec::new(),
+        };
+        for (band, ssid, ifname, clients) in answers {
+            if let Some(clients) = result.keep(clients) {
+                result.value.push(InterfaceClients {
+                    ifname,
+                    ssid,
+                    band,
+                    clients,
+                });
+            }
+        }
+        result
+    }
+}
+
+/// Orders bands by frequency; unknown ones go last.
+fn band_rank(band: &str) -> u8 {
+    match band {
+        "2g" => 0,
+        "5g" => 1,
+        "6g" => 2,
+        "60g" => 3,
+        _ => u8::MAX,
+    }
+}
+
+#[async_trait]
+pub trait NetworkInfoProvider: Send + Sync {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError>;
+}
+
+/// Changes to the wireless configuration, applied as soon as they are made.
+#[async_trait]
+pub trait WifiControl: Send + Sync {
+    /// Moves `radio` (a UCI section such as `radio1`) to `channel`.
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError>;
+    /// Sets options of `wifi-iface` sections, then commits them together.
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError>;
+}
+
+pub trait RouterInfo:
+    SystemInfoProvider + WifiInfoProvider + NetworkInfoProvider + WifiControl
+{
+}
+
+impl<T: SystemInfoProvider + WifiInfoProvider + NetworkInfoProvider + WifiControl> RouterInfo
+    for T
+{
 }
 
-pub trait RouterInfo: SystemInfoProvider + WifiInfoProvider {}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
 
-impl<T: SystemInfoProvider + WifiInfoProvider> RouterInfo for T {}
+    use crate::testing::FakeRouter;
+
+    use super::*;
+
+    /// Serves `hostapd.<ifname>` with one client per interface, except
+    /// `wlan-down`.
+    fn router() -> FakeRouter {
+        FakeRouter::default()
+            .with_delay(Duration::from_millis(20))
+            .with_clients(|_, iface| {
+                if iface == "wlan-down" {
+                    return Err(RouterError::from_status(
+                        &format!("hostapd.{iface}"),
+                        "get_clients",
+                        4,
+                        "Command failed: Not found",
+                    ));
+                }
+                Ok(serde_json::from_value(serde_json::json!({
+                    "clients": {format!("00:00:00:00:00:{}", iface.len()): {"signal": -50}}
+                }))
+                .unwrap())
+            })
+    }
+
+    fn wireless() -> WirelessStatus {
+        let iface = |ifname: &str, ssid: &str| serde_json::json!({"ifname": ifname, "config": {"ssid": ssid}});
+        serde_json::from_value(serde_json::json!({
+            "radio0": {"config": {"band": "5g"}, "interfaces": [
+                iface("wlan1", "Home"), iface("wlan1-1", "Guest"), iface("wlan-down", "IoT"),
+            ]},
+            "radio1": {"config": {"band": "2g"}, "interfaces": [
+                iface("wlan0", "Home"), iface("wlan0-1", "Guest"), iface("wlan0-2", "IoT"),
+                iface("", "Off"),
+            ]},
+            "radio2": {"config": {"band": "60g"}, "interfaces": [iface("wlan3", "Home")]},
+            "radio3": {"config": {"band": "6g"}, "interfaces": [iface("wlan2", "Home")]},
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn queries_interfaces_concurrently_in_a_stable_order() {
+        let router = router();
+
+        let clients = router.all_clients(&wireless()).await;
+
+        let order: Vec<_> = clients
+            .value
+            .iter()
+            .map(|i| format!("{} {}", i.band, i.ssid))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "2g Guest", "2g Home", "2g IoT", "5g Guest", "5g Home", "6g Home", "60g Home"
+            ]
+        );
+        assert_eq!(clients.warnings.len(), 1);
+        assert_eq!(router.peak(), CLIENT_QUERIES);
+    }
+
+    #[test]
+    fn orders_and_filters_clients() {
+        let clients: HostapdClients = serde_json::from_value(serde_json::json!({"clients": {
+            "aa:00:00:00:00:03": {},
+            "aa:00:00:00:00:02": {"signal": -75},
+            "aa:00:00:00:00:01": {"signal": -40},
+            "aa:00:00:00:00:00": {"signal": -75},
+        }}))
+        .unwrap();
+        let iface = InterfaceClients {
+            ifname: "wlan1".to_string(),
+            ssid: "Home".to_string(),
+            band: "5g".to_string(),
+            clients,
+        };
+
+        let macs = |query: &ClientQuery| -> Vec<String> {
+            iface
+                .select(query)
+                .into_iter()
+                .map(|(mac, _)| mac[15..].to_string())
+                .collect()
+        };
+        assert_eq!(macs(&ClientQuery::default()), ["01", "00", "02", "03"]);
+        let query = ClientQuery {
+            sort: ClientSort::Name,
+            ..ClientQuery::default()
+        };
+        assert_eq!(macs(&query), ["00", "01", "02", "03"]);
+        let query = ClientQuery {
+            weak: true,
+            ..ClientQuery::default()
+        };
+        assert_eq!(macs(&query), ["00", "02"]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Secret values that never show up in logs.
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Wraps a sensitive value; `Debug` and `Display` print a placeholder.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl Secret<String> {
+    /// Replaces every occurrence of the secret in `text`.
+    pub fn redact(&self, text: &str) -> String {
+        if self.0.is_empty() {
+            return text.to_string();
+        }
+        text.replace(&self.0, REDACTED)
+    }
+}
+
+impl<T> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> std::fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Secret<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_prints_the_value() {
+        let secret = Secret::new("hunter2".to_string());
+
+        assert_eq!(format!("{secret}"), REDACTED);
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(format!("{:?}", Some(&secret)), "Some([REDACTED])");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn redacts_every_occurrence() {
+        let secret = Secret::new("123:abc".to_string());
+
+        assert_eq!(
+            secret.redact("bot123:abc/getMe and bot123:abc/getUpdates"),
+            "bot[REDACTED]/getMe and bot[REDACTED]/getUpdates"
+        );
+        assert_eq!(Secret::<String>::default().redact("as is"), "as is");
+    }
+}
@@ -4,7 +4,6 @@ use async_trait::async_trait;
 pub enum ShutdownKind {
     Terminate,
     Interrupt,
-    Hangup,
 }
 
 impl std::fmt::Display for ShutdownKind {
@@ -12,12 +11,21 @@ impl std::fmt::Display for ShutdownKind {
         match self {
             ShutdownKind::Terminate => write!(f, "SIGTERM"),
             ShutdownKind::Interrupt => write!(f, "SIGINT (Ctrl+C)"),
-            ShutdownKind::Hangup => write!(f, "SIGHUP"),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalEvent {
+    Shutdown(ShutdownKind),
+    Reload,
+    /// SIGUSR1: log one level more.
+    MoreVerbose,
+    /// SIGUSR2: log one level less.
+    LessVerbose,
+}
+
 #[async_trait]
 pub trait SignalHandler: Send + Sync {
-    async fn wait_for_shutdown(&self) -> ShutdownKind;
+    async fn next_event(&self) -> SignalEvent;
 }
@@ -1,12 +1,23 @@
 //! OpenWRT ubus response types.
+//!
+//! Outputs differ between releases and drivers, so every field has a default
+//! and unknown fields are kept in `extra` rather than rejected. The corpus in
+//! `fixtures/ubus` covers 19.07 through current snapshots with WiFi 7.
 
 #![allow(dead_code)]
 
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
 
-#[derive(Debug, Deserialize)]
+use super::{Secret, WifiMode};
+
+/// Fields a model does not know about, as ubus returned them.
+pub type Extra = Map<String, Value>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct SystemInfo {
     pub localtime: u64,
     pub uptime: u64,
@@ -15,19 +26,33 @@ pub struct SystemInfo {
     pub root: StorageInfo,
     pub tmp: StorageInfo,
     pub swap: SwapInfo,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct MemoryInfo {
     pub total: u64,
     pub free: u64,
     pub shared: u64,
     pub buffered: u64,
-    pub available: u64,
+    /// Missing before 21.02.
+    pub available: Option<u64>,
     pub cached: u64,
 }
 
-#[derive(Debug, Deserialize)]
+impl MemoryInfo {
+    /// Memory available to programs, estimated on releases that lack it.
+    pub fn usable(&self) -> u64 {
+        self.available
+            .unwrap_or(self.free + self.buffered + self.cached)
+            .min(self.total)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct StorageInfo {
     pub total: u64,
     pub free: u64,
@@ -35,13 +60,15 @@ pub struct StorageInfo {
     pub avail: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct SwapInfo {
     pub total: u64,
     pub free: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct BoardInfo {
     pub kernel: String,
     pub hostname: String,
@@ -50,9 +77,12 @@ pub struct BoardInfo {
     pub board_name: String,
     pub rootfs_type: String,
     pub release: ReleaseInfo,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct ReleaseInfo {
     pub distribution: String,
     pub version: String,
@@ -62,66 +92,496 @@ pub struct ReleaseInfo {
     pub builddate: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct WirelessStatus(pub HashMap<String, RadioInfo>);
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct RadioInfo {
     pub up: bool,
     pub disabled: bool,
     pub config: RadioConfig,
     pub interfaces: Vec<WifiInterface>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "RawRadioConfig")]
 pub struct RadioConfig {
+    /// `2g`, `5g`, `6g` or `60g`; derived from `hwmode` before 21.02.
     pub band: String,
     pub channel: String,
     pub htmode: String,
+    pub extra: Extra,
+}
+
+impl RadioConfig {
+    /// Width in MHz from `htmode` (`HT20` … `EHT320`, `HT40+`); `NOHT` is 20.
+    pub fn channel_width(&self) -> Option<u32> {
+        split_htmode(&self.htmode).map(|(_, width)| width)
+    }
+
+    /// The newest standard the radio has enabled.
+    pub fn mode(&self) -> Option<WifiMode> {
+        let (prefix, _) = split_htmode(&self.htmode)?;
+        WifiMode::from_htmode(prefix, &self.band)
+    }
+}
+
+fn split_htmode(htmode: &str) -> Option<(&str, u32)> {
+    if htmode == "NOHT" {
+        return Some((htmode, 20));
+    }
+    let digits = htmode.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, width) = htmode.split_at(digits);
+    let width = width.trim_end_matches(['+', '-']).parse().ok()?;
+    Some((prefix, width))
+}
+
+/// `2g`, `5g`, `6g` or `60g` for a frequency in MHz.
+pub fn band_from_freq(freq: u32) -> &'static str {
+    match freq {
+        2400..=2500 => "2g",
+        5150..=5925 => "5g",
+        5926..=7125 => "6g",
+        57000..=71000 => "60g",
+        _ => "",
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RawRadioConfig {
+    band: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    channel: String,
+    htmode: String,
+    #[serde(flatten)]
+    extra: Extra,
+}
+
+impl From<RawRadioConfig> for RadioConfig {
+    fn from(raw: RawRadioConfig) -> Self {
+        let band = raw.band.unwrap_or_else(|| {
+            let hwmode = raw.extra.get("hwmode").and_then(Value::as_str);
+            band_from_hwmode(hwmode, &raw.channel).to_string()
+        });
+        Self {
+            band,
+            channel: raw.channel,
+            htmode: raw.htmode,
+            extra: raw.extra,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+/// `11a` (and `11ac`) radios are 5 GHz; others guess from the channel.
+fn band_from_hwmode(hwmode: Option<&str>, channel: &str) -> &'static str {
+    match (hwmode, channel.parse::<u32>()) {
+        (Some("11a" | "11ac" | "11ax_5g"), _) => "5g",
+        (Some("11b" | "11g" | "11n" | "11ng"), _) => "2g",
+        (_, Ok(36..)) => "5g",
+        (_, Ok(_)) => "2g",
+        _ => "",
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct WifiInterface {
     pub section: String,
+    /// Empty while the interface is down.
     pub ifname: String,
     pub config: WifiInterfaceConfig,
-    #[serde(default)]
     pub stations: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct WifiInterfaceConfig {
     pub ssid: String,
     pub encryption: String,
+    pub key: Option<Secret<String>>,
+    pub mode: String,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct HostapdClients {
     pub freq: u32,
     pub clients: HashMap<String, WifiClient>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct WifiClient {
     pub auth: bool,
     pub assoc: bool,
     pub authorized: bool,
-    pub signal: i32,
+    /// Missing on drivers that do not report per-station data.
+    pub signal: Option<i32>,
     pub ht: bool,
     pub vht: bool,
     pub he: bool,
+    /// Reported since 23.05.
+    pub eht: bool,
+    pub mfp: bool,
+    /// Radio measurement (802.11k) capabilities; all zero if unsupported.
+    pub rrm: Vec<u32>,
+    /// The extended capabilities element, byte by byte.
+    pub extended_capabilities: Vec<u8>,
     pub bytes: ClientTraffic,
+    pub packets: ClientTraffic,
     pub rate: ClientRate,
+    /// Address of a multi-link (802.11be MLO) station as a whole.
+    pub mld_addr: Option<String>,
+    /// One entry per affiliated link; empty for single-link stations.
+    pub links: Vec<MloLink>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Debug, Deserialize)]
+impl WifiClient {
+    pub fn is_multi_link(&self) -> bool {
+        self.links.len() > 1
+    }
+
+    /// 802.11k neighbor reports and measurements.
+    pub fn supports_rrm(&self) -> bool {
+        self.rrm.iter().any(|&b| b != 0)
+    }
+
+    /// 802.11v BSS transition management, bit 19 of the extended
+    /// capabilities.
+    pub fn supports_bss_transition(&self) -> bool {
+        self.extended_capabilities
+            .get(2)
+            .is_some_and(|b| b & 0x08 != 0)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MloLink {
+    pub link_id: u32,
+    pub addr: String,
+    pub freq: u32,
+    pub signal: Option<i32>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Counters as the access point sees them: `rx` came from the station.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct ClientTraffic {
     pub rx: u64,
     pub tx: u64,
 }
 
-#[derive(Debug, Deserialize)]
+/// Current link rates in kbit/s.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct ClientRate {
     pub rx: u64,
     pub tx: u64,
 }
+
+/// `iwinfo assoclist` for one interface.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AssocList {
+    pub results: Vec<Station>,
+}
+
+impl AssocList {
+    /// iwinfo reports MACs in upper case, hostapd in lower case.
+    pub fn find(&self, mac: &str) -> Option<&Station> {
+        self.results
+            .iter()
+            .find(|s| s.mac.eq_ignore_ascii_case(mac))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Station {
+    pub mac: String,
+    pub signal: Option<i32>,
+    pub signal_avg: Option<i32>,
+    /// Zero when the driver does not measure it.
+    pub noise: Option<i32>,
+    /// Milliseconds since the last frame.
+    pub inactive: u64,
+    /// Seconds since association.
+    pub connected_time: u64,
+    pub rx: StationRate,
+    pub tx: StationRate,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The last rate used in one direction.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StationRate {
+    /// kbit/s.
+    pub rate: u64,
+    pub mcs: Option<u32>,
+    pub nss: Option<u32>,
+    /// Channel width.
+    pub mhz: u32,
+    pub short_gi: bool,
+    pub ht: bool,
+    pub vht: bool,
+    pub he: bool,
+    pub eht: bool,
+    pub packets: u64,
+    /// Transmit direction only.
+    pub retries: Option<u64>,
+    pub failed: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// `iwinfo scan` for one interface: the networks its radio can hear.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScanList {
+    pub results: Vec<ScanNetwork>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScanNetwork {
+    /// Empty for hidden networks.
+    pub ssid: String,
+    pub bssid: String,
+    /// Primary channel.
+    pub channel: u32,
+    /// Primary frequency; missing before 23.05.
+    pub mhz: Option<u32>,
+    pub signal: Option<i32>,
+    pub ht_operation: Option<HtOperation>,
+    pub vht_operation: Option<VhtOperation>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The HT operation element; says whether a 40 MHz network extends up or
+/// down from its primary channel.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HtOperation {
+    pub primary_channel: u32,
+    /// `no secondary`, `above` or `below`.
+    pub secondary_channel_offset: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// The VHT operation element, for networks 80 MHz and wider.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VhtOperation {
+    /// 40 (meaning 20 or 40, see HT), 80, 160 or 8080.
+    pub channel_width: u32,
+    /// Channel numbers of the segment centres; the second is zero unless
+    /// the network is wider than 80 MHz.
+    pub center_freq_1: u32,
+    pub center_freq_2: u32,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// `iwinfo survey` for one interface: how each channel has been used since
+/// the driver started counting.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SurveyList {
+    pub results: Vec<ChannelSurvey>,
+}
+
+impl SurveyList {
+    pub fn find(&self, mhz: u32) -> Option<&ChannelSurvey> {
+        self.results.iter().find(|s| s.mhz == mhz)
+    }
+}
+
+/// Cumulative counters in milliseconds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChannelSurvey {
+    pub mhz: u32,
+    /// Zero when the driver does not measure it.
+    pub noise: Option<i32>,
+    /// Time the radio spent on the channel.
+    pub active_time: u64,
+    /// Time the channel was sensed busy, by anyone.
+    pub busy_time: u64,
+    pub rx_time: u64,
+    pub tx_time: u64,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// `luci-rpc getDHCPLeases`; only available with LuCI installed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DhcpLeases {
+    pub dhcp_leases: Vec<DhcpLease>,
+    pub dhcp6_leases: Vec<DhcpLease>,
+}
+
+impl DhcpLeases {
+    pub fn for_mac<'a>(&'a self, mac: &'a str) -> impl Iterator<Item = &'a DhcpLease> {
+        self.all()
+            .filter(move |l| l.macaddr.eq_ignore_ascii_case(mac))
+    }
+
+    pub fn find_host(&self, hostname: &str) -> Option<&DhcpLease> {
+        self.all()
+            .find(|l| !l.macaddr.is_empty() && l.hostname.eq_ignore_ascii_case(hostname))
+    }
+
+    fn all(&self) -> impl Iterator<Item = &DhcpLease> {
+        self.dhcp_leases.iter().chain(&self.dhcp6_leases)
+    }
+}
+
+/// An IPv4 or IPv6 lease; the other family's fields stay empty.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DhcpLease {
+    pub hostname: String,
+    pub macaddr: String,
+    pub ipaddr: String,
+    pub ip6addrs: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// UCI values come through as strings, but some releases emit numbers.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    fn corpus() -> Vec<PathBuf> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/ubus");
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        dirs
+    }
+
+    fn load<T: DeserializeOwned>(path: &Path) -> T {
+        let data = std::fs::read_to_string(path).unwrap();
+        serde_json::from_str(&data).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    #[test]
+    fn parses_every_release_in_the_corpus() {
+        let dirs = corpus();
+        assert!(dirs.len() >= 5);
+
+        for dir in dirs {
+            let system: SystemInfo = load(&dir.join("system.info.json"));
+            let board: BoardInfo = load(&dir.join("system.board.json"));
+            let wireless: WirelessStatus = load(&dir.join("network.wireless.status.json"));
+            let name = dir.display();
+
+            assert!(system.memory.total > 0, "{name}");
+            assert!(system.memory.usable() <= system.memory.total, "{name}");
+            assert!(!board.release.version.is_empty(), "{name}");
+            for radio in wireless.0.values() {
+                assert!(
+                    ["2g", "5g", "6g"].contains(&radio.config.band.as_str()),
+                    "{name}: {:?}",
+                    radio.config
+                );
+                for iface in radio.interfaces.iter().filter(|i| !i.ifname.is_empty()) {
+                    let path = dir.join(format!("hostapd.{}.get_clients.json", iface.ifname));
+                    let clients: HostapdClients = load(&path);
+                    assert!(clients.clients.values().all(|c| c.assoc), "{name}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_in_fields_older_releases_lack() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/ubus/19.07.10-ath79");
+
+        let system: SystemInfo = load(&dir.join("system.info.json"));
+        assert_eq!(system.memory.available, None);
+        assert_eq!(system.memory.usable(), 33_669_120);
+
+        let wireless: WirelessStatus = load(&dir.join("network.wireless.status.json"));
+        assert_eq!(wireless.0["radio0"].config.band, "5g");
+        assert_eq!(wireless.0["radio1"].config.band, "2g");
+        assert_eq!(wireless.0["radio1"].config.extra["hwmode"], "11g");
+
+        let clients: HostapdClients = load(&dir.join("hostapd.wlan1.get_clients.json"));
+        let client = clients.clients.values().next().unwrap();
+        assert!(!client.he);
+        assert_eq!(client.rrm, [0, 0, 0, 0, 0]);
+        assert!(!client.supports_rrm());
+        assert_eq!(client.extra["wps"], false);
+    }
+
+    #[test]
+    fn reads_channel_width_and_standard_from_htmode() {
+        let radio = |htmode: &str, band: &str| RadioConfig {
+            htmode: htmode.to_string(),
+            band: band.to_string(),
+            ..RadioConfig::default()
+        };
+
+        assert_eq!(radio("HT40+", "2g").channel_width(), Some(40));
+        assert_eq!(radio("HT40+", "2g").mode(), Some(WifiMode::Wifi4));
+        assert_eq!(radio("NOHT", "2g").channel_width(), Some(20));
+        assert_eq!(radio("HE160", "6g").mode(), Some(WifiMode::Wifi6E));
+        assert_eq!(radio("EHT320", "6g").channel_width(), Some(320));
+        assert_eq!(radio("EHT320", "6g").mode(), Some(WifiMode::Wifi7));
+        assert_eq!(radio("", "5g").channel_width(), None);
+        assert_eq!(band_from_freq(5955), "6g");
+    }
+
+    #[test]
+    fn accepts_numeric_channels_and_hides_keys() {
+        let wireless: WirelessStatus = serde_json::from_str(
+            r#"{"radio0": {"config": {"channel": 6},
+                "interfaces": [{"config": {"ssid": "home", "key": "hunter22"}}]}}"#,
+        )
+        .unwrap();
+
+        let radio = &wireless.0["radio0"];
+        assert_eq!(radio.config.channel, "6");
+        assert_eq!(radio.config.band, "2g");
+        let iface = &radio.interfaces[0];
+        assert_eq!(iface.config.key.as_ref().unwrap().expose(), "hunter22");
+        assert!(!format!("{wireless:?}").contains("hunter22"));
+    }
+}
@@ -0,0 +1,27 @@
+//! Self-update of the running binary.
+
+use async_trait::async_trait;
+
+use super::UpdateError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The new binary is in place and takes over on restart.
+    Installed { from: String, to: String },
+    /// The published binary is the version already running.
+    UpToDate { version: String },
+    /// The previous binary is back in place.
+    RolledBack,
+}
+
+#[async_trait]
+pub trait Updater: Send + Sync {
+    /// Downloads, verifies and installs the published binary.
+    async fn update(&self) -> Result<UpdateOutcome, UpdateError>;
+
+    /// Puts the binary replaced by the last update back.
+    async fn rollback(&self) -> Result<UpdateOutcome, UpdateError>;
+
+    /// Asks the service manager to restart the process.
+    fn restart(&self) -> Result<(), UpdateError>;
+}
@@ -4,6 +4,10 @@ use crate::domain::ubus::WifiClient;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WifiMode {
+    /// 802.11be (EHT).
+    Wifi7,
+    /// 802.11ax (HE) on 6 GHz.
+    Wifi6E,
     Wifi6,
     Wifi5,
     Wifi4,
@@ -11,17 +15,44 @@ pub enum WifiMode {
 }
 
 impl WifiMode {
-    pub fn from_client(client: &WifiClient) -> Self {
-        match (client.he, client.vht, client.ht) {
-            (true, _, _) => Self::Wifi6,
-            (_, true, _) => Self::Wifi5,
-            (_, _, true) => Self::Wifi4,
+    pub const ALL: [Self; 6] = [
+        Self::Wifi7,
+        Self::Wifi6E,
+        Self::Wifi6,
+        Self::Wifi5,
+        Self::Wifi4,
+        Self::Legacy,
+    ];
+
+    /// The newest standard the station uses on `band` (`2g`, `5g`, `6g`).
+    pub fn from_client(client: &WifiClient, band: &str) -> Self {
+        match (client.eht, client.he, client.vht, client.ht) {
+            (true, _, _, _) => Self::Wifi7,
+            (_, true, _, _) if band == "6g" => Self::Wifi6E,
+            (_, true, _, _) => Self::Wifi6,
+            (_, _, true, _) => Self::Wifi5,
+            (_, _, _, true) => Self::Wifi4,
             _ => Self::Legacy,
         }
     }
 
+    /// The standard an `htmode` prefix (`HT`, `VHT`, `HE`, `EHT`) enables.
+    pub fn from_htmode(prefix: &str, band: &str) -> Option<Self> {
+        match prefix {
+            "EHT" => Some(Self::Wifi7),
+            "HE" if band == "6g" => Some(Self::Wifi6E),
+            "HE" => Some(Self::Wifi6),
+            "VHT" => Some(Self::Wifi5),
+            "HT" => Some(Self::Wifi4),
+            "NOHT" => Some(Self::Legacy),
+            _ => None,
+        }
+    }
+
     pub const fn as_str(&self) -> &'static str {
         match self {
+            Self::Wifi7 => "WiFi 7",
+            Self::Wifi6E => "WiFi 6E",
             Self::Wifi6 => "WiFi 6",
             Self::Wifi5 => "WiFi 5",
             Self::Wifi4 => "WiFi 4",
@@ -35,3 +66,30 @@ impl std::fmt::Display for WifiMode {
         write!(f, "{}", self.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_6e_from_6_by_band() {
+        let he = WifiClient {
+            ht: true,
+            vht: true,
+            he: true,
+            ..WifiClient::default()
+        };
+        let eht = WifiClient {
+            eht: true,
+            ..he.clone()
+        };
+
+        assert_eq!(WifiMode::from_client(&he, "5g"), WifiMode::Wifi6);
+        assert_eq!(WifiMode::from_client(&he, "6g"), WifiMode::Wifi6E);
+        assert_eq!(WifiMode::from_client(&eht, "6g"), WifiMode::Wifi7);
+        assert_eq!(
+            WifiMode::from_client(&WifiClient::default(), "2g"),
+            WifiMode::Legacy
+        );
+    }
+}
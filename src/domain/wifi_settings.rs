@@ -0,0 +1,387 @@
+//! Changing the SSID, passphrase or encryption of access points.
+//!
+//! A change is written to every access point with the given SSID and then
+//! watched: if hostapd does not answer on each of them again in time, the
+//! old values are written back, so a bad change cannot lock everyone out.
+
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+
+use super::router::{WifiControl, WifiInfoProvider};
+use super::ubus::WifiInterface;
+use super::{Secret, WifiSettingsError};
+
+/// Between checks on whether the interfaces are back.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// The longest SSID 802.11 allows, in bytes.
+const MAX_SSID_LEN: usize = 32;
+const MIN_KEY_LEN: usize = 8;
+/// Longer WPA2 keys are taken as a raw PSK, which must be 64 hex digits.
+const MAX_PASSPHRASE_LEN: usize = 63;
+const PSK_HEX_LEN: usize = 64;
+
+/// Encryption modes the bot can set, by their UCI value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+    None,
+    /// Opportunistic wireless encryption: open, but encrypted.
+    Owe,
+    Psk2,
+    /// WPA/WPA2 mixed.
+    PskMixed,
+    Sae,
+    /// WPA2/WPA3 mixed.
+    SaeMixed,
+}
+
+impl Encryption {
+    pub const ALL: [Self; 6] = [
+        Self::None,
+        Self::Owe,
+        Self::Psk2,
+        Self::PskMixed,
+        Self::Sae,
+        Self::SaeMixed,
+    ];
+
+    pub fn as_uci(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Owe => "owe",
+            Self::Psk2 => "psk2",
+            Self::PskMixed => "psk-mixed",
+            Self::Sae => "sae",
+            Self::SaeMixed => "sae-mixed",
+        }
+    }
+
+    pub fn needs_key(self) -> bool {
+        !matches!(self, Self::None | Self::Owe)
+    }
+
+    fn key_rules(self) -> KeyRules {
+        match self {
+            Self::Sae => KeyRules::Wpa3,
+            Self::SaeMixed => KeyRules::Mixed,
+            _ => KeyRules::Wpa2,
+        }
+    }
+}
+
+/// What a passphrase must look like, by encryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyRules {
+    /// 8 to 63 printable ASCII characters, or a raw PSK as 64 hex digits.
+    Wpa2,
+    /// At least 8 printable ASCII characters; SAE has no raw PSK form.
+    Wpa3,
+    /// Both at once: 8 to 63 printable ASCII characters.
+    Mixed,
+}
+
+impl KeyRules {
+    /// The rules of an encryption as found in UCI, cipher suffix and all;
+    /// open and unknown modes get the WPA2 ones.
+    fn of(encryption: &str) -> Self {
+        match encryption.split('+').next().unwrap_or_default() {
+            "sae" => Self::Wpa3,
+            "sae-mixed" => Self::Mixed,
+            _ => Self::Wpa2,
+        }
+    }
+
+    fn check(self, key: &str) -> Result<(), WifiSettingsError> {
+        let printable = key.bytes().all(|b| (b' '..=b'~').contains(&b));
+        let passphrase = printable && (MIN_KEY_LEN..=MAX_PASSPHRASE_LEN).contains(&key.len());
+        let ok = match self {
+            Self::Wpa2 => {
+                passphrase
+                    || (key.len() == PSK_HEX_LEN && key.bytes().all(|b| b.is_ascii_hexdigit()))
+            }
+            Self::Wpa3 => printable && key.len() >= MIN_KEY_LEN,
+            Self::Mixed => passphrase,
+        };
+        match (ok, self) {
+            (true, _) => Ok(()),
+            (false, Self::Wpa2) => Err(WifiSettingsError::Wpa2Key),
+            (false, Self::Wpa3) => Err(WifiSettingsError::Wpa3Key),
+            (false, Self::Mixed) => Err(WifiSettingsError::MixedKey),
+        }
+    }
+}
+
+/// One change requested for an SSID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WifiSetting {
+    Ssid(String),
+    Password(Secret<String>),
+    Encryption(Encryption),
+}
+
+/// New option values for one `wifi-iface` section.
+///
+/// No `Debug`: the values may hold a passphrase.
+pub struct InterfaceOptions {
+    /// The UCI section, e.g. `default_radio0`.
+    pub section: String,
+    pub values: Vec<(&'static str, String)>,
+}
+
+/// How long to give interfaces to come back after a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyTiming {
+    /// Before the first check, so the interfaces are not caught before the
+    /// reload has even begun.
+    pub settle: Duration,
+    /// From the first check until the change is undone.
+    pub timeout: Duration,
+}
+
+impl Default for ApplyTiming {
+    fn default() -> Self {
+        Self {
+            settle: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Applies `setting` to every access point named `ssid`, undoing it if any of
+/// them is not back within `timing`.
+///
+/// Returns the interfaces changed.
+pub async fn change_wifi<R: WifiInfoProvider + WifiControl>(
+    router: &R,
+    ssid: &str,
+    setting: &WifiSetting,
+    timing: ApplyTiming,
+) -> Result<Vec<String>, WifiSettingsError> {
+    let wireless = router.wireless_status().await?;
+    let mut ifaces: Vec<&WifiInterface> = wireless
+        .0
+        .values()
+        .flat_map(|radio| &radio.interfaces)
+        .filter(|i| i.config.ssid == ssid && matches!(i.config.mode.as_str(), "" | "ap"))
+        .collect();
+    if ifaces.is_empty() {
+        return Err(WifiSettingsError::UnknownSsid(ssid.to_string()));
+    }
+    ifaces.sort_by(|a, b| a.section.cmp(&b.section));
+
+    for iface in &ifaces {
+        validate(iface, setting)?;
+    }
+    let change: Vec<_> = ifaces.iter().map(|i| new_options(i, setting)).collect();
+    let undo: Vec<_> = ifaces.iter().map(|i| old_options(i, setting)).collect();
+    let running: Vec<String> = ifaces
+        .iter()
+        .filter(|i| !i.ifname.is_empty())
+        .map(|i| i.ifname.clone())
+        .collect();
+
+    router.configure_interfaces(&change).await?;
+    if came_back(router, &running, timing).await {
+        return Ok(ifaces
+            .iter()
+            .map(|i| match i.ifname.as_str() {
+                "" => i.section.clone(),
+                ifname => ifname.to_string(),
+            })
+            .collect());
+    }
+
+    let ifnames = running.join(", ");
+    tracing::warn!("{ifnames} did not come back after a change to {ssid}; undoing it");
+    router.configure_interfaces(&undo).await?;
+    if came_back(router, &running, timing).await {
+        Err(WifiSettingsError::RolledBack { ifnames })
+    } else {
+        Err(WifiSettingsError::StillDown { ifnames })
+    }
+}
+
+fn validate(iface: &WifiInterface, setting: &WifiSetting) -> Result<(), WifiSettingsError> {
+    let config = &iface.config;
+    match setting {
+        WifiSetting::Ssid(ssid) => {
+            if ssid.is_empty() || ssid.len() > MAX_SSID_LEN || ssid.chars().any(char::is_control) {
+                return Err(WifiSettingsError::Ssid);
+            }
+            Ok(())
+        }
+        WifiSetting::Password(key) => KeyRules::of(&config.encryption).check(key.expose()),
+        WifiSetting::Encryption(encryption) if encryption.needs_key() => {
+            let key = config.key.as_ref().map(|k| k.expose().as_str());
+            match key.filter(|k| !k.is_empty()) {
+                Some(key) => encryption.key_rules().check(key),
+                None => Err(WifiSettingsError::NoKey(config.ssid.clone())),
+            }
+        }
+        WifiSetting::Encryption(_) => Ok(()),
+    }
+}
+
+fn new_options(iface: &WifiInterface, setting: &WifiSetting) -> InterfaceOptions {
+    let value = match setting {
+        WifiSetting::Ssid(ssid) => ("ssid", ssid.clone()),
+        WifiSetting::Password(key) => ("key", key.expose().clone()),
+        WifiSetting::Encryption(encryption) => ("encryption", encryption.as_uci().to_string()),
+    };
+    InterfaceOptions {
+        section: iface.section.clone(),
+        values: vec![value],
+    }
+}
+
+fn old_options(iface: &WifiInterface, setting: &WifiSetting) -> InterfaceOptions {
+    let config = &iface.config;
+    let value = match setting {
+        WifiSetting::Ssid(_) => ("ssid", config.ssid.clone()),
+        WifiSetting::Password(_) => (
+            "key",
+            config
+                .key
+                .as_ref()
+                .map(|k| k.expose().clone())
+                .unwrap_or_default(),
+        ),
+        WifiSetting::Encryption(_) => ("encryption", config.encryption.clone()),
+    };
+    InterfaceOptions {
+        section: iface.section.clone(),
+        values: vec![value],
+    }
+}
+
+/// Whether hostapd answers on every one of `ifnames` within `timing`.
+async fn came_back<R: WifiInfoProvider>(
+    router: &R,
+    ifnames: &[String],
+    timing: ApplyTiming,
+) -> bool {
+    if ifnames.is_empty() {
+        return true;
+    }
+    tokio::time::sleep(timing.settle).await;
+
+    let deadline = Instant::now() + timing.timeout;
+    loop {
+        let answers = join_all(ifnames.iter().map(|i| router.wifi_clients(i))).await;
+        match answers.into_iter().find_map(Result::err) {
+            None => return true,
+            Some(e) => tracing::debug!("Waiting for the WiFi to come back: {e}"),
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        tokio::time::sleep(left.min(POLL_INTERVAL)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::testing::FakeRouter;
+
+    use super::super::RouterError;
+    use super::super::ubus::HostapdClients;
+    use super::*;
+
+    /// One radio with `Home` on `phy0-ap0`, plus a client interface of the
+    /// same name; its driver cannot do WPA3, so hostapd stays down on `sae`.
+    fn router() -> FakeRouter {
+        FakeRouter::default()
+            .with_wireless(json!({"radio0": {"interfaces": [
+                {"section": "default_radio0", "ifname": "phy0-ap0", "config": {
+                    "ssid": "Home", "mode": "ap", "encryption": "psk2", "key": "old-secret"
+                }},
+                {"section": "wifinet1", "ifname": "phy0-sta0", "config": {
+                    "ssid": "Home", "mode": "sta"
+                }},
+            ]}}))
+            .with_clients(|router, ifname| {
+                if router.option("default_radio0", "encryption").as_deref() == Some("sae") {
+                    return Err(RouterError::NotFound {
+                        object: format!("hostapd.{ifname}"),
+                        method: "get_clients".to_string(),
+                    });
+                }
+                Ok(HostapdClients::default())
+            })
+    }
+
+    const FAST: ApplyTiming = ApplyTiming {
+        settle: Duration::ZERO,
+        timeout: Duration::from_millis(50),
+    };
+
+    fn password(key: &str) -> WifiSetting {
+        WifiSetting::Password(Secret::new(key.to_string()))
+    }
+
+    #[test]
+    fn checks_passphrases_by_encryption() {
+        let hex = "0123456789abcdef".repeat(4);
+        let long = "x".repeat(64);
+
+        assert!(KeyRules::of("psk2+ccmp").check("12345678").is_ok());
+        assert!(KeyRules::of("psk2").check(&hex).is_ok());
+        assert!(KeyRules::of("psk2").check(&long).is_err());
+        assert!(KeyRules::of("psk2").check("1234567").is_err());
+        assert!(KeyRules::of("psk2").check("päss wörd").is_err());
+        assert!(KeyRules::of("sae").check(&long).is_ok());
+        assert!(KeyRules::of("sae-mixed").check(&hex).is_err());
+        assert!(KeyRules::of("none").check("with spaces ok").is_ok());
+    }
+
+    #[tokio::test]
+    async fn changes_access_points_only() {
+        let router = router();
+
+        let changed = change_wifi(&router, "Home", &password("new-secret"), FAST)
+            .await
+            .unwrap();
+
+        assert_eq!(changed, ["phy0-ap0"]);
+        assert_eq!(router.writes(), ["default_radio0.key=new-secret"]);
+        let error = change_wifi(&router, "Guest", &password("new-secret"), FAST)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, WifiSettingsError::UnknownSsid(_)));
+        let error = change_wifi(&router, "Home", &password("short"), FAST)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, WifiSettingsError::Wpa2Key));
+        let error = change_wifi(&router, "Home", &WifiSetting::Ssid("x".repeat(33)), FAST)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, WifiSettingsError::Ssid));
+        assert_eq!(router.writes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn undoes_a_change_the_radio_does_not_survive() {
+        let router = router();
+
+        let error = change_wifi(
+            &router,
+            "Home",
+            &WifiSetting::Encryption(Encryption::Sae),
+            FAST,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(error, WifiSettingsError::RolledBack { .. }));
+        assert_eq!(
+            router.writes(),
+            [
+                "default_radio0.encryption=sae",
+                "default_radio0.encryption=psk2"
+            ]
+        );
+    }
+}
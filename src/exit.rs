@@ -0,0 +1,12 @@
+//! Process exit codes, so procd and scripts can tell failures apart.
+//!
+//! Codes follow `sysexits.h` where one fits.
+
+/// A bot gave up or the process failed at runtime.
+pub const FAILURE: u8 = 1;
+/// The watchdog found no heartbeat for too long.
+pub const HUNG: u8 = 3;
+/// Bad command-line arguments.
+pub const USAGE: u8 = 64;
+/// Invalid configuration.
+pub const CONFIG: u8 = 78;
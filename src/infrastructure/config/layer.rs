@@ -0,0 +1,123 @@
+//! Raw configuration layers keyed by dotted paths.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::reader::{ConfigError, ConfigErrors};
+use super::{ENV_PREFIX, KEYS};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    List(Vec<Value>),
+    Unsupported(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub value: Value,
+    /// Where the value came from, e.g. a file path or an env variable name.
+    pub origin: String,
+}
+
+/// One configuration source; later layers override earlier ones.
+#[derive(Debug, Default)]
+pub struct Layer {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Layer {
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.get(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        self.entries.iter().map(|(k, e)| (k.as_str(), e))
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: Value, origin: impl Into<String>) {
+        let entry = Entry {
+            value,
+            origin: origin.into(),
+        };
+        self.entries.insert(key.into(), entry);
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<Self, ConfigErrors> {
+        let origin = path.display().to_string();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| single(ConfigError::new(&origin, format!("cannot read file: {e}"))))?;
+        Self::from_toml(&text, &origin)
+    }
+
+    pub fn from_toml(text: &str, origin: &str) -> Result<Self, ConfigErrors> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| single(ConfigError::new(origin, e.message())))?;
+
+        let mut layer = Self::default();
+        flatten_table(&mut layer, "", &table, origin);
+        Ok(layer)
+    }
+
+    /// Maps `BOT_*` variables onto known keys.
+    ///
+    /// Each key `a.b_c` is read from `BOT_A_B_C`, falling back to its legacy
+    /// name if it has one.
+    pub fn from_env(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let vars: BTreeMap<String, String> = vars
+            .into_iter()
+            .filter(|(k, _)| k.starts_with(ENV_PREFIX))
+            .collect();
+
+        let mut layer = Self::default();
+        for key in KEYS {
+            let primary = env_name(key.path);
+            let found = std::iter::once(primary.as_str())
+                .chain(key.legacy_env)
+                .find_map(|name| vars.get(name).map(|v| (name, v)));
+
+            if let Some((name, value)) = found {
+                layer.insert(key.path, Value::Str(value.clone()), name);
+            }
+        }
+        layer
+    }
+}
+
+pub fn env_name(path: &str) -> String {
+    format!("{ENV_PREFIX}{}", path.replace('.', "_").to_uppercase())
+}
+
+fn flatten_table(layer: &mut Layer, prefix: &str, table: &toml::Table, origin: &str) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+
+        match value {
+            toml::Value::Table(inner) => flatten_table(layer, &key, inner, origin),
+            other => layer.insert(key, convert(other), origin),
+        }
+    }
+}
+
+fn convert(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::Str(s.clone()),
+        toml::Value::Integer(i) => Value::Int(*i),
+        toml::Value::Boolean(b) => Value::Bool(*b),
+        toml::Value::Array(items) => Value::List(items.iter().map(convert).collect()),
+        toml::Value::Float(_) => Value::Unsupported("float"),
+        toml::Value::Datetime(_) => Value::Unsupported("datetime"),
+        toml::Value::Table(_) => Value::Unsupported("table"),
+    }
+}
+
+pub fn single(error: ConfigError) -> ConfigErrors {
+    ConfigErrors(vec![error])
+}
@@ -0,0 +1,769 @@
+//! Typed application configuration.
+//!
+//! Values are layered, later sources overriding earlier ones:
+//!
+//! 1. UCI file `/etc/config/tb-router`, as edited by LuCI
+//! 2. TOML file (`--config <path>`, default `/etc/tb-router/config.toml`)
+//! 3. legacy dotenv file `config` next to the executable
+//! 4. process environment
+//!
+//! Missing files are skipped, except for an explicit `--config` path. No
+//! path depends on the working directory, which is `/` under procd.
+//!
+//! Every key `section.name` can be set through `BOT_SECTION_NAME`; keys from
+//! the original flat format keep their old names (`BOT_TOKEN`, `BOT_LOG`, ...).
+//!
+//! Secrets can instead be read from a file named by the matching `*_file` key
+//! (`telegram.token_file`, `BOT_TOKEN_FILE`), which keeps them out of files
+//! that get copied around.
+
+mod layer;
+mod reader;
+mod uci;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use tracing_subscriber::EnvFilter;
+
+use crate::domain::Secret;
+use crate::domain::messenger::Role;
+
+use layer::{Layer, Value};
+use reader::{FromValue, Reader};
+
+pub use reader::ConfigErrors;
+
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/tb-router/config.toml";
+const UCI_CONFIG_PATH: &str = "/etc/config/tb-router";
+const LEGACY_ENV_FILE: &str = "config";
+const ENV_PREFIX: &str = "BOT_";
+/// Smallest log file worth rotating when log.tmpfs splits the limit up.
+const MIN_TMPFS_FILE_SIZE: u64 = 16 * 1024;
+
+struct Key {
+    path: &'static str,
+    legacy_env: Option<&'static str>,
+}
+
+const fn key(path: &'static str, legacy_env: Option<&'static str>) -> Key {
+    Key { path, legacy_env }
+}
+
+/// Every key the configuration understands; anything else is rejected.
+const KEYS: &[Key] = &[
+    key("telegram.token", Some("BOT_TOKEN")),
+    key("telegram.token_file", Some("BOT_TOKEN_FILE")),
+    key("telegram.allowed_users", Some("BOT_ALLOWED_USERS")),
+    key("telegram.admin_users", None),
+    key("telegram.share_role", None),
+    key("log.dir", None),
+    key("log.filter", Some("BOT_LOG")),
+    key("log.level_timeout", None),
+    key("log.ansi", None),
+    key("log.target", None),
+    key("log.max_size", None),
+    key("log.max_files", None),
+    key("log.compress", None),
+    key("log.tmpfs", None),
+    key("log.tmpfs_limit", None),
+    key("log.output", None),
+    key("log.syslog_socket", None),
+    key("log.syslog_format", None),
+    key("log.syslog_ident", None),
+    key("restart.delay", None),
+    key("restart.max_delay", None),
+    key("restart.max_failures", None),
+    key("service.heartbeat", None),
+    key("router.backend", None),
+    key("router.timeout", None),
+    key("router.scan_timeout", None),
+    key("router.apply_timeout", None),
+    key("router.retries", None),
+    key("router.max_concurrent", None),
+    key("router.fixture_dir", None),
+    key("router.fixture_latency_ms", None),
+    key("cache.system_info", None),
+    key("cache.board_info", None),
+    key("cache.wireless_status", None),
+    key("cache.wifi_clients", None),
+    key("cache.wifi_stations", None),
+    key("cache.dhcp_leases", None),
+    key("survey.interval", None),
+    key("survey.window", None),
+    key("update.url", None),
+    key("update.public_key", None),
+    key("update.max_size", None),
+    key("update.health_timeout", None),
+];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub telegram: TelegramConfig,
+    pub log: LogConfig,
+    pub restart: RestartConfig,
+    pub service: ServiceConfig,
+    pub router: RouterConfig,
+    pub cache: CacheConfig,
+    pub survey: SurveyConfig,
+    pub update: UpdateConfig,
+    /// Non-fatal problems, logged once logging is up.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TelegramConfig {
+    pub token: Secret<String>,
+    pub allowed_users: Vec<u64>,
+    /// Users allowed to run admin commands; each must also be allowed.
+    pub admin_users: Vec<u64>,
+    /// Who may send WiFi QR codes with `/share`.
+    pub share_role: Role,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Defaults to the directory of the executable, or `/tmp` in tmpfs mode.
+    pub dir: Option<PathBuf>,
+    pub filter: String,
+    /// How long a `/loglevel` or SIGUSR1/SIGUSR2 override lasts; 0 = forever.
+    pub level_timeout: Duration,
+    pub ansi: bool,
+    pub target: bool,
+    /// Bytes per file before rotating; 0 disables rotation.
+    pub max_size: u64,
+    pub max_files: usize,
+    pub compress: bool,
+    /// Keep logs in RAM only, so they never wear the flash.
+    pub tmpfs: bool,
+    /// Hard cap on all log files together in tmpfs mode.
+    pub tmpfs_limit: u64,
+    pub output: LogOutput,
+    pub syslog_socket: PathBuf,
+    pub syslog_format: SyslogFormat,
+    pub syslog_ident: String,
+}
+
+/// Where log records go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOutput {
+    File,
+    Syslog,
+    Both,
+}
+
+impl LogOutput {
+    pub fn file(self) -> bool {
+        matches!(self, Self::File | Self::Both)
+    }
+
+    pub fn syslog(self) -> bool {
+        matches!(self, Self::Syslog | Self::Both)
+    }
+}
+
+impl FromValue for LogOutput {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match String::from_value(value)?.as_str() {
+            "file" => Ok(Self::File),
+            "syslog" => Ok(Self::Syslog),
+            "both" => Ok(Self::Both),
+            other => Err(format!("expected file, syslog or both, got '{other}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogFormat {
+    /// BSD framing, what logd and busybox syslogd expect.
+    Rfc3164,
+    Rfc5424,
+}
+
+impl FromValue for SyslogFormat {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match String::from_value(value)?.as_str() {
+            "rfc3164" => Ok(Self::Rfc3164),
+            "rfc5424" => Ok(Self::Rfc5424),
+            other => Err(format!("expected rfc3164 or rfc5424, got '{other}'")),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            filter: "info".to_string(),
+            level_timeout: Duration::from_secs(30 * 60),
+            ansi: false,
+            target: true,
+            max_size: 1024 * 1024,
+            max_files: 3,
+            compress: false,
+            tmpfs: false,
+            tmpfs_limit: 512 * 1024,
+            output: LogOutput::File,
+            syslog_socket: PathBuf::from("/dev/log"),
+            syslog_format: SyslogFormat::Rfc3164,
+            syslog_ident: "tb-router".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartConfig {
+    pub delay: Duration,
+    pub max_delay: Duration,
+    /// Consecutive failures tolerated before giving up.
+    pub max_failures: u32,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(300),
+            max_failures: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceConfig {
+    /// Exit for a procd restart after this long without a bot heartbeat;
+    /// zero disables the watchdog.
+    pub heartbeat: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouterConfig {
+    pub backend: RouterBackend,
+    /// How long a `ubus` call may take before it is killed.
+    pub timeout: Duration,
+    /// The same for neighbour scans, which take seconds by nature.
+    pub scan_timeout: Duration,
+    /// How long WiFi interfaces get to come back after `/wifi_set` before
+    /// the change is undone.
+    pub apply_timeout: Duration,
+    /// Extra attempts after a timeout or a lost ubus connection.
+    pub retries: u32,
+    /// Cap on `ubus` processes running at once.
+    pub max_concurrent: usize,
+    /// JSON fixtures served by the `fixture` backend.
+    pub fixture_dir: Option<PathBuf>,
+    /// Delay added to every fixture call, to mimic a slow router.
+    pub fixture_latency: Duration,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            backend: RouterBackend::default(),
+            timeout: Duration::from_secs(5),
+            scan_timeout: Duration::from_secs(20),
+            apply_timeout: Duration::from_secs(30),
+            retries: 2,
+            max_concurrent: 4,
+            fixture_dir: None,
+            fixture_latency: Duration::ZERO,
+        }
+    }
+}
+
+/// Where router data comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RouterBackend {
+    /// `ubus` on the router itself.
+    #[default]
+    Ubus,
+    /// Files from `router.fixture_dir`, for development without a router.
+    Fixture,
+}
+
+impl FromValue for Role {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match String::from_value(value)?.as_str() {
+            "user" => Ok(Self::User),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("expected user or admin, got '{other}'")),
+        }
+    }
+}
+
+impl FromValue for RouterBackend {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match String::from_value(value)?.as_str() {
+            "ubus" => Ok(Self::Ubus),
+            "fixture" => Ok(Self::Fixture),
+            other => Err(format!("expected ubus or fixture, got '{other}'")),
+        }
+    }
+}
+
+/// How long each kind of router answer is reused; zero still merges
+/// concurrent identical calls but keeps nothing.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub system_info: Duration,
+    pub board_info: Duration,
+    pub wireless_status: Duration,
+    pub wifi_clients: Duration,
+    pub wifi_stations: Duration,
+    pub dhcp_leases: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            system_info: Duration::from_secs(2),
+            board_info: Duration::from_secs(300),
+            wireless_status: Duration::from_secs(10),
+            wifi_clients: Duration::from_secs(2),
+            wifi_stations: Duration::from_secs(2),
+            dhcp_leases: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Background sampling of channel use for `/survey`.
+#[derive(Debug, Clone)]
+pub struct SurveyConfig {
+    /// Zero turns sampling off; `/survey` then only compares its own readings.
+    pub interval: Duration,
+    /// How far back peaks and averages reach.
+    pub window: Duration,
+}
+
+impl Default for SurveyConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            window: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateConfig {
+    /// Where the release binary is published; `{arch}` is replaced with the
+    /// CPU architecture. `<url>.manifest` and `<url>.sig` must sit beside it.
+    pub url: Option<String>,
+    /// Ed25519 public key the manifest's `.sig` file is checked against.
+    pub public_key: Vec<u8>,
+    pub max_size: u64,
+    /// How long the bots of a new binary must keep running before it is trusted.
+    pub health_timeout: Duration,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            public_key: Vec::new(),
+            max_size: 32 * 1024 * 1024,
+            health_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl Config {
+    /// Loads and validates the configuration.
+    ///
+    /// An explicitly given `path` must exist; the default one is optional.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let layers = load_layers(path)?;
+        Ok(Self::from_layers(&layers)?)
+    }
+
+    /// Reads a lone TOML document, for tests elsewhere.
+    #[cfg(test)]
+    pub fn from_toml(text: &str) -> Result<Self, ConfigErrors> {
+        Self::from_layers(&[Layer::from_toml(text, "test")?])
+    }
+
+    fn from_layers(layers: &[Layer]) -> Result<Self, ConfigErrors> {
+        let mut r = Reader::new(layers);
+
+        let telegram = TelegramConfig::read(&mut r);
+        let log = LogConfig::read(&mut r);
+        let restart = RestartConfig::read(&mut r);
+        let service = ServiceConfig::read(&mut r, &restart);
+        let router = RouterConfig::read(&mut r);
+        let cache = CacheConfig::read(&mut r);
+        let survey = SurveyConfig::read(&mut r);
+        let update = UpdateConfig::read(&mut r);
+
+        let known: Vec<&str> = KEYS.iter().map(|k| k.path).collect();
+        let warnings = r.finish(&known)?;
+
+        Ok(Self {
+            telegram,
+            log,
+            restart,
+            service,
+            router,
+            cache,
+            survey,
+            update,
+            warnings: warnings.iter().map(ToString::to_string).collect(),
+        })
+    }
+}
+
+impl TelegramConfig {
+    fn read(r: &mut Reader) -> Self {
+        let config = Self {
+            token: r.required_secret("telegram.token"),
+            allowed_users: r.required("telegram.allowed_users"),
+            admin_users: r.or("telegram.admin_users", Vec::new()),
+            share_role: r.or("telegram.share_role", Role::default()),
+        };
+        r.check(
+            "telegram.allowed_users",
+            !config.allowed_users.is_empty(),
+            "must list at least one user id",
+        );
+        r.check(
+            "telegram.admin_users",
+            config
+                .admin_users
+                .iter()
+                .all(|id| config.allowed_users.contains(id)),
+            "every admin must also be in telegram.allowed_users",
+        );
+        config
+    }
+}
+
+impl LogConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        let config = Self {
+            dir: r.optional("log.dir"),
+            filter: r.or("log.filter", d.filter),
+            level_timeout: r.or("log.level_timeout", d.level_timeout),
+            ansi: r.or("log.ansi", d.ansi),
+            target: r.or("log.target", d.target),
+            max_size: r.or("log.max_size", d.max_size),
+            max_files: r.or("log.max_files", d.max_files),
+            compress: r.or("log.compress", d.compress),
+            tmpfs: r.or("log.tmpfs", d.tmpfs),
+            tmpfs_limit: r.or("log.tmpfs_limit", d.tmpfs_limit),
+            output: r.or("log.output", d.output),
+            syslog_socket: r.or("log.syslog_socket", d.syslog_socket),
+            syslog_format: r.or("log.syslog_format", d.syslog_format),
+            syslog_ident: r.or("log.syslog_ident", d.syslog_ident),
+        };
+        let in_tmp = config
+            .dir
+            .as_ref()
+            .is_none_or(|dir| dir.starts_with("/tmp"));
+        r.check(
+            "log.dir",
+            !config.tmpfs || in_tmp,
+            "must be under /tmp when log.tmpfs is on",
+        );
+        // Each of the max_files + 1 files gets an equal share, as in rotation.
+        let per_file = config.tmpfs_limit / (config.max_files as u64 + 1);
+        r.check(
+            "log.tmpfs_limit",
+            !config.tmpfs || per_file >= MIN_TMPFS_FILE_SIZE,
+            &format!(
+                "must leave at least {MIN_TMPFS_FILE_SIZE} bytes for each of log.max_files + 1 files"
+            ),
+        );
+        r.check(
+            "log.syslog_ident",
+            !config.syslog_ident.is_empty() && !config.syslog_ident.contains(char::is_whitespace),
+            "must be a non-empty word",
+        );
+        if let Err(e) = EnvFilter::try_new(&config.filter) {
+            r.error(
+                "log.filter",
+                format!("invalid filter '{}': {e}", config.filter),
+            );
+        }
+        config
+    }
+
+    /// Takes the settings that only apply at startup from `running`, and
+    /// returns the keys whose new values were set aside until a restart.
+    ///
+    /// The log outputs are built once, so only the filter can be reloaded.
+    pub fn keep_startup_settings(&mut self, running: &LogConfig) -> Vec<&'static str> {
+        let mut kept = Vec::new();
+        macro_rules! keep {
+            ($($field:ident),*) => {$(
+                if self.$field != running.$field {
+                    self.$field = running.$field.clone();
+                    kept.push(concat!("log.", stringify!($field)));
+                }
+            )*};
+        }
+        keep!(
+            dir,
+            ansi,
+            target,
+            max_size,
+            max_files,
+            compress,
+            tmpfs,
+            tmpfs_limit,
+            output,
+            syslog_socket,
+            syslog_format,
+            syslog_ident
+        );
+        kept
+    }
+}
+
+impl RestartConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        let config = Self {
+            delay: r.or("restart.delay", d.delay),
+            max_delay: r.or("restart.max_delay", d.max_delay),
+            max_failures: r.or("restart.max_failures", d.max_failures),
+        };
+        r.check(
+            "restart.max_delay",
+            config.max_delay >= config.delay,
+            "must not be less than restart.delay",
+        );
+        config
+    }
+}
+
+impl ServiceConfig {
+    fn read(r: &mut Reader, restart: &RestartConfig) -> Self {
+        let config = Self {
+            heartbeat: r.or("service.heartbeat", Duration::ZERO),
+        };
+        r.check(
+            "service.heartbeat",
+            config.heartbeat.is_zero() || config.heartbeat > restart.max_delay,
+            "must be longer than restart.max_delay, or 0",
+        );
+        config
+    }
+}
+
+impl RouterConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        let config = Self {
+            backend: r.or("router.backend", d.backend),
+            timeout: r.or("router.timeout", d.timeout),
+            scan_timeout: r.or("router.scan_timeout", d.scan_timeout),
+            apply_timeout: r.or("router.apply_timeout", d.apply_timeout),
+            retries: r.or("router.retries", d.retries),
+            max_concurrent: r.or("router.max_concurrent", d.max_concurrent),
+            fixture_dir: r.optional("router.fixture_dir"),
+            fixture_latency: Duration::from_millis(r.or("router.fixture_latency_ms", 0)),
+        };
+        r.check(
+            "router.timeout",
+            !config.timeout.is_zero(),
+            "must be positive",
+        );
+        r.check(
+            "router.scan_timeout",
+            !config.scan_timeout.is_zero(),
+            "must be positive",
+        );
+        r.check(
+            "router.apply_timeout",
+            !config.apply_timeout.is_zero(),
+            "must be positive",
+        );
+        r.check(
+            "router.max_concurrent",
+            config.max_concurrent > 0,
+            "must be at least 1",
+        );
+        if config.backend == RouterBackend::Fixture {
+            r.check(
+                "router.fixture_dir",
+                config.fixture_dir.as_ref().is_some_and(|dir| dir.is_dir()),
+                "must be an existing directory for the fixture backend",
+            );
+        }
+        config
+    }
+}
+
+impl SurveyConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        let config = Self {
+            interval: r.or("survey.interval", d.interval),
+            window: r.or("survey.window", d.window),
+        };
+        r.check(
+            "survey.window",
+            !config.window.is_zero(),
+            "must be positive",
+        );
+        config
+    }
+}
+
+impl CacheConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        Self {
+            system_info: r.or("cache.system_info", d.system_info),
+            board_info: r.or("cache.board_info", d.board_info),
+            wireless_status: r.or("cache.wireless_status", d.wireless_status),
+            wifi_clients: r.or("cache.wifi_clients", d.wifi_clients),
+            wifi_stations: r.or("cache.wifi_stations", d.wifi_stations),
+            dhcp_leases: r.or("cache.dhcp_leases", d.dhcp_leases),
+        }
+    }
+}
+
+impl UpdateConfig {
+    fn read(r: &mut Reader) -> Self {
+        let d = Self::default();
+        let url: Option<String> = r.optional("update.url");
+        let key: Option<String> = r.optional("update.public_key");
+        let public_key = match key.as_deref().map(|k| hex::decode(k.trim())) {
+            Some(Ok(public_key)) => public_key,
+            Some(Err(e)) => {
+                r.error("update.public_key", format!("invalid hex: {e}"));
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        let config = Self {
+            public_key,
+            url,
+            max_size: r.or("update.max_size", d.max_size),
+            health_timeout: r.or("update.health_timeout", d.health_timeout),
+        };
+
+        r.check(
+            "update.url",
+            config
+                .url
+                .as_ref()
+                .is_none_or(|u| u.starts_with("https://") || u.starts_with("http://")),
+            "must be an http:// or https:// URL",
+        );
+        if config.url.is_some() || key.is_some() {
+            r.check(
+                "update.public_key",
+                config.public_key.len() == 32,
+                "must be a hex-encoded 32-byte Ed25519 public key",
+            );
+        }
+        config
+    }
+}
+
+fn load_layers(path: Option<&Path>) -> anyhow::Result<Vec<Layer>> {
+    let mut layers = Vec::new();
+
+    let uci = Path::new(UCI_CONFIG_PATH);
+    if uci.is_file() {
+        layers.push(uci::load(uci)?);
+    }
+
+    let default = Path::new(DEFAULT_CONFIG_PATH);
+    match path {
+        Some(path) => layers.push(Layer::from_toml_file(path)?),
+        None if default.exists() => layers.push(Layer::from_toml_file(default)?),
+        None => {}
+    }
+
+    layers.push(Layer::from_env(env_vars()?));
+    Ok(layers)
+}
+
+/// Process environment on top of the legacy dotenv file, if present.
+fn env_vars() -> anyhow::Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+
+    if let Some(path) = legacy_env_file()
+        && path.is_file()
+    {
+        vars = dotenvy::from_path_iter(&path)
+            .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+    }
+
+    vars.extend(std::env::vars());
+    Ok(vars)
+}
+
+/// The old deployment kept the dotenv file beside the binary.
+fn legacy_env_file() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(LEGACY_ENV_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let uci = uci::parse(
+            "config telegram\n\toption token 'uci'\n\tlist allowed_users '1'\n\
+             config log\n\toption ansi '1'\n\toption filter 'debug'\n",
+            "uci",
+        )
+        .unwrap();
+        let toml = Layer::from_toml("[telegram]\ntoken = \"toml\"\n", "toml").unwrap();
+        let env = Layer::from_env([("BOT_LOG".to_string(), "warn".to_string())]);
+
+        let config = Config::from_layers(&[uci, toml, env]).unwrap();
+
+        assert_eq!(config.telegram.token.expose(), "toml");
+        assert_eq!(config.telegram.allowed_users, [1]);
+        assert!(config.log.ansi);
+        assert_eq!(config.log.filter, "warn");
+    }
+
+    #[test]
+    fn collects_every_error_with_its_key() {
+        let toml = "[telegram]\nallowed_users = [1]\nadmin_users = [2]\n\
+                    [log]\nfilter = \"info,=\"\nmax_files = \"many\"\n\
+                    [update]\npublic_key = \"not hex\"\n\
+                    [bogus]\nkey = 1\n";
+
+        let errors = Config::from_toml(toml).unwrap_err();
+
+        let keys: Vec<&str> = errors.0.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "telegram.token",
+                "telegram.admin_users",
+                "log.max_files",
+                "log.filter",
+                "update.public_key",
+                "bogus.key",
+            ]
+        );
+        assert!(errors.0[4].message.starts_with("invalid hex"), "{errors}");
+    }
+
+    #[test]
+    fn splits_the_tmpfs_limit_across_log_files() {
+        let config = |limit: u64| {
+            Config::from_toml(&format!(
+                "[telegram]\ntoken = \"t\"\nallowed_users = [1]\n\
+                 [log]\ntmpfs = true\nmax_files = 3\ntmpfs_limit = {limit}\n"
+            ))
+        };
+
+        assert!(config(4 * MIN_TMPFS_FILE_SIZE).is_ok());
+        let errors = config(4 * MIN_TMPFS_FILE_SIZE - 1).unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].key, "log.tmpfs_limit");
+    }
+}
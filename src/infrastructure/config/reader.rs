@@ -0,0 +1,347 @@
+//! Typed reads over configuration layers with error collection.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::domain::Secret;
+
+use super::layer::{Entry, Layer, Value};
+
+const WORLD_READABLE: u32 = 0o004;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// All problems found in a configuration, reported together.
+#[derive(Debug, thiserror::Error)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl std::fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid configuration ({} error(s))", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  {error}")?;
+        }
+        Ok(())
+    }
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, String>;
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Str(s) => Ok(s.clone()),
+            other => Err(mismatch("a string", other)),
+        }
+    }
+}
+
+impl FromValue for PathBuf {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        String::from_value(value).map(PathBuf::from)
+    }
+}
+
+/// Strings accept the UCI spellings `1`/`0`, `yes`/`no` and `on`/`off` too.
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            Value::Str(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" | "on" => Ok(true),
+                "false" | "0" | "no" | "off" => Ok(false),
+                _ => Err(format!("expected true or false, got '{s}'")),
+            },
+            other => Err(mismatch("a boolean", other)),
+        }
+    }
+}
+
+macro_rules! int_from_value {
+    ($($ty:ty),*) => {$(
+        impl FromValue for $ty {
+            fn from_value(value: &Value) -> Result<Self, String> {
+                match value {
+                    Value::Int(i) => <$ty>::try_from(*i)
+                        .map_err(|_| format!("{i} is out of range")),
+                    Value::Str(s) => s
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("expected an integer, got '{s}'")),
+                    other => Err(mismatch("an integer", other)),
+                }
+            }
+        }
+    )*};
+}
+
+int_from_value!(u32, u64, usize);
+
+/// Durations are given in whole seconds.
+impl FromValue for Duration {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        u64::from_value(value).map(Duration::from_secs)
+    }
+}
+
+/// Lists are TOML arrays or comma-separated strings (for env variables).
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let items: Vec<Value> = match value {
+            Value::List(items) => items.clone(),
+            Value::Str(s) => s
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::Str(item.to_string()))
+                .collect(),
+            other => return Err(mismatch("a list", other)),
+        };
+
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_value(item).map_err(|e| format!("item {}: {e}", i + 1)))
+            .collect()
+    }
+}
+
+fn mismatch(expected: &str, got: &Value) -> String {
+    let got = match got {
+        Value::Str(_) => "a string",
+        Value::Int(_) => "an integer",
+        Value::Bool(_) => "a boolean",
+        Value::List(_) => "a list",
+        Value::Unsupported(kind) => kind,
+    };
+    format!("expected {expected}, got {got}")
+}
+
+/// Reads keys from the highest-precedence layer that has them.
+///
+/// Errors are collected instead of returned, so that a single pass reports
+/// every problem; placeholders are returned for values that failed.
+pub struct Reader<'a> {
+    layers: &'a [Layer],
+    errors: Vec<ConfigError>,
+    warnings: Vec<ConfigError>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(layers: &'a [Layer]) -> Self {
+        Self {
+            layers,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<&'a Entry> {
+        self.lookup_indexed(key).map(|(_, entry)| entry)
+    }
+
+    fn lookup_indexed(&self, key: &str) -> Option<(usize, &'a Entry)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, layer)| layer.get(key).map(|entry| (i, entry)))
+    }
+
+    pub fn optional<T: FromValue>(&mut self, key: &str) -> Option<T> {
+        let entry = self.lookup(key)?;
+        match T::from_value(&entry.value) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.error(key, format!("{message} (from {})", entry.origin));
+                None
+            }
+        }
+    }
+
+    pub fn or<T: FromValue>(&mut self, key: &str, default: T) -> T {
+        self.optional(key).unwrap_or(default)
+    }
+
+    pub fn required<T: FromValue + Default>(&mut self, key: &str) -> T {
+        if self.lookup(key).is_none() {
+            self.error(key, "missing required value");
+            return T::default();
+        }
+        self.optional(key).unwrap_or_default()
+    }
+
+    /// Reads a secret from `key`, or from the file named by `<key>_file`.
+    ///
+    /// If both are set, the one from the higher-precedence source wins.
+    pub fn required_secret(&mut self, key: &str) -> Secret<String> {
+        let file_key = format!("{key}_file");
+        let inline = self.lookup_indexed(key).map(|(i, _)| i);
+        let file = self.lookup_indexed(&file_key).map(|(i, _)| i);
+
+        match (inline, file) {
+            (Some(a), Some(b)) if a == b => {
+                self.error(key, format!("set either {key} or {file_key}, not both"));
+                Secret::default()
+            }
+            (Some(a), Some(b)) if b > a => self.secret_file(&file_key),
+            (Some(_), _) => Secret::new(self.required(key)),
+            (None, Some(_)) => self.secret_file(&file_key),
+            (None, None) => {
+                self.error(key, format!("missing required value (or {file_key})"));
+                Secret::default()
+            }
+        }
+    }
+
+    fn secret_file(&mut self, file_key: &str) -> Secret<String> {
+        let Some(path) = self.optional::<PathBuf>(file_key) else {
+            return Secret::default();
+        };
+
+        match read_secret_file(&path) {
+            Ok((secret, world_readable)) => {
+                if world_readable {
+                    let message = format!("{} is world-readable", path.display());
+                    self.warnings.push(ConfigError::new(file_key, message));
+                }
+                secret
+            }
+            Err(message) => {
+                self.error(file_key, format!("{}: {message}", path.display()));
+                Secret::default()
+            }
+        }
+    }
+
+    /// Records `message` against `key` unless `ok` holds.
+    ///
+    /// Skipped if reading `key` already failed, as the check would only see
+    /// the placeholder.
+    pub fn check(&mut self, key: &str, ok: bool, message: &str) {
+        if !ok && !self.errors.iter().any(|e| e.key == key) {
+            self.error(key, message);
+        }
+    }
+
+    pub fn error(&mut self, key: &str, message: impl Into<String>) {
+        self.errors.push(ConfigError::new(key, message));
+    }
+
+    /// Returns the collected warnings, or every error if there were any.
+    pub fn finish(mut self, known: &[&str]) -> Result<Vec<ConfigError>, ConfigErrors> {
+        for layer in self.layers {
+            for (key, entry) in layer.keys() {
+                if !known.contains(&key) {
+                    let message = format!("unknown key (from {})", entry.origin);
+                    self.errors.push(ConfigError::new(key, message));
+                }
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(self.warnings)
+        } else {
+            Err(ConfigErrors(self.errors))
+        }
+    }
+}
+
+/// Reads a secret file, dropping the whitespace editors tend to add.
+fn read_secret_file(path: &Path) -> Result<(Secret<String>, bool), String> {
+    let mode = std::fs::metadata(path)
+        .map_err(|e| e.to_string())?
+        .permissions()
+        .mode();
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value = content.trim();
+
+    if value.is_empty() {
+        return Err("file is empty".to_string());
+    }
+    Ok((Secret::new(value.to_string()), mode & WORLD_READABLE != 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    const KEYS: [&str; 2] = ["telegram.token", "telegram.token_file"];
+
+    fn secret_file(dir: &TempDir, content: &str, mode: u32) -> Layer {
+        let path = dir.join("token");
+        std::fs::write(&path, content).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        let toml = format!("[telegram]\ntoken_file = \"{}\"\n", path.display());
+        Layer::from_toml(&toml, "file").unwrap()
+    }
+
+    fn inline(token: &str) -> Layer {
+        Layer::from_toml(&format!("[telegram]\ntoken = \"{token}\"\n"), "inline").unwrap()
+    }
+
+    #[test]
+    fn later_secret_file_overrides_the_inline_value() {
+        let dir = TempDir::new("secret-override");
+        let layers = [inline("inline"), secret_file(&dir, " s3cret\t\r\n", 0o600)];
+        let mut r = Reader::new(&layers);
+
+        assert_eq!(r.required_secret("telegram.token").expose(), "s3cret");
+        assert!(r.finish(&KEYS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_inline_value_and_file_in_one_layer() {
+        let dir = TempDir::new("secret-both");
+        let path = dir.join("token");
+        std::fs::write(&path, "s3cret").unwrap();
+        let toml = format!(
+            "[telegram]\ntoken = \"inline\"\ntoken_file = \"{}\"\n",
+            path.display()
+        );
+        let layers = [Layer::from_toml(&toml, "both").unwrap()];
+        let mut r = Reader::new(&layers);
+
+        r.required_secret("telegram.token");
+        let errors = r.finish(&KEYS).unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].key, "telegram.token");
+    }
+
+    #[test]
+    fn warns_about_world_readable_secret_files() {
+        let dir = TempDir::new("secret-mode");
+        let layers = [secret_file(&dir, "s3cret\n", 0o644)];
+        let mut r = Reader::new(&layers);
+
+        assert_eq!(r.required_secret("telegram.token").expose(), "s3cret");
+        let warnings = r.finish(&KEYS).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].key, "telegram.token_file");
+        assert!(warnings[0].message.ends_with("is world-readable"));
+    }
+}
@@ -0,0 +1,229 @@
+//! OpenWrt UCI config source (`/etc/config/tb-router`).
+//!
+//! `config <section>` blocks map onto config sections, `option` and `list`
+//! lines onto keys within them:
+//!
+//! ```text
+//! config telegram 'main'
+//!     option token '123:abc'
+//!     list allowed_users '111'
+//!     list allowed_users '222'
+//! ```
+//!
+//! Section names are ignored; each section type may appear only once.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::ConfigErrors;
+use super::layer::{Layer, Value, single};
+use super::reader::ConfigError;
+
+pub fn load(path: &Path) -> Result<Layer, ConfigErrors> {
+    let origin = path.display().to_string();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| single(ConfigError::new(&origin, format!("cannot read file: {e}"))))?;
+    parse(&text, &origin)
+}
+
+pub fn parse(text: &str, origin: &str) -> Result<Layer, ConfigErrors> {
+    let mut parser = Parser::default();
+
+    for (index, line) in text.lines().enumerate() {
+        let at = format!("{origin}:{}", index + 1);
+        if let Err(message) = parser.line(line, &at) {
+            parser.errors.push(ConfigError::new(at, message));
+        }
+    }
+
+    if parser.errors.is_empty() {
+        Ok(parser.layer)
+    } else {
+        Err(ConfigErrors(parser.errors))
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    layer: Layer,
+    errors: Vec<ConfigError>,
+    section: Option<String>,
+    seen_sections: HashSet<String>,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str, at: &str) -> Result<(), String> {
+        let words = split_words(line)?;
+        let Some((keyword, args)) = words.split_first() else {
+            return Ok(());
+        };
+
+        match (keyword.as_str(), args) {
+            ("package", [_]) => Ok(()),
+            ("config", [kind] | [kind, _]) => self.section(kind),
+            ("option", [name, value]) => {
+                let key = self.key(name)?;
+                self.layer.insert(key, Value::Str(value.clone()), at);
+                Ok(())
+            }
+            ("list", [name, value]) => {
+                let key = self.key(name)?;
+                self.push_list(key, value, at);
+                Ok(())
+            }
+            ("package" | "config" | "option" | "list", _) => {
+                Err(format!("wrong number of arguments to '{keyword}'"))
+            }
+            (other, _) => Err(format!("unknown statement '{other}'")),
+        }
+    }
+
+    fn section(&mut self, kind: &str) -> Result<(), String> {
+        self.section = Some(kind.to_string());
+        if self.seen_sections.insert(kind.to_string()) {
+            Ok(())
+        } else {
+            Err(format!("duplicate section '{kind}'"))
+        }
+    }
+
+    fn key(&self, name: &str) -> Result<String, String> {
+        match &self.section {
+            Some(section) => Ok(format!("{section}.{name}")),
+            None => Err(format!("'{name}' outside of a config section")),
+        }
+    }
+
+    fn push_list(&mut self, key: String, value: &str, at: &str) {
+        let mut items = match self.layer.get(&key).map(|e| &e.value) {
+            Some(Value::List(items)) => items.clone(),
+            _ => Vec::new(),
+        };
+        items.push(Value::Str(value.to_string()));
+        self.layer.insert(key, Value::List(items), at);
+    }
+}
+
+/// Splits a line into shell-like words.
+///
+/// Supports single quotes (literal), double quotes (with backslash escapes),
+/// backslash escapes outside quotes, adjacent quoted parts and `#` comments.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(current.take()),
+            '#' if current.is_none() => break,
+            '\'' => {
+                let word = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => word.push(c),
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => current.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(current);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(layer: &Layer, key: &str) -> Value {
+        layer.get(key).expect(key).value.clone()
+    }
+
+    fn str_list(items: &[&str]) -> Value {
+        Value::List(items.iter().map(|s| Value::Str(s.to_string())).collect())
+    }
+
+    #[test]
+    fn maps_sections_options_and_lists() {
+        let text = "\
+package tb-router
+
+config telegram 'main'
+\toption token '123:abc'
+\tlist allowed_users '111'
+\tlist allowed_users \"222\"
+
+# logging
+config log
+\toption filter info,teloxide=warn
+\toption ansi '0'
+";
+        let layer = parse(text, "uci").unwrap();
+
+        assert_eq!(
+            value(&layer, "telegram.token"),
+            Value::Str("123:abc".into())
+        );
+        assert_eq!(
+            value(&layer, "telegram.allowed_users"),
+            str_list(&["111", "222"])
+        );
+        assert_eq!(
+            value(&layer, "log.filter"),
+            Value::Str("info,teloxide=warn".into())
+        );
+        assert_eq!(layer.get("log.ansi").unwrap().origin, "uci:11");
+    }
+
+    #[test]
+    fn handles_quoting() {
+        assert_eq!(
+            split_words(r#"option name 'it'\''s' "a \"b\"" c\ d # note"#).unwrap(),
+            ["option", "name", "it's", "a \"b\"", "c d"]
+        );
+        assert_eq!(
+            split_words("option empty ''").unwrap(),
+            ["option", "empty", ""]
+        );
+    }
+
+    #[test]
+    fn reports_every_error_with_line_numbers() {
+        let text = "\
+option token 'x'
+config telegram
+\toption token 'unterminated
+\toption
+config telegram
+\tvalue x y
+";
+        let errors = parse(text, "uci").unwrap_err().0;
+        let keys: Vec<_> = errors.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["uci:1", "uci:3", "uci:4", "uci:5", "uci:6"]);
+        assert!(errors[0].message.contains("outside of a config section"));
+        assert!(errors[3].message.contains("duplicate section"));
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 /// Trait for filesystem operations
@@ -7,8 +8,22 @@ pub trait FileSystem: Send + Sync {
     fn current_exe(&self) -> std::io::Result<PathBuf>;
 
     fn create_dir_all(&self, path: &Path) -> std::io::Result<()>;
+
+    /// Opens `path` for appending, creating it if needed.
+    fn append(&self, path: &Path) -> std::io::Result<Box<dyn Write + Send>>;
+
+    fn file_size(&self, path: &Path) -> std::io::Result<u64>;
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+
+    fn write(&self, path: &Path, data: &[u8]) -> std::io::Result<()>;
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()>;
 }
 
+#[derive(Clone, Copy)]
 pub struct RealFileSystem;
 
 impl FileSystem for RealFileSystem {
@@ -19,6 +34,34 @@ impl FileSystem for RealFileSystem {
     fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
         std::fs::create_dir_all(path)
     }
+
+    fn append(&self, path: &Path) -> std::io::Result<Box<dyn Write + Send>> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn file_size(&self, path: &Path) -> std::io::Result<u64> {
+        std::fs::metadata(path).map(|m| m.len())
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> std::io::Result<()> {
+        std::fs::write(path, data)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::remove_file(path)
+    }
 }
 
 impl Default for RealFileSystem {
@@ -0,0 +1,250 @@
+//! Runtime log filter overrides that revert on their own.
+//!
+//! An override (from `/loglevel` or SIGUSR1/SIGUSR2) replaces the configured
+//! filter until `log.level_timeout` passes, so debug logging is never left on
+//! by accident.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::domain::{LogError, LogLevelControl, LogLevelState};
+
+pub(super) type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Default levels from least to most verbose.
+const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: FilterHandle,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    configured: String,
+    current: String,
+    /// How long an override lasts; zero keeps it until reset.
+    timeout: Duration,
+    revert: Option<Revert>,
+    generation: u64,
+}
+
+struct Revert {
+    at: Instant,
+    task: JoinHandle<()>,
+    /// Tells a timer that already fired apart from the current one.
+    generation: u64,
+}
+
+impl LogLevel {
+    pub(super) fn new(handle: FilterHandle, configured: String, timeout: Duration) -> Self {
+        let state = State {
+            current: configured.clone(),
+            configured,
+            timeout,
+            revert: None,
+            generation: 0,
+        };
+        Self {
+            handle,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Installs a new configured filter, dropping any override.
+    pub fn configure(&self, filter: &str, timeout: Duration) -> Result<(), LogError> {
+        let mut state = self.lock();
+        self.apply(&mut state, filter)?;
+        state.configured = filter.to_string();
+        state.timeout = timeout;
+        cancel_revert(&mut state);
+        Ok(())
+    }
+
+    /// Moves the default level `steps` levels up (more verbose) or down.
+    ///
+    /// Per-target directives are kept as they are.
+    pub fn bump(&self, steps: isize) -> Result<LogLevelState, LogError> {
+        let filter = shift_default_level(&self.lock().current, steps);
+        self.set(&filter)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn apply(&self, state: &mut State, filter: &str) -> Result<(), LogError> {
+        let parsed = EnvFilter::try_new(filter)
+            .map_err(|e| LogError::Filter(format!("invalid filter '{filter}': {e}")))?;
+        self.handle
+            .reload(parsed)
+            .map_err(|e| LogError::Filter(e.to_string()))?;
+        state.current = filter.to_string();
+        Ok(())
+    }
+
+    fn schedule_revert(&self, state: &mut State) {
+        cancel_revert(state);
+        if state.timeout.is_zero() {
+            return;
+        }
+
+        state.generation += 1;
+        let generation = state.generation;
+        let this = self.clone();
+        let timeout = state.timeout;
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            this.revert(generation);
+        });
+        state.revert = Some(Revert {
+            at: Instant::now() + timeout,
+            task,
+            generation,
+        });
+    }
+
+    fn revert(&self, generation: u64) {
+        let mut state = self.lock();
+        if state
+            .revert
+            .as_ref()
+            .is_none_or(|r| r.generation != generation)
+        {
+            return;
+        }
+        state.revert = None;
+        let configured = state.configured.clone();
+        match self.apply(&mut state, &configured) {
+            Ok(()) => tracing::info!("Log filter override expired, back to '{configured}'"),
+            Err(e) => tracing::error!("Failed to restore log filter: {e}"),
+        }
+    }
+
+    fn snapshot(state: &State) -> LogLevelState {
+        LogLevelState {
+            filter: state.current.clone(),
+            configured: state.configured.clone(),
+            revert_in: state
+                .revert
+                .as_ref()
+                .map(|r| r.at.saturating_duration_since(Instant::now())),
+        }
+    }
+}
+
+impl LogLevelControl for LogLevel {
+    fn state(&self) -> LogLevelState {
+        Self::snapshot(&self.lock())
+    }
+
+    fn set(&self, filter: &str) -> Result<LogLevelState, LogError> {
+        let mut state = self.lock();
+        self.apply(&mut state, filter)?;
+        if state.current == state.configured {
+            cancel_revert(&mut state);
+        } else {
+            self.schedule_revert(&mut state);
+        }
+        Ok(Self::snapshot(&state))
+    }
+
+    fn reset(&self) -> Result<LogLevelState, LogError> {
+        let mut state = self.lock();
+        let configured = state.configured.clone();
+        self.apply(&mut state, &configured)?;
+        cancel_revert(&mut state);
+        Ok(Self::snapshot(&state))
+    }
+}
+
+fn cancel_revert(state: &mut State) {
+    if let Some(revert) = state.revert.take() {
+        revert.task.abort();
+    }
+}
+
+/// Replaces the bare level directive in `filter`, adding one if missing.
+///
+/// A filter without one logs nothing outside its targets, which counts as
+/// `off`.
+fn shift_default_level(filter: &str, steps: isize) -> String {
+    let mut directives: Vec<&str> = filter
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .collect();
+    let position = directives.iter().position(|d| level_index(d).is_some());
+    let current = position
+        .and_then(|i| level_index(directives[i]))
+        .unwrap_or(0);
+
+    let shifted = current.saturating_add_signed(steps).min(LEVELS.len() - 1);
+    let level = LEVELS[shifted];
+    match position {
+        Some(i) => directives[i] = level,
+        None => directives.insert(0, level),
+    }
+    directives.join(",")
+}
+
+fn level_index(directive: &str) -> Option<usize> {
+    LEVELS
+        .iter()
+        .position(|level| directive.eq_ignore_ascii_case(level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_only_the_default_level() {
+        assert_eq!(
+            shift_default_level("info,teloxide=warn", 1),
+            "debug,teloxide=warn"
+        );
+        assert_eq!(
+            shift_default_level("teloxide=warn,WARN", -1),
+            "teloxide=warn,error"
+        );
+        assert_eq!(
+            shift_default_level("teloxide=warn", 2),
+            "warn,teloxide=warn"
+        );
+        assert_eq!(shift_default_level("trace", 1), "trace");
+        assert_eq!(shift_default_level("error", -3), "off");
+    }
+
+    #[tokio::test]
+    async fn override_reverts_after_timeout() {
+        let (_layer, handle) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let level = LogLevel::new(handle, "info".into(), Duration::from_millis(50));
+
+        let state = level.set("debug,teloxide=warn").unwrap();
+        assert_eq!(state.filter, "debug,teloxide=warn");
+        assert!(state.revert_in.is_some());
+        assert!(level.set("teloxide=loud").is_err());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let state = level.state();
+        assert_eq!(state.filter, "info");
+        assert_eq!(state.revert_in, None);
+    }
+
+    #[tokio::test]
+    async fn reset_cancels_override() {
+        let (_layer, handle) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let level = LogLevel::new(handle, "info".into(), Duration::ZERO);
+
+        assert_eq!(level.bump(1).unwrap().revert_in, None);
+        assert_eq!(level.state().filter, "debug");
+
+        let state = level.reset().unwrap();
+        assert_eq!(state.filter, "info");
+    }
+}
@@ -2,69 +2,174 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use tracing_appender::non_blocking;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, reload};
 
-use super::config::Config;
+use super::config::{Config, LogConfig};
 use super::fs::FileSystem;
+use super::log_level::{FilterHandle, LogLevel};
+use super::rotation::{RotatingWriter, RotationPolicy};
+use super::syslog::SyslogLayer;
 
-const KEY_LOG_DIR: &str = "BOT_LOG_DIR";
-const KEY_LOG_FILTER: &str = "BOT_LOG";
-const KEY_LOG_ANSI: &str = "BOT_LOG_ANSI";
-const KEY_LOG_TARGET: &str = "BOT_LOG_TARGET";
-
-const DEFAULT_LOG_FILTER: &str = "info";
 const LOG_FILE_NAME: &str = "log";
+const TMPFS_LOG_DIR: &str = "/tmp/tb-router";
 
 #[must_use = "LogGuard must be held to keep logging active"]
 pub struct LogGuard {
-    _guard: tracing_appender::non_blocking::WorkerGuard,
+    _guard: Option<non_blocking::WorkerGuard>,
+    level: LogLevel,
 }
 
-pub fn init_with_fs<F: FileSystem>(conf: &Config, fs: &F) -> anyhow::Result<LogGuard> {
-    let dir = log_dir(conf, fs)?;
-    let (writer, guard) = build_file_writer(dir);
-    let filter = conf.optional(KEY_LOG_FILTER).unwrap_or(DEFAULT_LOG_FILTER);
-    let ansi = conf.parse_optional(KEY_LOG_ANSI).unwrap_or(false);
-    let target = conf.parse_optional(KEY_LOG_TARGET).unwrap_or(true);
-    init_subscriber(filter, writer, ansi, target)?;
-    Ok(LogGuard { _guard: guard })
+impl LogGuard {
+    /// Swaps in the log filter from `conf`; the current one stays on error.
+    ///
+    /// Drops any runtime override, since the operator asked for a new config.
+    pub fn reload_filter(&self, conf: &Config) -> anyhow::Result<()> {
+        self.level
+            .configure(&conf.log.filter, conf.log.level_timeout)
+            .context("failed to reload log filter")
+    }
+
+    pub fn level(&self) -> &LogLevel {
+        &self.level
+    }
+
+    /// A guard with no subscriber behind it, so reloading its filter fails.
+    #[cfg(test)]
+    pub fn detached(conf: &Config) -> Self {
+        let (_, handle) = reload::Layer::new(EnvFilter::new(&conf.log.filter));
+        Self {
+            _guard: None,
+            level: LogLevel::new(handle, conf.log.filter.clone(), conf.log.level_timeout),
+        }
+    }
+}
+
+pub fn init_with_fs<F: FileSystem + Clone + 'static>(
+    conf: &Config,
+    fs: &F,
+) -> anyhow::Result<LogGuard> {
+    let file = if conf.log.output.file() {
+        let dir = log_dir(conf, fs)?;
+        Some(build_file_writer(
+            fs.clone(),
+            dir,
+            rotation_policy(&conf.log),
+        ))
+    } else {
+        None
+    };
+    let (writer, guard) = file.unzip();
+    let syslog = conf
+        .log
+        .output
+        .syslog()
+        .then(|| build_syslog_layer(&conf.log));
+
+    let filter = parse_filter(conf)?;
+    let handle = init_subscriber(filter, writer, syslog, &conf.log)?;
+    Ok(LogGuard {
+        _guard: guard,
+        level: LogLevel::new(handle, conf.log.filter.clone(), conf.log.level_timeout),
+    })
+}
+
+fn parse_filter(conf: &Config) -> anyhow::Result<EnvFilter> {
+    let filter = &conf.log.filter;
+    EnvFilter::try_new(filter).with_context(|| format!("invalid log filter '{filter}'"))
 }
 
 pub fn init(conf: &Config) -> anyhow::Result<LogGuard> {
     init_with_fs(conf, &super::fs::RealFileSystem)
 }
 
+/// Path of the bot's log file, or `None` if it only logs to syslog.
+pub fn log_file(conf: &Config) -> anyhow::Result<Option<PathBuf>> {
+    if !conf.log.output.file() {
+        return Ok(None);
+    }
+    let dir = log_dir_path(conf, &super::fs::RealFileSystem)?;
+    Ok(Some(dir.join(LOG_FILE_NAME)))
+}
+
 fn log_dir<F: FileSystem>(conf: &Config, fs: &F) -> anyhow::Result<PathBuf> {
-    let dir = match conf.optional(KEY_LOG_DIR) {
-        Some(path) => PathBuf::from(path),
+    let dir = log_dir_path(conf, fs)?;
+    fs.create_dir_all(&dir)
+        .context("failed to create log dir")?;
+    Ok(dir)
+}
+
+fn log_dir_path<F: FileSystem>(conf: &Config, fs: &F) -> anyhow::Result<PathBuf> {
+    let dir = match &conf.log.dir {
+        Some(path) => path.clone(),
+        None if conf.log.tmpfs => PathBuf::from(TMPFS_LOG_DIR),
         None => fs
             .current_exe()?
             .parent()
             .unwrap_or_else(|| Path::new("."))
             .to_path_buf(),
     };
-
-    fs.create_dir_all(&dir)
-        .context("failed to create log dir")?;
     Ok(dir)
 }
 
-fn build_file_writer(dir: PathBuf) -> (non_blocking::NonBlocking, non_blocking::WorkerGuard) {
-    let appender = tracing_appender::rolling::never(dir, LOG_FILE_NAME);
-    non_blocking(appender)
+/// In tmpfs mode files are sized so that all of them fit in the limit.
+fn rotation_policy(conf: &LogConfig) -> RotationPolicy {
+    let mut policy = RotationPolicy {
+        max_size: conf.max_size,
+        max_files: conf.max_files,
+        compress: conf.compress,
+    };
+
+    if conf.tmpfs {
+        let per_file = conf.tmpfs_limit / (conf.max_files as u64 + 1);
+        policy.max_size = match conf.max_size {
+            0 => per_file,
+            size => size.min(per_file),
+        };
+    }
+    policy
+}
+
+fn build_file_writer<F: FileSystem + 'static>(
+    fs: F,
+    dir: PathBuf,
+    policy: RotationPolicy,
+) -> (non_blocking::NonBlocking, non_blocking::WorkerGuard) {
+    let writer = RotatingWriter::new(fs, dir.join(LOG_FILE_NAME), policy);
+    non_blocking(writer)
 }
 
+fn build_syslog_layer(conf: &LogConfig) -> SyslogLayer {
+    SyslogLayer::new(
+        conf.syslog_socket.clone(),
+        conf.syslog_format,
+        conf.syslog_ident.clone(),
+    )
+    .with_target(conf.target)
+}
+
+/// Installs the global subscriber with a file writer, syslog, or both.
 fn init_subscriber(
-    filter: &str,
-    writer: non_blocking::NonBlocking,
-    ansi: bool,
-    target: bool,
-) -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .compact()
-        .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
-        .with_ansi(ansi)
-        .with_target(target)
-        .with_writer(writer)
+    filter: EnvFilter,
+    writer: Option<non_blocking::NonBlocking>,
+    syslog: Option<SyslogLayer>,
+    conf: &LogConfig,
+) -> anyhow::Result<FilterHandle> {
+    let (filter, handle) = reload::Layer::new(filter);
+    let fmt = writer.map(|writer| {
+        tracing_subscriber::fmt::layer()
+            .compact()
+            .with_ansi(conf.ansi)
+            .with_target(conf.target)
+            .with_writer(writer)
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(syslog)
         .try_init()
-        .map_err(|e| anyhow::anyhow!("failed to initialize tracing: {e}"))
+        .map_err(|e| anyhow::anyhow!("failed to initialize tracing: {e}"))?;
+    Ok(handle)
 }
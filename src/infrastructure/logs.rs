@@ -0,0 +1,115 @@
+//! Log access for chat commands: the bot's log file and `logread`.
+
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::process::Command;
+
+use crate::domain::{LogError, LogQuery, LogSource};
+
+/// How much of the end of the log file is searched.
+const TAIL_WINDOW: u64 = 1024 * 1024;
+const LOGREAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct LocalLogs {
+    /// `None` when logging goes to syslog only.
+    log_file: Option<PathBuf>,
+}
+
+impl LocalLogs {
+    pub fn new(log_file: Option<PathBuf>) -> Self {
+        Self { log_file }
+    }
+}
+
+#[async_trait]
+impl LogSource for LocalLogs {
+    async fn bot_log(&self, query: &LogQuery) -> Result<Vec<String>, LogError> {
+        let path = self.log_file.as_ref().ok_or(LogError::NoLogFile)?;
+        let text = read_tail(path).await.map_err(|source| LogError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(query.select(&text))
+    }
+
+    /// Reads logd's ring buffer, which is small enough to search whole.
+    async fn system_log(&self, query: &LogQuery) -> Result<Vec<String>, LogError> {
+        let output = Command::new("logread").kill_on_drop(true).output();
+        let output = tokio::time::timeout(LOGREAD_TIMEOUT, output)
+            .await
+            .map_err(|_| LogError::Timeout { cmd: "logread" })?
+            .map_err(|source| LogError::Spawn {
+                cmd: "logread",
+                source,
+            })?;
+
+        if !output.status.success() {
+            return Err(LogError::NonZeroExit {
+                cmd: "logread",
+                code: output.status.code().unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+        Ok(query.select(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Reads the last `TAIL_WINDOW` bytes, dropping the first partial line.
+async fn read_tail(path: &PathBuf) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let start = len.saturating_sub(TAIL_WINDOW);
+    file.seek(SeekFrom::Start(start)).await?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).await?;
+
+    let text = String::from_utf8_lossy(&data);
+    let text = match (start, text.find('\n')) {
+        (0, _) => &text[..],
+        (_, Some(newline)) => &text[newline + 1..],
+        (_, None) => "",
+    };
+    Ok(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    fn query(lines: usize, pattern: Option<&str>) -> LogQuery {
+        LogQuery {
+            lines,
+            pattern: pattern.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_matching_tail_of_log_file() {
+        let dir = TempDir::new("logs");
+        let path = dir.join("log");
+        std::fs::write(&path, "one INFO\ntwo WARN\nthree info\nfour warn\nfive\n").unwrap();
+        let logs = LocalLogs::new(Some(path));
+
+        let tail = logs.bot_log(&query(2, None)).await.unwrap();
+        let warnings = logs.bot_log(&query(10, Some("Warn"))).await.unwrap();
+
+        assert_eq!(tail, ["four warn", "five"]);
+        assert_eq!(warnings, ["two WARN", "four warn"]);
+    }
+
+    #[tokio::test]
+    async fn reports_missing_log_file() {
+        let logs = LocalLogs::new(None);
+
+        let error = logs.bot_log(&query(10, None)).await.unwrap_err();
+
+        assert!(matches!(error, LogError::NoLogFile));
+    }
+}
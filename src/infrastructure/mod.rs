@@ -4,11 +4,19 @@
 
 pub mod config;
 pub mod fs;
+mod log_level;
 pub mod logging;
+pub mod logs;
+mod rotation;
 pub mod router;
+pub mod service;
 pub mod signal;
+mod syslog;
+pub mod update;
 
 pub use config::Config;
+pub use log_level::LogLevel;
 pub use logging::{LogGuard, init as init_logging};
-pub use router::OpenWrtRouter;
+pub use logs::LocalLogs;
 pub use signal::UnixSignalHandler;
+pub use update::{Probation, SelfUpdater};
@@ -0,0 +1,306 @@
+//! Size-based log file rotation.
+//!
+//! The active file is `<name>`; rotated files are `<name>.1` (newest) up to
+//! `<name>.<max_files>`, with a `.gz` suffix when compression is on.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use super::fs::FileSystem;
+
+const GZ_EXT: &str = ".gz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Bytes per file before rotating; 0 disables rotation.
+    pub max_size: u64,
+    /// Rotated files to keep next to the active one.
+    pub max_files: usize,
+    pub compress: bool,
+}
+
+pub struct RotatingWriter<F: FileSystem> {
+    fs: F,
+    path: PathBuf,
+    policy: RotationPolicy,
+    file: Option<Box<dyn Write + Send>>,
+    size: u64,
+}
+
+impl<F: FileSystem> RotatingWriter<F> {
+    pub fn new(fs: F, path: PathBuf, policy: RotationPolicy) -> Self {
+        let size = fs.file_size(&path).unwrap_or(0);
+        Self {
+            fs,
+            path,
+            policy,
+            file: None,
+            size,
+        }
+    }
+
+    fn rotated(&self, index: usize, ext: &str) -> PathBuf {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        self.path.with_file_name(format!("{name}.{index}{ext}"))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        self.size = 0;
+
+        let max = self.policy.max_files;
+        if max == 0 {
+            return ignore_missing(self.fs.remove_file(&self.path));
+        }
+
+        for ext in ["", GZ_EXT] {
+            ignore_missing(self.fs.remove_file(&self.rotated(max, ext)))?;
+        }
+        for index in (1..max).rev() {
+            for ext in ["", GZ_EXT] {
+                let from = self.rotated(index, ext);
+                ignore_missing(self.fs.rename(&from, &self.rotated(index + 1, ext)))?;
+            }
+        }
+
+        let newest = self.rotated(1, "");
+        self.fs.rename(&self.path, &newest)?;
+        if self.policy.compress {
+            self.compress(&newest)?;
+        }
+        Ok(())
+    }
+
+    fn compress(&self, path: &Path) -> io::Result<()> {
+        let data = self.fs.read(path)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data)?;
+
+        let gz = self.rotated(1, GZ_EXT);
+        self.fs.write(&gz, &encoder.finish()?)?;
+        self.fs.remove_file(path)
+    }
+
+    fn file(&mut self) -> io::Result<&mut Box<dyn Write + Send>> {
+        match &mut self.file {
+            Some(file) => Ok(file),
+            file => Ok(file.insert(self.fs.append(&self.path)?)),
+        }
+    }
+}
+
+impl<F: FileSystem> Write for RotatingWriter<F> {
+    /// Rotates before a write that would overflow the file.
+    ///
+    /// A single record larger than `max_size` is truncated, so no file ever
+    /// exceeds the limit.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let limit = self.policy.max_size;
+        if limit > 0 && self.size > 0 && self.size + buf.len() as u64 > limit {
+            self.rotate()?;
+        }
+
+        let data = match usize::try_from(limit) {
+            Ok(limit) if limit > 0 && buf.len() > limit => &buf[..limit],
+            _ => buf,
+        };
+        self.file()?.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    type Files = Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>;
+
+    #[derive(Clone, Default)]
+    struct MemoryFileSystem {
+        files: Files,
+    }
+
+    impl MemoryFileSystem {
+        fn names(&self) -> Vec<String> {
+            let files = self.files.lock().unwrap();
+            files.keys().map(|p| p.display().to_string()).collect()
+        }
+
+        fn content(&self, path: &str) -> Vec<u8> {
+            self.files.lock().unwrap()[Path::new(path)].clone()
+        }
+
+        fn total_size(&self) -> usize {
+            self.files.lock().unwrap().values().map(Vec::len).sum()
+        }
+    }
+
+    struct MemoryFile {
+        files: Files,
+        path: PathBuf,
+    }
+
+    impl Write for MemoryFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut files = self.files.lock().unwrap();
+            files.entry(self.path.clone()).or_default().extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn not_found() -> io::Error {
+        io::Error::from(io::ErrorKind::NotFound)
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn current_exe(&self) -> io::Result<PathBuf> {
+            Ok(PathBuf::from("/bin/tb-router"))
+        }
+
+        fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn append(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+            let mut files = self.files.lock().unwrap();
+            files.entry(path.to_path_buf()).or_default();
+            Ok(Box::new(MemoryFile {
+                files: Arc::clone(&self.files),
+                path: path.to_path_buf(),
+            }))
+        }
+
+        fn file_size(&self, path: &Path) -> io::Result<u64> {
+            let files = self.files.lock().unwrap();
+            files
+                .get(path)
+                .map(|f| f.len() as u64)
+                .ok_or_else(not_found)
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            let files = self.files.lock().unwrap();
+            files.get(path).cloned().ok_or_else(not_found)
+        }
+
+        fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            let mut files = self.files.lock().unwrap();
+            files.insert(path.to_path_buf(), data.to_vec());
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut files = self.files.lock().unwrap();
+            let data = files.remove(from).ok_or_else(not_found)?;
+            files.insert(to.to_path_buf(), data);
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            let mut files = self.files.lock().unwrap();
+            files.remove(path).map(drop).ok_or_else(not_found)
+        }
+    }
+
+    fn writer(
+        fs: &MemoryFileSystem,
+        max_size: u64,
+        max_files: usize,
+        compress: bool,
+    ) -> impl Write {
+        let policy = RotationPolicy {
+            max_size,
+            max_files,
+            compress,
+        };
+        RotatingWriter::new(fs.clone(), PathBuf::from("/logs/log"), policy)
+    }
+
+    #[test]
+    fn rotates_and_keeps_max_files() {
+        let fs = MemoryFileSystem::default();
+        let mut w = writer(&fs, 10, 2, false);
+
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            w.write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs.names(), ["/logs/log", "/logs/log.1", "/logs/log.2"]);
+        assert_eq!(fs.content("/logs/log"), b"dddddddd\n");
+        assert_eq!(fs.content("/logs/log.1"), b"cccccccc\n");
+        assert_eq!(fs.content("/logs/log.2"), b"bbbbbbbb\n");
+    }
+
+    #[test]
+    fn compresses_rotated_files() {
+        let fs = MemoryFileSystem::default();
+        let mut w = writer(&fs, 10, 2, true);
+
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n"] {
+            w.write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(
+            fs.names(),
+            ["/logs/log", "/logs/log.1.gz", "/logs/log.2.gz"]
+        );
+        let mut text = String::new();
+        GzDecoder::new(&fs.content("/logs/log.2.gz")[..])
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "aaaaaaaa\n");
+    }
+
+    #[test]
+    fn total_size_stays_under_cap() {
+        let fs = MemoryFileSystem::default();
+        let mut w = writer(&fs, 64, 3, false);
+
+        for i in 0..500 {
+            writeln!(w, "record {i} {}", "x".repeat(i % 40)).unwrap();
+        }
+        w.write_all(&[b'y'; 200]).unwrap();
+
+        assert!(fs.total_size() <= 64 * 4, "total {}", fs.total_size());
+        assert_eq!(fs.content("/logs/log").len(), 64);
+    }
+
+    #[test]
+    fn continues_existing_file() {
+        let fs = MemoryFileSystem::default();
+        fs.write(Path::new("/logs/log"), b"0123456789").unwrap();
+
+        writer(&fs, 12, 1, false).write_all(b"abc").unwrap();
+
+        assert_eq!(fs.content("/logs/log"), b"abc");
+        assert_eq!(fs.content("/logs/log.1"), b"0123456789");
+    }
+}
@@ -0,0 +1,287 @@
+//! Caching decorator over any router backend.
+//!
+//! Each query kind is kept for its own TTL. Callers asking for something
+//! that is already being fetched wait for that call instead of starting
+//! another, and share its outcome, failures included. Failures are never
+//! kept beyond that.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::domain::{
+    RouterError,
+    router::{NetworkInfoProvider, SystemInfoProvider, WifiControl, WifiInfoProvider},
+    ubus::{
+        AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+        WirelessStatus,
+    },
+    wifi_settings::InterfaceOptions,
+};
+use crate::infrastructure::config::CacheConfig;
+
+pub struct CachedRouter<R> {
+    inner: R,
+    /// Bumped by `invalidate`; answers from an older generation are stale.
+    generation: AtomicU64,
+    system_info: Slot<SystemInfo>,
+    board_info: Slot<BoardInfo>,
+    wireless_status: Slot<WirelessStatus>,
+    clients_ttl: Duration,
+    wifi_clients: Mutex<HashMap<String, Arc<Slot<HostapdClients>>>>,
+    stations_ttl: Duration,
+    wifi_stations: Mutex<HashMap<String, Arc<Slot<AssocList>>>>,
+    dhcp_leases: Slot<DhcpLeases>,
+}
+
+impl<R> CachedRouter<R> {
+    pub fn new(inner: R, config: &CacheConfig) -> Self {
+        Self {
+            inner,
+            generation: AtomicU64::new(0),
+            system_info: Slot::new(config.system_info),
+            board_info: Slot::new(config.board_info),
+            wireless_status: Slot::new(config.wireless_status),
+            clients_ttl: config.wifi_clients,
+            wifi_clients: Mutex::new(HashMap::new()),
+            stations_ttl: config.wifi_stations,
+            wifi_stations: Mutex::new(HashMap::new()),
+            dhcp_leases: Slot::new(config.dhcp_leases),
+        }
+    }
+
+    /// Forgets everything cached, including calls still in flight. Every
+    /// action that changes the router must call this once it is applied.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// The slot for `key`, created on first use.
+fn slot_for<T>(
+    slots: &Mutex<HashMap<String, Arc<Slot<T>>>>,
+    key: &str,
+    ttl: Duration,
+) -> Arc<Slot<T>> {
+    let mut slots = slots.lock().unwrap();
+    slots
+        .entry(key.to_string())
+        .or_insert_with(|| Arc::new(Slot::new(ttl)))
+        .clone()
+}
+
+/// The last answer to one query, and a lock held while it is refreshed.
+struct Slot<T> {
+    ttl: Duration,
+    /// Completed fetches; lets waiters tell whether one finished meanwhile.
+    fetches: AtomicU64,
+    last: tokio::sync::Mutex<Option<Answer<T>>>,
+}
+
+struct Answer<T> {
+    result: Result<T, RouterError>,
+    at: Instant,
+    generation: u64,
+    fetch: u64,
+}
+
+impl<T> Slot<T> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            fetches: AtomicU64::new(0),
+            last: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl<T: Clone> Slot<T> {
+    async fn get(
+        &self,
+        generation: &AtomicU64,
+        fetch: impl Future<Output = Result<T, RouterError>>,
+    ) -> Result<T, RouterError> {
+        let seen = self.fetches.load(Ordering::Acquire);
+        let mut last = self.last.lock().await;
+        let current = generation.load(Ordering::Acquire);
+
+        if let Some(answer) = last.as_ref().filter(|a| a.generation == current) {
+            let fresh = answer.result.is_ok() && answer.at.elapsed() < self.ttl;
+            let awaited = answer.fetch > seen;
+            if fresh || awaited {
+                return answer.result.clone();
+            }
+        }
+
+        let result = fetch.await;
+        *last = Some(Answer {
+            result: result.clone(),
+            at: Instant::now(),
+            generation: current,
+            fetch: self.fetches.fetch_add(1, Ordering::AcqRel) + 1,
+        });
+        result
+    }
+}
+
+#[async_trait]
+impl<R: SystemInfoProvider> SystemInfoProvider for CachedRouter<R> {
+    async fn system_info(&self) -> Result<SystemInfo, RouterError> {
+        self.system_info
+            .get(&self.generation, self.inner.system_info())
+            .await
+    }
+
+    async fn board_info(&self) -> Result<BoardInfo, RouterError> {
+        self.board_info
+            .get(&self.generation, self.inner.board_info())
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: WifiInfoProvider> WifiInfoProvider for CachedRouter<R> {
+    async fn wireless_status(&self) -> Result<WirelessStatus, RouterError> {
+        self.wireless_status
+            .get(&self.generation, self.inner.wireless_status())
+            .await
+    }
+
+    async fn wifi_clients(&self, iface: &str) -> Result<HostapdClients, RouterError> {
+        slot_for(&self.wifi_clients, iface, self.clients_ttl)
+            .get(&self.generation, self.inner.wifi_clients(iface))
+            .await
+    }
+
+    async fn wifi_stations(&self, iface: &str) -> Result<AssocList, RouterError> {
+        slot_for(&self.wifi_stations, iface, self.stations_ttl)
+            .get(&self.generation, self.inner.wifi_stations(iface))
+            .await
+    }
+
+    /// Never cached: a scan is only ever asked for to see what is there now.
+    async fn wifi_scan(&self, iface: &str) -> Result<ScanList, RouterError> {
+        self.inner.wifi_scan(iface).await
+    }
+
+    /// Never cached either: readings are compared by when they were taken.
+    async fn wifi_survey(&self, iface: &str) -> Result<SurveyList, RouterError> {
+        self.inner.wifi_survey(iface).await
+    }
+}
+
+#[async_trait]
+impl<R: NetworkInfoProvider> NetworkInfoProvider for CachedRouter<R> {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError> {
+        self.dhcp_leases
+            .get(&self.generation, self.inner.dhcp_leases())
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: WifiControl> WifiControl for CachedRouter<R> {
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError> {
+        let result = self.inner.set_channel(radio, channel).await;
+        // Even a failed change may have been partly applied.
+        self.invalidate();
+        result
+    }
+
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError> {
+        let result = self.inner.configure_interfaces(changes).await;
+        self.invalidate();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use crate::testing::FakeRouter;
+
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(50);
+
+    /// A router whose client calls take `DELAY` and fail while `failing` is set.
+    fn cached(clients_ttl: Duration, failing: Arc<AtomicBool>) -> CachedRouter<FakeRouter> {
+        let inner = FakeRouter::default()
+            .with_delay(DELAY)
+            .with_clients(move |_, iface| {
+                if failing.load(Ordering::SeqCst) {
+                    return Err(RouterError::Timeout {
+                        object: format!("hostapd.{iface}"),
+                        method: "get_clients".to_string(),
+                    });
+                }
+                Ok(HostapdClients {
+                    freq: 2412,
+                    ..HostapdClients::default()
+                })
+            });
+        let config = CacheConfig {
+            wifi_clients: clients_ttl,
+            ..CacheConfig::default()
+        };
+        CachedRouter::new(inner, &config)
+    }
+
+    #[tokio::test]
+    async fn shares_concurrent_calls_and_keeps_answers_for_the_ttl() {
+        let router = cached(Duration::from_millis(200), Arc::default());
+
+        let (a, b, c) = tokio::join!(
+            router.wifi_clients("wlan0"),
+            router.wifi_clients("wlan0"),
+            router.wifi_clients("wlan0"),
+        );
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert_eq!(router.inner.calls(), 1);
+
+        assert_eq!(router.wifi_clients("wlan0").await.unwrap().freq, 2412);
+        assert_eq!(router.inner.calls(), 1);
+        router.wifi_clients("wlan1").await.unwrap();
+        assert_eq!(router.inner.calls(), 2);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        router.wifi_clients("wlan0").await.unwrap();
+        assert_eq!(router.inner.calls(), 3);
+
+        router.invalidate();
+        router.wifi_clients("wlan0").await.unwrap();
+        assert_eq!(router.inner.calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn shares_failures_with_waiters_only() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let router = cached(Duration::from_secs(60), Arc::clone(&failing));
+
+        let (a, b) = tokio::join!(router.wifi_clients("wlan0"), router.wifi_clients("wlan0"));
+        assert!(matches!(a, Err(RouterError::Timeout { .. })));
+        assert!(matches!(b, Err(RouterError::Timeout { .. })));
+        assert_eq!(router.inner.calls(), 1);
+
+        failing.store(false, Ordering::SeqCst);
+        router.wifi_clients("wlan0").await.unwrap();
+        assert_eq!(router.inner.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_keeps_nothing() {
+        let router = cached(Duration::ZERO, Arc::default());
+
+        let (a, b) = tokio::join!(router.wifi_clients("wlan0"), router.wifi_clients("wlan0"));
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(router.inner.calls(), 1);
+
+        router.wifi_clients("wlan0").await.unwrap();
+        assert_eq!(router.inner.calls(), 2);
+    }
+}
@@ -0,0 +1,348 @@
+//! Router backed by JSON files, for development and demos without `ubus`.
+//!
+//! Each call `ubus call <object> <method>` is answered from
+//! `<dir>/<object>.<method>.json`, so fixtures can be captured on a real
+//! router with `ubus call system info > system.info.json`:
+//!
+//! ```text
+//! fixtures/router/
+//!   system.info.json
+//!   system.board.json
+//!   network.wireless.status.json
+//!   hostapd.phy0-ap0.get_clients.json
+//!   iwinfo.assoclist.phy0-ap0.json
+//!   iwinfo.scan.phy0-ap0.json
+//!   iwinfo.survey.phy0-ap0.json
+//!   steps/60/hostapd.phy0-ap0.get_clients.json
+//!   steps/120/hostapd.phy1-ap0.get_clients.error
+//! ```
+//!
+//! `steps/<seconds>/` directories script changes over time: once that many
+//! seconds have passed since the router was created, their files take
+//! precedence over earlier steps and the base directory.
+//!
+//! Calls that take an interface look for `<object>.<method>.<iface>.json`,
+//! e.g. `iwinfo.assoclist.phy1-ap0.json`.
+//!
+//! A `<object>.<method>.error` file makes the call fail as if `ubus` exited
+//! non-zero, with the file contents as stderr. A leading number is taken as
+//! the exit code (`4 Not found`), otherwise the code is 1. Calls without any
+//! file fail like `ubus` does for a missing object.
+//!
+//! Changes such as `uci set` are accepted and logged but not made, so the
+//! fixtures stay as they are; an `.error` file for them still makes them fail.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use async_trait::async_trait;
+
+use crate::domain::error::UBUS_NOT_FOUND;
+use crate::domain::{
+    RouterError,
+    router::{NetworkInfoProvider, SystemInfoProvider, WifiControl, WifiInfoProvider},
+    ubus::{
+        AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+        WirelessStatus,
+    },
+    wifi_settings::InterfaceOptions,
+};
+
+const CMD: &str = "fixture";
+const STEPS_DIR: &str = "steps";
+
+pub struct FixtureRouter {
+    dir: PathBuf,
+    /// `(offset, directory)`, latest first.
+    steps: Vec<(Duration, PathBuf)>,
+    latency: Duration,
+    started: Instant,
+}
+
+enum Fixture {
+    Json(PathBuf),
+    Error(PathBuf),
+}
+
+impl FixtureRouter {
+    pub fn new(dir: &Path, latency: Duration) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            dir.is_dir(),
+            "fixture directory {} does not exist",
+            dir.display()
+        );
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            steps: read_steps(&dir.join(STEPS_DIR))?,
+            latency,
+            started: Instant::now(),
+        })
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        object: &str,
+        method: &str,
+    ) -> Result<T, RouterError> {
+        self.call_named(object, method, &format!("{object}.{method}"))
+            .await
+    }
+
+    /// Answers `object method` from the fixture called `name`.
+    async fn call_named<T: serde::de::DeserializeOwned>(
+        &self,
+        object: &str,
+        method: &str,
+        name: &str,
+    ) -> Result<T, RouterError> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+
+        match self.find(name) {
+            Some(Fixture::Json(path)) => {
+                let data = read(&path).await?;
+                serde_json::from_str(&data).map_err(|source| RouterError::Json {
+                    object: object.to_string(),
+                    method: method.to_string(),
+                    source: Arc::new(source),
+                })
+            }
+            Some(Fixture::Error(path)) => {
+                let (code, stderr) = injected_status(&read(&path).await?);
+                Err(RouterError::from_status(object, method, code, &stderr))
+            }
+            None => Err(RouterError::from_status(
+                object,
+                method,
+                UBUS_NOT_FOUND,
+                "Command failed: Not found",
+            )),
+        }
+    }
+
+    /// Pretends to make a change, failing only as an `.error` fixture says.
+    async fn write(&self, object: &str, method: &str, message: &str) -> Result<(), RouterError> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+
+        match self.find(&format!("{object}.{method}")) {
+            Some(Fixture::Error(path)) => {
+                let (code, stderr) = injected_status(&read(&path).await?);
+                Err(RouterError::from_status(object, method, code, &stderr))
+            }
+            _ => {
+                tracing::info!("Fixture router ignoring {object} {method} {message}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Looks through the steps already reached, then the base directory.
+    fn find(&self, call: &str) -> Option<Fixture> {
+        let elapsed = self.started.elapsed();
+        self.steps
+            .iter()
+            .filter(|(offset, _)| *offset <= elapsed)
+            .map(|(_, dir)| dir.as_path())
+            .chain([self.dir.as_path()])
+            .find_map(|dir| {
+                let error = dir.join(format!("{call}.error"));
+                let json = dir.join(format!("{call}.json"));
+                if error.is_file() {
+                    Some(Fixture::Error(error))
+                } else if json.is_file() {
+                    Some(Fixture::Json(json))
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+fn read_steps(dir: &Path) -> anyhow::Result<Vec<(Duration, PathBuf)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+    };
+
+    let mut steps = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let offset = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u64>().ok());
+        match offset {
+            Some(secs) if path.is_dir() => steps.push((Duration::from_secs(secs), path)),
+            _ => tracing::warn!(
+                "Ignoring {}: not a steps/<seconds> directory",
+                path.display()
+            ),
+        }
+    }
+    steps.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+    Ok(steps)
+}
+
+async fn read(path: &Path) -> Result<String, RouterError> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|source| RouterError::Spawn {
+            cmd: CMD,
+            source: Arc::new(source),
+        })
+}
+
+/// Splits `[code] message`; the code defaults to 1.
+fn injected_status(contents: &str) -> (i32, String) {
+    let contents = contents.trim();
+    contents
+        .split_once(char::is_whitespace)
+        .and_then(|(code, rest)| Some((code.parse().ok()?, rest.trim().to_string())))
+        .unwrap_or((1, contents.to_string()))
+}
+
+#[async_trait]
+impl SystemInfoProvider for FixtureRouter {
+    async fn system_info(&self) -> Result<SystemInfo, RouterError> {
+        self.call("system", "info").await
+    }
+
+    async fn board_info(&self) -> Result<BoardInfo, RouterError> {
+        self.call("system", "board").await
+    }
+}
+
+#[async_trait]
+impl WifiInfoProvider for FixtureRouter {
+    async fn wireless_status(&self) -> Result<WirelessStatus, RouterError> {
+        self.call("network.wireless", "status").await
+    }
+
+    async fn wifi_clients(&self, iface: &str) -> Result<HostapdClients, RouterError> {
+        self.call(&format!("hostapd.{iface}"), "get_clients").await
+    }
+
+    async fn wifi_stations(&self, iface: &str) -> Result<AssocList, RouterError> {
+        self.call_named("iwinfo", "assoclist", &format!("iwinfo.assoclist.{iface}"))
+            .await
+    }
+
+    async fn wifi_scan(&self, iface: &str) -> Result<ScanList, RouterError> {
+        self.call_named("iwinfo", "scan", &format!("iwinfo.scan.{iface}"))
+            .await
+    }
+
+    async fn wifi_survey(&self, iface: &str) -> Result<SurveyList, RouterError> {
+        self.call_named("iwinfo", "survey", &format!("iwinfo.survey.{iface}"))
+            .await
+    }
+}
+
+#[async_trait]
+impl NetworkInfoProvider for FixtureRouter {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError> {
+        self.call("luci-rpc", "getDHCPLeases").await
+    }
+}
+
+#[async_trait]
+impl WifiControl for FixtureRouter {
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError> {
+        self.write("uci", "set", &format!("wireless.{radio}.channel={channel}"))
+            .await
+    }
+
+    /// Logs only the options changed, never their values.
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError> {
+        for change in changes {
+            let options: Vec<_> = change.values.iter().map(|(option, _)| *option).collect();
+            let message = format!("wireless.{} {}", change.section, options.join(","));
+            self.write("uci", "set", &message).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_bundled_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/router");
+        let router = FixtureRouter::new(&dir, Duration::ZERO).unwrap();
+
+        let status = router.status().await.unwrap();
+        assert_eq!(status.value.board.unwrap().release.distribution, "OpenWrt");
+
+        let wireless = router.wireless_status().await.unwrap();
+        for iface in wireless.0.values().flat_map(|radio| &radio.interfaces) {
+            let clients = router.wifi_clients(&iface.ifname).await.unwrap();
+            let stations = router.wifi_stations(&iface.ifname).await.unwrap();
+            let scan = router.wifi_scan(&iface.ifname).await.unwrap();
+            let survey = router.wifi_survey(&iface.ifname).await.unwrap();
+            assert!(!scan.results.is_empty());
+            assert!(survey.find(clients.freq).is_some());
+            assert!(
+                clients
+                    .clients
+                    .keys()
+                    .all(|mac| stations.find(mac).is_some())
+            );
+        }
+        assert!(!router.dhcp_leases().await.unwrap().dhcp_leases.is_empty());
+        router.set_channel("radio1", 149).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn later_steps_override_and_inject_errors() {
+        let dir = TempDir::new("fixture-steps");
+        let clients = |mac: &str| {
+            let client = serde_json::json!({
+                "auth": true, "assoc": true, "authorized": true, "signal": -50,
+                "ht": true, "vht": false, "he": false,
+                "bytes": {"rx": 1, "tx": 2}, "rate": {"rx": 3, "tx": 4},
+            });
+            serde_json::json!({"freq": 2412, "clients": {mac: client}}).to_string()
+        };
+        std::fs::write(dir.join("hostapd.wlan0.get_clients.json"), clients("base")).unwrap();
+        std::fs::create_dir_all(dir.join("steps/0")).unwrap();
+        std::fs::create_dir_all(dir.join("steps/3600")).unwrap();
+        std::fs::write(
+            dir.join("steps/0/hostapd.wlan0.get_clients.json"),
+            clients("now"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("steps/0/hostapd.wlan1.get_clients.error"),
+            "4 Not found\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("steps/0/uci.set.error"), "6 Access denied").unwrap();
+        std::fs::write(
+            dir.join("steps/3600/hostapd.wlan0.get_clients.error"),
+            "later",
+        )
+        .unwrap();
+
+        let router = FixtureRouter::new(dir.path(), Duration::ZERO).unwrap();
+
+        let wlan0 = router.wifi_clients("wlan0").await.unwrap();
+        assert!(wlan0.clients.contains_key("now"));
+        let error = router.wifi_clients("wlan1").await.unwrap_err();
+        assert_eq!(error.to_string(), "hostapd.wlan1: not running");
+        let error = router.set_channel("radio0", 6).await.unwrap_err();
+        assert!(matches!(error, RouterError::PermissionDenied { .. }));
+        let error = router.system_info().await.unwrap_err();
+        assert!(matches!(error, RouterError::NotFound { ref object, .. } if object == "system"));
+    }
+}
@@ -1,3 +1,124 @@
+mod cache;
+mod fixture;
 mod openwrt;
 
+use async_trait::async_trait;
+
+use crate::domain::{
+    RouterError,
+    router::{NetworkInfoProvider, SystemInfoProvider, WifiControl, WifiInfoProvider},
+    ubus::{
+        AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+        WirelessStatus,
+    },
+    wifi_settings::InterfaceOptions,
+};
+
+use super::config::{RouterBackend, RouterConfig};
+
+pub use cache::CachedRouter;
+pub use fixture::FixtureRouter;
 pub use openwrt::OpenWrtRouter;
+
+/// The backend selected by `router.backend`.
+pub enum Router {
+    Ubus(OpenWrtRouter),
+    Fixture(FixtureRouter),
+}
+
+impl Router {
+    pub fn from_config(config: &RouterConfig) -> anyhow::Result<Self> {
+        match (config.backend, &config.fixture_dir) {
+            (RouterBackend::Ubus, _) => Ok(Self::Ubus(OpenWrtRouter::new(config))),
+            (RouterBackend::Fixture, Some(dir)) => Ok(Self::Fixture(FixtureRouter::new(
+                dir,
+                config.fixture_latency,
+            )?)),
+            (RouterBackend::Fixture, None) => {
+                anyhow::bail!("router.fixture_dir is required for the fixture backend")
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SystemInfoProvider for Router {
+    async fn system_info(&self) -> Result<SystemInfo, RouterError> {
+        match self {
+            Self::Ubus(router) => router.system_info().await,
+            Self::Fixture(router) => router.system_info().await,
+        }
+    }
+
+    async fn board_info(&self) -> Result<BoardInfo, RouterError> {
+        match self {
+            Self::Ubus(router) => router.board_info().await,
+            Self::Fixture(router) => router.board_info().await,
+        }
+    }
+}
+
+#[async_trait]
+impl WifiInfoProvider for Router {
+    async fn wireless_status(&self) -> Result<WirelessStatus, RouterError> {
+        match self {
+            Self::Ubus(router) => router.wireless_status().await,
+            Self::Fixture(router) => router.wireless_status().await,
+        }
+    }
+
+    async fn wifi_clients(&self, iface: &str) -> Result<HostapdClients, RouterError> {
+        match self {
+            Self::Ubus(router) => router.wifi_clients(iface).await,
+            Self::Fixture(router) => router.wifi_clients(iface).await,
+        }
+    }
+
+    async fn wifi_stations(&self, iface: &str) -> Result<AssocList, RouterError> {
+        match self {
+            Self::Ubus(router) => router.wifi_stations(iface).await,
+            Self::Fixture(router) => router.wifi_stations(iface).await,
+        }
+    }
+
+    async fn wifi_scan(&self, iface: &str) -> Result<ScanList, RouterError> {
+        match self {
+            Self::Ubus(router) => router.wifi_scan(iface).await,
+            Self::Fixture(router) => router.wifi_scan(iface).await,
+        }
+    }
+
+    async fn wifi_survey(&self, iface: &str) -> Result<SurveyList, RouterError> {
+        match self {
+            Self::Ubus(router) => router.wifi_survey(iface).await,
+            Self::Fixture(router) => router.wifi_survey(iface).await,
+        }
+    }
+}
+
+#[async_trait]
+impl NetworkInfoProvider for Router {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError> {
+        match self {
+            Self::Ubus(router) => router.dhcp_leases().await,
+            Self::Fixture(router) => router.dhcp_leases().await,
+        }
+    }
+}
+
+#[async_trait]
+impl WifiControl for Router {
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError> {
+        match self {
+            Self::Ubus(router) => router.set_channel(radio, channel).await,
+            Self::Fixture(router) => router.set_channel(radio, channel).await,
+        }
+    }
+
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError> {
+        match self {
+            Self::Ubus(router) => router.configure_interfaces(changes).await,
+            Self::Fixture(router) => router.configure_interfaces(changes).await,
+        }
+    }
+}
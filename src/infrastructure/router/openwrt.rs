@@ -1,70 +1,173 @@
 //! OpenWRT router via ubus.
 
+use std::path::PathBuf;
 use std::process::Output;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use serde_json::{Value, json};
 use tokio::process::Command;
+use tokio::sync::Semaphore;
 
 use crate::domain::{
     RouterError,
-    router::{SystemInfoProvider, WifiInfoProvider},
-    ubus::{BoardInfo, HostapdClients, SystemInfo, WirelessStatus},
+    router::{NetworkInfoProvider, SystemInfoProvider, WifiControl, WifiInfoProvider},
+    ubus::{
+        AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+        WirelessStatus,
+    },
+    wifi_settings::InterfaceOptions,
 };
+use crate::infrastructure::config::RouterConfig;
 
-pub struct OpenWrtRouter;
+/// Wait before the first retry; doubles on each further one.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+pub struct OpenWrtRouter {
+    program: PathBuf,
+    timeout: Duration,
+    scan_timeout: Duration,
+    retries: u32,
+    permits: Semaphore,
+}
 
 impl OpenWrtRouter {
-    pub fn new() -> Self {
-        Self
+    pub fn new(config: &RouterConfig) -> Self {
+        Self {
+            program: PathBuf::from("ubus"),
+            timeout: config.timeout,
+            scan_timeout: config.scan_timeout,
+            retries: config.retries,
+            permits: Semaphore::new(config.max_concurrent),
+        }
     }
 
     async fn ubus_call<T: serde::de::DeserializeOwned>(
         &self,
-        service: &str,
+        object: &str,
         method: &str,
     ) -> Result<T, RouterError> {
-        let output = self.execute_ubus(service, method).await?;
-        self.check_success(&output)?;
-        self.parse_response(&output)
+        self.ubus_call_with(object, method, None).await
     }
 
-    async fn execute_ubus(&self, service: &str, method: &str) -> Result<Output, RouterError> {
-        Command::new("ubus")
-            .args(["call", service, method])
-            .output()
+    async fn ubus_call_with<T: serde::de::DeserializeOwned>(
+        &self,
+        object: &str,
+        method: &str,
+        message: Option<&Value>,
+    ) -> Result<T, RouterError> {
+        self.ubus_call_within(object, method, message, self.timeout)
             .await
-            .map_err(|source| RouterError::Spawn {
+    }
+
+    /// Calls `object method [message]`, retrying transient failures with
+    /// backoff; each attempt gets `timeout`.
+    async fn ubus_call_within<T: serde::de::DeserializeOwned>(
+        &self,
+        object: &str,
+        method: &str,
+        message: Option<&Value>,
+        timeout: Duration,
+    ) -> Result<T, RouterError> {
+        let mut delay = RETRY_DELAY;
+        for _ in 0..self.retries {
+            match self.call_once(object, method, message, timeout).await {
+                Err(e) if e.is_transient() => {
+                    tracing::debug!("{e}; retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        self.call_once(object, method, message, timeout).await
+    }
+
+    async fn call_once<T: serde::de::DeserializeOwned>(
+        &self,
+        object: &str,
+        method: &str,
+        message: Option<&Value>,
+        timeout: Duration,
+    ) -> Result<T, RouterError> {
+        let output = self.execute_ubus(object, method, message, timeout).await?;
+        self.check_success(object, method, &output)?;
+        self.parse_response(object, method, &output)
+    }
+
+    /// Runs `ubus`, killing it if it does not finish within `timeout`.
+    async fn execute_ubus(
+        &self,
+        object: &str,
+        method: &str,
+        message: Option<&Value>,
+        timeout: Duration,
+    ) -> Result<Output, RouterError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+        let mut command = Command::new(&self.program);
+        command.args(["call", object, method]);
+        if let Some(message) = message {
+            command.arg(message.to_string());
+        }
+        let output = command.kill_on_drop(true).output();
+
+        match tokio::time::timeout(timeout, output).await {
+            Ok(output) => output.map_err(|source| RouterError::Spawn {
                 cmd: "ubus",
-                source,
-            })
+                source: Arc::new(source),
+            }),
+            Err(_) => Err(RouterError::Timeout {
+                object: object.to_string(),
+                method: method.to_string(),
+            }),
+        }
     }
 
-    fn check_success(&self, output: &Output) -> Result<(), RouterError> {
+    fn check_success(
+        &self,
+        object: &str,
+        method: &str,
+        output: &Output,
+    ) -> Result<(), RouterError> {
         if output.status.success() {
             Ok(())
         } else {
-            Err(RouterError::NonZeroExit {
-                cmd: "ubus",
-                code: output.status.code().unwrap_or(-1),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-            })
+            Err(RouterError::from_status(
+                object,
+                method,
+                output.status.code().unwrap_or(-1),
+                &String::from_utf8_lossy(&output.stderr),
+            ))
         }
     }
 
+    /// Methods without a reply print nothing, which reads as `null`.
     fn parse_response<T: serde::de::DeserializeOwned>(
         &self,
+        object: &str,
+        method: &str,
         output: &Output,
     ) -> Result<T, RouterError> {
-        serde_json::from_slice(&output.stdout).map_err(|source| RouterError::Json {
-            cmd: "ubus",
-            source,
+        let stdout = match output.stdout.trim_ascii() {
+            [] => b"null".as_slice(),
+            stdout => stdout,
+        };
+        serde_json::from_slice(stdout).map_err(|source| RouterError::Json {
+            object: object.to_string(),
+            method: method.to_string(),
+            source: Arc::new(source),
         })
     }
 }
 
 impl Default for OpenWrtRouter {
     fn default() -> Self {
-        Self::new()
+        Self::new(&RouterConfig::default())
     }
 }
 
@@ -89,4 +192,148 @@ impl WifiInfoProvider for OpenWrtRouter {
         self.ubus_call(&format!("hostapd.{iface}"), "get_clients")
             .await
     }
+
+    async fn wifi_stations(&self, iface: &str) -> Result<AssocList, RouterError> {
+        self.ubus_call_with("iwinfo", "assoclist", Some(&json!({ "device": iface })))
+            .await
+    }
+
+    async fn wifi_scan(&self, iface: &str) -> Result<ScanList, RouterError> {
+        let message = json!({ "device": iface });
+        self.ubus_call_within("iwinfo", "scan", Some(&message), self.scan_timeout)
+            .await
+    }
+
+    async fn wifi_survey(&self, iface: &str) -> Result<SurveyList, RouterError> {
+        self.ubus_call_with("iwinfo", "survey", Some(&json!({ "device": iface })))
+            .await
+    }
+}
+
+#[async_trait]
+impl NetworkInfoProvider for OpenWrtRouter {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError> {
+        self.ubus_call("luci-rpc", "getDHCPLeases").await
+    }
+}
+
+#[async_trait]
+impl WifiControl for OpenWrtRouter {
+    /// Committing `wireless` makes procd reload the radios that changed.
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError> {
+        let values = json!({
+            "config": "wireless",
+            "section": radio,
+            "values": { "channel": channel.to_string() },
+        });
+        self.ubus_call_with::<()>("uci", "set", Some(&values))
+            .await?;
+        self.ubus_call_with("uci", "commit", Some(&json!({ "config": "wireless" })))
+            .await
+    }
+
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError> {
+        for change in changes {
+            let values: serde_json::Map<_, _> = change
+                .values
+                .iter()
+                .map(|(option, value)| (option.to_string(), json!(value)))
+                .collect();
+            let message = json!({
+                "config": "wireless",
+                "section": change.section,
+                "values": values,
+            });
+            self.ubus_call_with::<()>("uci", "set", Some(&message))
+                .await?;
+        }
+        self.ubus_call_with("uci", "commit", Some(&json!({ "config": "wireless" })))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Instant;
+
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    /// Stands in for `ubus` with a shell script.
+    fn fake_ubus(name: &str, script: &str, config: &RouterConfig) -> (OpenWrtRouter, TempDir) {
+        let dir = TempDir::new(&format!("ubus-{name}"));
+        let program = dir.join("ubus");
+        std::fs::write(
+            &program,
+            format!("#!/bin/sh\ncd {}\n{script}", dir.path().display()),
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut router = OpenWrtRouter::new(config);
+        router.program = program;
+        (router, dir)
+    }
+
+    fn attempts(dir: &TempDir) -> usize {
+        std::fs::read_to_string(dir.join("attempts"))
+            .map(|s| s.lines().count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn kills_hung_calls_and_retries_them() {
+        let config = RouterConfig {
+            timeout: Duration::from_millis(200),
+            retries: 1,
+            ..RouterConfig::default()
+        };
+        let (router, dir) = fake_ubus("hung", "echo >> attempts\nexec sleep 10\n", &config);
+
+        let started = Instant::now();
+        let error = router.board_info().await.unwrap_err();
+
+        assert!(matches!(error, RouterError::Timeout { .. }), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(attempts(&dir), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_lost_connections_but_not_missing_objects() {
+        let script = "echo >> attempts\n\
+            [ \"$2\" = hostapd.wlan9 ] && { echo 'Command failed: Not found' >&2; exit 4; }\n\
+            [ $(wc -l < attempts) -lt 3 ] && { echo 'Command failed: Connection failed' >&2; exit 10; }\n\
+            echo '{\"freq\": 2412, \"clients\": {}}'\n";
+        let (router, dir) = fake_ubus("flaky", script, &RouterConfig::default());
+
+        assert!(router.wifi_clients("wlan0").await.is_ok());
+        assert_eq!(attempts(&dir), 3);
+
+        let error = router.wifi_clients("wlan9").await.unwrap_err();
+        assert!(matches!(error, RouterError::NotFound { .. }), "{error}");
+        assert_eq!(attempts(&dir), 4);
+    }
+
+    #[tokio::test]
+    async fn sets_and_commits_a_channel() {
+        let (router, dir) = fake_ubus(
+            "channel",
+            "echo \"$2 $3 $4\" >> attempts\n",
+            &RouterConfig::default(),
+        );
+
+        router.set_channel("radio1", 149).await.unwrap();
+
+        let calls = std::fs::read_to_string(dir.join("attempts")).unwrap();
+        let calls: Vec<&str> = calls.lines().collect();
+        assert_eq!(
+            calls,
+            [
+                r#"uci set {"config":"wireless","section":"radio1","values":{"channel":"149"}}"#,
+                r#"uci commit {"config":"wireless"}"#,
+            ]
+        );
+    }
 }
@@ -0,0 +1,107 @@
+//! procd service integration: the `/etc/init.d` script.
+
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+
+pub const INIT_SCRIPT_PATH: &str = "/etc/init.d/tb-router";
+
+/// Writes the init script for the running executable.
+///
+/// `config` is passed as `--config` when the file exists at service start,
+/// so a UCI-only setup works too.
+pub fn install(config: &Path) -> anyhow::Result<PathBuf> {
+    let exe = std::env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .context("cannot locate the executable")?;
+    let path = PathBuf::from(INIT_SCRIPT_PATH);
+
+    std::fs::write(&path, init_script(&exe, config))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("failed to make {} executable", path.display()))?;
+    Ok(path)
+}
+
+/// Restarts the service through its init script.
+///
+/// The script runs in its own process group, so it survives procd stopping
+/// this process.
+pub fn restart() -> std::io::Result<()> {
+    Command::new(INIT_SCRIPT_PATH)
+        .arg("restart")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map(drop)
+}
+
+/// `reload` sends SIGHUP, which reloads the config without a restart; so
+/// does `uci commit tb-router`.
+pub fn init_script(exe: &Path, config: &Path) -> String {
+    format!(
+        "\
+#!/bin/sh /etc/rc.common
+# Generated by `tb-router install-service`.
+
+USE_PROCD=1
+START=95
+STOP=10
+
+PROG={prog}
+CONFIG={config}
+
+start_service() {{
+\tprocd_open_instance
+\tprocd_set_param command \"$PROG\"
+\t[ -f \"$CONFIG\" ] && procd_append_param command --config \"$CONFIG\"
+\tprocd_append_param command run
+\t# Restart on any exit; give up after 5 exits within an hour.
+\tprocd_set_param respawn 3600 5 5
+\tprocd_set_param stdout 1
+\tprocd_set_param stderr 1
+\t# Long polling can take a while to wind down.
+\tprocd_set_param term_timeout 30
+\tprocd_close_instance
+}}
+
+reload_service() {{
+\tprocd_send_signal tb-router
+}}
+
+service_triggers() {{
+\tprocd_add_reload_trigger tb-router
+}}
+",
+        prog = shell_quote(&exe.display().to_string()),
+        config = shell_quote(&config.display().to_string()),
+    )
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_script_runs_binary_under_procd() {
+        let script = init_script(
+            Path::new("/usr/bin/tb-router"),
+            Path::new("/etc/tb router/it's.toml"),
+        );
+
+        assert!(script.starts_with("#!/bin/sh /etc/rc.common\n"));
+        assert!(script.contains("PROG='/usr/bin/tb-router'\n"));
+        assert!(script.contains(r"CONFIG='/etc/tb router/it'\''s.toml'"));
+        assert!(script.contains("procd_set_param respawn"));
+        assert!(script.contains("procd_add_reload_trigger tb-router"));
+    }
+}
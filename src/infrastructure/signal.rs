@@ -1,13 +1,40 @@
 use async_trait::async_trait;
-use tokio::signal::unix::{SignalKind, signal};
+use tokio::signal::unix::{Signal, SignalKind, signal};
+use tokio::sync::Mutex;
 
-use crate::domain::signal::{ShutdownKind, SignalHandler};
+use crate::domain::signal::{ShutdownKind, SignalEvent, SignalHandler};
 
-pub struct UnixSignalHandler;
+/// Signal streams are created once and kept, so signals arriving between
+/// two `next_event` calls are queued instead of lost.
+struct Streams {
+    sigterm: Signal,
+    sigint: Signal,
+    sighup: Signal,
+    sigusr1: Signal,
+    sigusr2: Signal,
+}
+
+impl Streams {
+    fn new() -> Self {
+        Self {
+            sigterm: signal(SignalKind::terminate()).expect("SIGTERM handler"),
+            sigint: signal(SignalKind::interrupt()).expect("SIGINT handler"),
+            sighup: signal(SignalKind::hangup()).expect("SIGHUP handler"),
+            sigusr1: signal(SignalKind::user_defined1()).expect("SIGUSR1 handler"),
+            sigusr2: signal(SignalKind::user_defined2()).expect("SIGUSR2 handler"),
+        }
+    }
+}
+
+pub struct UnixSignalHandler {
+    streams: Mutex<Option<Streams>>,
+}
 
 impl UnixSignalHandler {
     pub fn new() -> Self {
-        Self
+        Self {
+            streams: Mutex::new(None),
+        }
     }
 }
 
@@ -19,15 +46,16 @@ impl Default for UnixSignalHandler {
 
 #[async_trait]
 impl SignalHandler for UnixSignalHandler {
-    async fn wait_for_shutdown(&self) -> ShutdownKind {
-        let mut sigterm = signal(SignalKind::terminate()).expect("SIGTERM handler");
-        let mut sigint = signal(SignalKind::interrupt()).expect("SIGINT handler");
-        let mut sighup = signal(SignalKind::hangup()).expect("SIGHUP handler");
+    async fn next_event(&self) -> SignalEvent {
+        let mut guard = self.streams.lock().await;
+        let streams = guard.get_or_insert_with(Streams::new);
 
         tokio::select! {
-            _ = sigterm.recv() => ShutdownKind::Terminate,
-            _ = sigint.recv() => ShutdownKind::Interrupt,
-            _ = sighup.recv() => ShutdownKind::Hangup,
+            _ = streams.sigterm.recv() => SignalEvent::Shutdown(ShutdownKind::Terminate),
+            _ = streams.sigint.recv() => SignalEvent::Shutdown(ShutdownKind::Interrupt),
+            _ = streams.sighup.recv() => SignalEvent::Reload,
+            _ = streams.sigusr1.recv() => SignalEvent::MoreVerbose,
+            _ = streams.sigusr2.recv() => SignalEvent::LessVerbose,
         }
     }
 }
@@ -0,0 +1,278 @@
+//! Tracing layer that forwards records to the local syslog socket.
+//!
+//! On OpenWrt this is logd's `/dev/log`, so records show up in `logread`.
+
+use std::fmt::Write as _;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use super::config::SyslogFormat;
+
+/// `daemon` facility.
+const FACILITY: u8 = 3;
+const MAX_MESSAGE_LEN: usize = 8 * 1024;
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub struct SyslogLayer {
+    socket_path: PathBuf,
+    format: SyslogFormat,
+    ident: String,
+    target: bool,
+    hostname: String,
+    pid: u32,
+    socket: Mutex<Option<UnixDatagram>>,
+}
+
+impl SyslogLayer {
+    pub fn new(socket_path: PathBuf, format: SyslogFormat, ident: String) -> Self {
+        Self {
+            socket_path,
+            format,
+            ident,
+            target: true,
+            hostname: hostname(),
+            pid: std::process::id(),
+            socket: Mutex::new(None),
+        }
+    }
+
+    /// Whether to prefix messages with the event's target, as the file log does.
+    pub fn with_target(mut self, target: bool) -> Self {
+        self.target = target;
+        self
+    }
+
+    fn frame(&self, level: &Level, message: &str, now: SystemTime) -> String {
+        let pri = FACILITY * 8 + severity(level);
+        let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let (date, time) = civil_time(secs.as_secs());
+
+        let mut frame = match self.format {
+            SyslogFormat::Rfc3164 => format!(
+                "<{pri}>{} {:>2} {time} {}[{}]: {message}",
+                MONTHS[usize::from(date.1 - 1)],
+                date.2,
+                self.ident,
+                self.pid
+            ),
+            SyslogFormat::Rfc5424 => format!(
+                "<{pri}>1 {:04}-{:02}-{:02}T{time}.{:06}Z {} {} {} - - {message}",
+                date.0,
+                date.1,
+                date.2,
+                secs.subsec_micros(),
+                self.hostname,
+                self.ident,
+                self.pid
+            ),
+        };
+
+        if frame.len() > MAX_MESSAGE_LEN {
+            let mut end = MAX_MESSAGE_LEN;
+            while !frame.is_char_boundary(end) {
+                end -= 1;
+            }
+            frame.truncate(end);
+        }
+        frame
+    }
+
+    /// Sends without blocking; records are dropped if logd is busy or gone.
+    ///
+    /// The socket is reconnected on the next record after an error, so a
+    /// logd restart does not silence us for good.
+    fn send(&self, frame: &str) {
+        let Ok(mut socket) = self.socket.lock() else {
+            return;
+        };
+
+        if socket.is_none() {
+            *socket = connect(&self.socket_path).ok();
+        }
+        if let Some(s) = socket.as_ref()
+            && let Err(e) = s.send(frame.as_bytes())
+            && e.kind() != io::ErrorKind::WouldBlock
+        {
+            *socket = None;
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SyslogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let message = if self.target {
+            format!("{}: {}", meta.target(), visitor.finish())
+        } else {
+            visitor.finish()
+        };
+        self.send(&self.frame(meta.level(), &message, SystemTime::now()));
+    }
+}
+
+fn connect(path: &std::path::Path) -> io::Result<UnixDatagram> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Splits a UNIX timestamp into a UTC `(year, month, day)` and `hh:mm:ss`.
+fn civil_time(secs: u64) -> ((i64, u8, u8), String) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let time = format!("{:02}:{:02}:{:02}", rem / 3600, rem % 3600 / 60, rem % 60);
+
+    // Howard Hinnant's days-to-civil algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    ((year, month, day), time)
+}
+
+/// Renders the message followed by the other fields as `key=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        self.message + &self.fields
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    fn receive(socket: &UnixDatagram) -> String {
+        let mut buf = [0u8; 1024];
+        let len = socket.recv(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    }
+
+    #[test]
+    fn sends_rfc3164_records_with_severity() {
+        let dir = TempDir::new("syslog");
+        let path = dir.join("log");
+        let server = UnixDatagram::bind(&path).unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let layer = SyslogLayer::new(path.clone(), SyslogFormat::Rfc3164, "tb-router".into());
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(user_id = 42, "disk almost full");
+            tracing::error!("gone");
+        });
+
+        let pid = std::process::id();
+        let warn = receive(&server);
+        assert!(warn.starts_with("<28>"), "{warn}");
+        assert!(
+            warn.ends_with(&format!(
+                "tb-router[{pid}]: tb_router::infrastructure::syslog::tests: disk almost full user_id=42"
+            )),
+            "{warn}"
+        );
+        assert!(receive(&server).starts_with("<27>"));
+    }
+
+    #[test]
+    fn frames_rfc5424() {
+        let layer = SyslogLayer::new(
+            PathBuf::from("/nonexistent"),
+            SyslogFormat::Rfc5424,
+            "tb-router".into(),
+        );
+        let now = UNIX_EPOCH + Duration::from_micros(1_760_856_518_000_042);
+
+        let frame = layer.frame(&Level::INFO, "hello", now);
+
+        let expected = format!(
+            "<30>1 2025-10-19T06:48:38.000042Z {} tb-router {} - - hello",
+            layer.hostname, layer.pid
+        );
+        assert_eq!(frame, expected);
+    }
+
+    #[test]
+    fn frames_rfc3164_timestamp() {
+        let layer = SyslogLayer::new(
+            PathBuf::from("/nonexistent"),
+            SyslogFormat::Rfc3164,
+            "tb".into(),
+        );
+        let now = UNIX_EPOCH + Duration::from_secs(951_782_400);
+
+        let frame = layer.frame(&Level::DEBUG, "leap", now);
+
+        assert_eq!(
+            frame,
+            format!("<31>Feb 29 00:00:00 tb[{}]: leap", layer.pid)
+        );
+    }
+}
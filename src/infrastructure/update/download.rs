@@ -0,0 +1,34 @@
+//! Size-capped HTTP downloads.
+
+use crate::domain::UpdateError;
+
+pub async fn fetch(client: &reqwest::Client, url: &str, max: u64) -> Result<Vec<u8>, UpdateError> {
+    let failed = |e: reqwest::Error| UpdateError::Download {
+        url: url.to_string(),
+        message: e.without_url().to_string(),
+    };
+    let too_large = || UpdateError::TooLarge {
+        url: url.to_string(),
+        max,
+    };
+
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(failed)?;
+    if response.content_length().is_some_and(|len| len > max) {
+        return Err(too_large());
+    }
+
+    // The length header may be missing or wrong, so count as we go.
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(failed)? {
+        if (data.len() + chunk.len()) as u64 > max {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
@@ -0,0 +1,492 @@
+//! Self-update from a published release binary.
+//!
+//! Next to the executable `<exe>` live:
+//!
+//! - `<exe>.new`: a download being verified
+//! - `<exe>.old`: the binary replaced by the last update, for rollback
+//! - `<exe>.pending`: how often a new binary started without its bots yet
+//!   running for `update.health_timeout`
+//!
+//! A new binary that fails to get that far `MAX_START_ATTEMPTS` times in a
+//! row is rolled back on its next start.
+
+mod download;
+mod verify;
+
+use std::cmp::Ordering;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::domain::{UpdateError, UpdateOutcome, Updater};
+
+use super::config::UpdateConfig;
+
+const MAX_START_ATTEMPTS: u32 = 3;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Cap for the `.manifest` and `.sig` files.
+const SIDE_FILE_MAX: u64 = 4096;
+const VERSION_PREFIX: &str = "tb-router ";
+
+pub struct SelfUpdater {
+    client: reqwest::Client,
+    config: UpdateConfig,
+    exe: PathBuf,
+}
+
+impl SelfUpdater {
+    pub fn new(config: UpdateConfig) -> anyhow::Result<Self> {
+        Ok(Self::with_exe(config, current_exe()?))
+    }
+
+    fn with_exe(config: UpdateConfig, exe: PathBuf) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config,
+            exe,
+        }
+    }
+
+    fn url(&self) -> Result<String, UpdateError> {
+        let url = self.config.url.as_ref().ok_or(UpdateError::NotConfigured)?;
+        Ok(url.replace("{arch}", std::env::consts::ARCH))
+    }
+
+    async fn fetch_manifest(&self, url: &str) -> Result<verify::Manifest, UpdateError> {
+        let manifest_url = format!("{url}.manifest");
+        let manifest = download::fetch(&self.client, &manifest_url, SIDE_FILE_MAX).await?;
+        let signature_url = format!("{url}.sig");
+        let signature = download::fetch(&self.client, &signature_url, SIDE_FILE_MAX).await?;
+        verify::manifest(&manifest, &signature, &self.config.public_key)
+    }
+}
+
+#[async_trait]
+impl Updater for SelfUpdater {
+    async fn update(&self) -> Result<UpdateOutcome, UpdateError> {
+        let url = self.url()?;
+        let manifest = self.fetch_manifest(&url).await?;
+
+        let current = env!("CARGO_PKG_VERSION").to_string();
+        let version = manifest.version.clone();
+        match verify::compare_versions(&version, &current)? {
+            Ordering::Greater => {}
+            Ordering::Equal => return Ok(UpdateOutcome::UpToDate { version }),
+            Ordering::Less => {
+                return Err(UpdateError::Downgrade {
+                    offered: version,
+                    current,
+                });
+            }
+        }
+
+        let binary = download::fetch(&self.client, &url, self.config.max_size).await?;
+        verify::checksum(&binary, &manifest)?;
+
+        let staged = sibling(&self.exe, "new");
+        write_executable(&staged, &binary)
+            .await
+            .map_err(io("stage the new binary"))?;
+        let probed = match probe_version(&staged).await {
+            Ok(reported) if reported == version => Ok(()),
+            Ok(reported) => Err(UpdateError::BadBinary(format!(
+                "it reports version {reported}, the manifest {version}"
+            ))),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = probed {
+            let _ = tokio::fs::remove_file(&staged).await;
+            return Err(e);
+        }
+
+        let exe = self.exe.clone();
+        blocking(move || install(&exe, &staged)).await?;
+        tracing::info!("Installed tb-router {version} over {current}");
+        Ok(UpdateOutcome::Installed {
+            from: current,
+            to: version,
+        })
+    }
+
+    async fn rollback(&self) -> Result<UpdateOutcome, UpdateError> {
+        let exe = self.exe.clone();
+        blocking(move || restore(&exe)).await?;
+        tracing::warn!("Rolled back to the previous binary");
+        Ok(UpdateOutcome::RolledBack)
+    }
+
+    fn restart(&self) -> Result<(), UpdateError> {
+        super::service::restart().map_err(io("restart the service"))
+    }
+}
+
+/// Tracks a freshly installed binary at startup.
+///
+/// Counts the start and returns the probation the binary is on, if any.
+/// Rolls back and returns an error if it already used up its attempts;
+/// exiting then lets procd start the previous binary.
+pub fn check_pending() -> anyhow::Result<Option<Probation>> {
+    let exe = current_exe()?;
+    Ok(record_start(&exe)?.map(|attempt| Probation { exe, attempt }))
+}
+
+/// A freshly installed binary that has not yet proven itself.
+///
+/// Until [`Probation::passed`] the start stays counted, so a start that ends
+/// with the bots giving up or the watchdog firing counts as failed.
+#[derive(Debug)]
+pub struct Probation {
+    exe: PathBuf,
+    /// This start's number, as written to the marker.
+    attempt: u32,
+}
+
+impl Probation {
+    /// Trusts the binary from now on.
+    pub async fn passed(self) {
+        match tokio::fs::remove_file(sibling(&self.exe, "pending")).await {
+            Ok(()) => tracing::info!("Update passed its health check"),
+            Err(e) => tracing::warn!("Failed to clear the pending update marker: {e}"),
+        }
+    }
+
+    /// Takes this start back after a clean shutdown, which says nothing
+    /// about the binary.
+    pub async fn interrupted(self) {
+        let marker = sibling(&self.exe, "pending");
+        if let Err(e) = tokio::fs::write(&marker, (self.attempt - 1).to_string()).await {
+            tracing::warn!("Failed to update the pending update marker: {e}");
+        }
+    }
+}
+
+/// Returns the start number of a new binary on probation.
+fn record_start(exe: &Path) -> anyhow::Result<Option<u32>> {
+    let marker = sibling(exe, "pending");
+    let attempts = match std::fs::read_to_string(&marker) {
+        Ok(text) => text.trim().parse::<u32>().unwrap_or(MAX_START_ATTEMPTS),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    if attempts >= MAX_START_ATTEMPTS {
+        restore(exe)?;
+        anyhow::bail!("the new binary failed {attempts} times, rolled back to the previous one");
+    }
+
+    let attempt = attempts + 1;
+    std::fs::write(&marker, attempt.to_string())?;
+    tracing::info!("Running a new binary, start {attempt} of {MAX_START_ATTEMPTS}");
+    Ok(Some(attempt))
+}
+
+/// Keeps a copy of `exe` for rollback, then renames `staged` over it.
+///
+/// The rename is atomic, so `exe` is never missing or half-written.
+fn install(exe: &Path, staged: &Path) -> Result<(), UpdateError> {
+    let marker = sibling(exe, "pending");
+    std::fs::copy(exe, sibling(exe, "old")).map_err(io("back up the current binary"))?;
+    std::fs::write(&marker, "0").map_err(io("mark the update pending"))?;
+
+    std::fs::rename(staged, exe).map_err(|e| {
+        let _ = std::fs::remove_file(&marker);
+        io("replace the binary")(e)
+    })
+}
+
+fn restore(exe: &Path) -> Result<(), UpdateError> {
+    let backup = sibling(exe, "old");
+    if !backup.exists() {
+        return Err(UpdateError::NoBackup);
+    }
+    std::fs::rename(&backup, exe).map_err(io("restore the previous binary"))?;
+
+    match std::fs::remove_file(sibling(exe, "pending")) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(io("clear the pending update marker")(e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs `<binary> --version`, which also proves it runs on this CPU.
+async fn probe_version(binary: &Path) -> Result<String, UpdateError> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(PROBE_TIMEOUT, output)
+        .await
+        .map_err(|_| UpdateError::BadBinary("--version timed out".to_string()))?
+        .map_err(|e| UpdateError::BadBinary(e.to_string()))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().strip_prefix(VERSION_PREFIX) {
+        Some(version) if output.status.success() => Ok(version.to_string()),
+        _ => Err(UpdateError::BadBinary(format!(
+            "unexpected --version output '{}'",
+            stdout.trim()
+        ))),
+    }
+}
+
+async fn write_executable(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    tokio::fs::write(path, data).await?;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).await
+}
+
+/// Runs a sequence of blocking file operations off the async workers.
+async fn blocking(
+    work: impl FnOnce() -> Result<(), UpdateError> + Send + 'static,
+) -> Result<(), UpdateError> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .unwrap_or_else(|e| Err(io("finish the file operations")(std::io::Error::other(e))))
+}
+
+fn current_exe() -> anyhow::Result<PathBuf> {
+    Ok(std::env::current_exe()?.canonicalize()?)
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let name = exe.file_name().unwrap_or_default().to_string_lossy();
+    exe.with_file_name(format!("{name}.{suffix}"))
+}
+
+fn io(action: &str) -> impl FnOnce(std::io::Error) -> UpdateError + '_ {
+    move |source| UpdateError::Io {
+        action: action.to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use ring::digest::{SHA256, digest};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::testing::TempDir;
+
+    use super::*;
+
+    const NEW_BINARY: &[u8] = b"#!/bin/sh\necho 'tb-router 99.0.0'\n";
+
+    /// Serves `files` by path over plain HTTP/1.1.
+    async fn serve(files: HashMap<String, Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let files = Arc::new(files);
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let files = Arc::clone(&files);
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 4096];
+                    let len = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..len]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+                    let (status, body) = match files.get(path) {
+                        Some(body) => ("200 OK", body.clone()),
+                        None => ("404 Not Found", Vec::new()),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(&body).await;
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    struct Release {
+        key: Ed25519KeyPair,
+        files: HashMap<String, Vec<u8>>,
+    }
+
+    impl Release {
+        fn new(binary: &[u8], version: &str) -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+            let mut release = Self {
+                key,
+                files: HashMap::from([("/tb-router".to_string(), binary.to_vec())]),
+            };
+            release.sign(&manifest(binary, version));
+            release
+        }
+
+        fn sign(&mut self, manifest: &str) {
+            let signature = self.key.sign(manifest.as_bytes()).as_ref().to_vec();
+            self.files
+                .insert("/tb-router.manifest".into(), manifest.as_bytes().to_vec());
+            self.files.insert("/tb-router.sig".into(), signature);
+        }
+
+        async fn updater(self, exe: &Path) -> SelfUpdater {
+            let base = serve(self.files).await;
+            let config = UpdateConfig {
+                url: Some(format!("{base}/tb-router")),
+                public_key: self.key.public_key().as_ref().to_vec(),
+                ..UpdateConfig::default()
+            };
+            SelfUpdater::with_exe(config, exe.to_path_buf())
+        }
+    }
+
+    fn manifest(binary: &[u8], version: &str) -> String {
+        let sha256 = hex::encode(digest(&SHA256, binary));
+        format!("version {version}\nsha256 {sha256}\n")
+    }
+
+    /// The running binary, in a directory removed with the returned guard.
+    fn temp_exe(name: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new(&format!("update-{name}"));
+        let exe = dir.join("tb-router");
+        std::fs::write(&exe, b"old binary").unwrap();
+        (dir, exe)
+    }
+
+    #[tokio::test]
+    async fn installs_verified_binary_and_rolls_back() {
+        let (_dir, exe) = temp_exe("install");
+        let updater = Release::new(NEW_BINARY, "99.0.0").updater(&exe).await;
+
+        let outcome = updater.update().await.unwrap();
+
+        assert_eq!(
+            outcome,
+            UpdateOutcome::Installed {
+                from: env!("CARGO_PKG_VERSION").to_string(),
+                to: "99.0.0".to_string()
+            }
+        );
+        assert_eq!(std::fs::read(&exe).unwrap(), NEW_BINARY);
+        assert_eq!(std::fs::read(sibling(&exe, "old")).unwrap(), b"old binary");
+        assert!(sibling(&exe, "pending").exists());
+        assert!(!sibling(&exe, "new").exists());
+
+        updater.rollback().await.unwrap();
+
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+        assert!(!sibling(&exe, "pending").exists());
+        assert!(matches!(
+            updater.rollback().await,
+            Err(UpdateError::NoBackup)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_binary_and_manifest() {
+        let (_dir, exe) = temp_exe("tampered");
+        let tampered = b"#!/bin/sh\necho 'tb-router 99.0.0'\necho pwned\n".to_vec();
+        let mut release = Release::new(NEW_BINARY, "99.0.0");
+        release.files.insert("/tb-router".into(), tampered.clone());
+        let updater = release.updater(&exe).await;
+
+        let error = updater.update().await.unwrap_err();
+        assert!(matches!(error, UpdateError::Checksum { .. }), "{error}");
+
+        let mut release = Release::new(NEW_BINARY, "99.0.0");
+        release.files.insert("/tb-router".into(), tampered.clone());
+        release.files.insert(
+            "/tb-router.manifest".into(),
+            manifest(&tampered, "99.0.0").into(),
+        );
+        let updater = release.updater(&exe).await;
+
+        let error = updater.update().await.unwrap_err();
+        assert!(matches!(error, UpdateError::Signature), "{error}");
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+    }
+
+    #[tokio::test]
+    async fn rejects_older_and_mislabelled_releases() {
+        let (_dir, exe) = temp_exe("versions");
+        let old = b"#!/bin/sh\necho 'tb-router 0.0.1'\n";
+        let updater = Release::new(old, "0.0.1").updater(&exe).await;
+
+        let error = updater.update().await.unwrap_err();
+        assert!(matches!(error, UpdateError::Downgrade { .. }), "{error}");
+
+        let current = env!("CARGO_PKG_VERSION");
+        let updater = Release::new(NEW_BINARY, current).updater(&exe).await;
+        let outcome = updater.update().await.unwrap();
+        assert_eq!(
+            outcome,
+            UpdateOutcome::UpToDate {
+                version: current.to_string()
+            }
+        );
+
+        let updater = Release::new(old, "99.0.0").updater(&exe).await;
+        let error = updater.update().await.unwrap_err();
+        assert!(matches!(error, UpdateError::BadBinary(_)), "{error}");
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+        assert!(!sibling(&exe, "new").exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_oversize_binaries() {
+        let (_dir, exe) = temp_exe("oversize");
+        let mut updater = Release::new(NEW_BINARY, "99.0.0").updater(&exe).await;
+
+        updater.config.max_size = 8;
+        let error = updater.update().await.unwrap_err();
+        assert!(matches!(error, UpdateError::TooLarge { .. }), "{error}");
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+    }
+
+    #[test]
+    fn rolls_back_after_repeated_failed_starts() {
+        let (_dir, exe) = temp_exe("attempts");
+        std::fs::write(sibling(&exe, "old"), b"previous").unwrap();
+        std::fs::write(sibling(&exe, "pending"), "0").unwrap();
+
+        for _ in 0..MAX_START_ATTEMPTS {
+            assert!(record_start(&exe).unwrap().is_some());
+        }
+        assert!(record_start(&exe).is_err());
+
+        assert_eq!(std::fs::read(&exe).unwrap(), b"previous");
+        assert_eq!(record_start(&exe).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_start_counted_until_it_passes() {
+        let (_dir, exe) = temp_exe("probation");
+        let marker = sibling(&exe, "pending");
+        std::fs::write(&marker, "0").unwrap();
+        let probation = |attempt| Probation {
+            exe: exe.clone(),
+            attempt,
+        };
+
+        assert_eq!(record_start(&exe).unwrap(), Some(1));
+        probation(1).interrupted().await;
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "0");
+
+        assert_eq!(record_start(&exe).unwrap(), Some(1));
+        assert_eq!(record_start(&exe).unwrap(), Some(2));
+        probation(2).passed().await;
+        assert!(!marker.exists());
+        assert_eq!(record_start(&exe).unwrap(), None);
+    }
+}
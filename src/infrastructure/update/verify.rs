@@ -0,0 +1,98 @@
+//! Manifest signature and checksum checks for downloaded binaries.
+//!
+//! The signed manifest names the version as well as the checksum, so an old
+//! release cannot be passed off as the latest one.
+
+use std::cmp::Ordering;
+
+use ring::digest::{SHA256, digest};
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+use crate::domain::UpdateError;
+
+/// What a release claims about its binary.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: String,
+    /// Lowercase hex SHA-256 of the binary.
+    pub sha256: String,
+}
+
+/// Checks the raw 64-byte Ed25519 `signature` over `text`, then parses it.
+///
+/// The manifest is one `<field> <value>` pair per line:
+///
+/// ```text
+/// version 1.4.0
+/// sha256 <64 hex chars>
+/// ```
+pub fn manifest(text: &[u8], signature: &[u8], public_key: &[u8]) -> Result<Manifest, UpdateError> {
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(text, signature)
+        .map_err(|_| UpdateError::Signature)?;
+
+    let text =
+        std::str::from_utf8(text).map_err(|_| UpdateError::Manifest("not UTF-8".to_string()))?;
+    let (mut version, mut sha256) = (None, None);
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.split_once(char::is_whitespace) {
+            Some(("version", value)) => version = Some(value.trim().to_string()),
+            Some(("sha256", value)) => sha256 = Some(value.trim().to_ascii_lowercase()),
+            _ => return Err(UpdateError::Manifest(format!("unexpected line '{line}'"))),
+        }
+    }
+
+    let missing = |field: &str| UpdateError::Manifest(format!("no {field}"));
+    Ok(Manifest {
+        version: version.ok_or_else(|| missing("version"))?,
+        sha256: sha256.ok_or_else(|| missing("sha256"))?,
+    })
+}
+
+/// Checks `data` against the manifest's checksum.
+pub fn checksum(data: &[u8], manifest: &Manifest) -> Result<(), UpdateError> {
+    let actual = hex::encode(digest(&SHA256, data));
+    if manifest.sha256 == actual {
+        Ok(())
+    } else {
+        Err(UpdateError::Checksum {
+            expected: manifest.sha256.clone(),
+            actual,
+        })
+    }
+}
+
+/// Orders `x.y.z` versions; a pre-release (`-rc1`) comes before its release.
+pub fn compare_versions(a: &str, b: &str) -> Result<Ordering, UpdateError> {
+    Ok(version_key(a)?.cmp(&version_key(b)?))
+}
+
+fn version_key(version: &str) -> Result<(Vec<u64>, bool), UpdateError> {
+    let invalid = || UpdateError::Manifest(format!("invalid version '{version}'"));
+    let without_build = version.split('+').next().unwrap_or_default();
+    let (release, pre) = match without_build.split_once('-') {
+        Some((release, _)) => (release, true),
+        None => (without_build, false),
+    };
+    let numbers = release
+        .split('.')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<Vec<u64>, _>>()?;
+    Ok((numbers, !pre))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_versions() {
+        let cmp = |a, b| compare_versions(a, b).unwrap();
+
+        assert_eq!(cmp("1.10.0", "1.9.3"), Ordering::Greater);
+        assert_eq!(cmp("0.1.0", "0.1.0"), Ordering::Equal);
+        assert_eq!(cmp("1.0.0-rc1", "1.0.0"), Ordering::Less);
+        assert_eq!(cmp("1.0.0+build.7", "1.0.0"), Ordering::Equal);
+        assert!(compare_versions("latest", "1.0.0").is_err());
+    }
+}
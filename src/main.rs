@@ -1,31 +1,117 @@
 //! Telegram bot for OpenWRT router management.
 
 mod bot;
+mod cli;
 mod core;
 mod domain;
+mod exit;
 mod infrastructure;
+#[cfg(test)]
+mod testing;
 
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
 
-use bot::BotManager;
-use core::App;
-use infrastructure::{Config, OpenWrtRouter, UnixSignalHandler};
+use bot::{BotManager, RestartPolicy};
+use cli::{Cli, Command, UsageError};
+use core::{App, Loader, Services};
+use infrastructure::config::{ConfigErrors, DEFAULT_CONFIG_PATH};
+use infrastructure::router::{CachedRouter, Router};
+use infrastructure::{Config, LocalLogs, SelfUpdater, UnixSignalHandler};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    load_config()?;
+async fn main() -> ExitCode {
+    match try_main().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+async fn try_main() -> anyhow::Result<()> {
+    let cli = Cli::parse(std::env::args().skip(1))?;
+
+    match cli.command {
+        Command::Run => run(cli.config).await,
+        Command::CheckConfig => check_config(cli.config),
+        Command::InstallService => install_service(cli.config),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("tb-router {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+    }
+}
 
-    let config = Config::new();
-    let router = Arc::new(OpenWrtRouter::new());
+async fn run(config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    let setup = Setup { config_path };
 
-    let mut manager = BotManager::new();
-    manager.add(bot::factory::create_telegram_bot(&config, router)?);
+    let app = App::new(setup, UnixSignalHandler::new())?;
+    let probation = infrastructure::update::check_pending()?;
+    app.with_probation(probation).run().await
+}
 
-    let app = App::new(&config, UnixSignalHandler::new(), manager)?;
-    app.run().await
+fn check_config(config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    let config = Config::load(config_path.as_deref())?;
+    for warning in &config.warnings {
+        println!("warning: {warning}");
+    }
+    println!("Configuration OK");
+    Ok(())
 }
 
-fn load_config() -> anyhow::Result<()> {
-    dotenvy::from_path("./config")?;
+fn install_service(config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    let config = config_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+    let config = std::path::absolute(&config).unwrap_or(config);
+    let script = infrastructure::service::install(&config)?;
+
+    println!("Installed {}", script.display());
+    println!("Enable and start it with:");
+    println!("  {0} enable && {0} start", script.display());
     Ok(())
 }
+
+/// Lets procd and scripts tell bad setups from runtime failures.
+fn exit_code(error: &anyhow::Error) -> u8 {
+    if error.downcast_ref::<UsageError>().is_some() {
+        exit::USAGE
+    } else if error.downcast_ref::<ConfigErrors>().is_some() {
+        exit::CONFIG
+    } else {
+        exit::FAILURE
+    }
+}
+
+struct Setup {
+    config_path: Option<PathBuf>,
+}
+
+impl Loader for Setup {
+    fn load_config(&self) -> anyhow::Result<Config> {
+        Config::load(self.config_path.as_deref())
+    }
+
+    fn build_bots(&self, config: &Config, services: &Services) -> anyhow::Result<BotManager> {
+        let router = Router::from_config(&config.router)?;
+        let router = Arc::new(CachedRouter::new(router, &config.cache));
+        let logs = Arc::new(LocalLogs::new(infrastructure::logging::log_file(config)?));
+        let updater = Arc::new(SelfUpdater::new(config.update.clone())?);
+
+        let mut manager = BotManager::new(RestartPolicy::from_config(&config.restart));
+        manager.add(bot::factory::create_telegram_bot(
+            config,
+            router,
+            logs,
+            Arc::new(services.log_level.clone()),
+            updater,
+            services.heartbeat.clone(),
+        )?);
+        Ok(manager)
+    }
+}
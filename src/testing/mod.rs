@@ -0,0 +1,7 @@
+//! Helpers shared by tests across modules.
+
+mod router;
+mod temp_dir;
+
+pub use router::FakeRouter;
+pub use temp_dir::TempDir;
@@ -0,0 +1,179 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use crate::domain::RouterError;
+use crate::domain::router::{
+    NetworkInfoProvider, SystemInfoProvider, WifiControl, WifiInfoProvider,
+};
+use crate::domain::ubus::{
+    AssocList, BoardInfo, DhcpLeases, HostapdClients, ScanList, SurveyList, SystemInfo,
+    WirelessStatus,
+};
+use crate::domain::wifi_settings::InterfaceOptions;
+
+type Clients = dyn Fn(&FakeRouter, &str) -> Result<HostapdClients, RouterError> + Send + Sync;
+
+/// A router that answers `wifi_clients` as a test tells it to, after
+/// `delay`, and counts the calls and how many overlap.
+///
+/// Wireless status comes from a `network.wireless status` document that
+/// UCI writes update in place; everything else is not found.
+pub struct FakeRouter {
+    wireless: Mutex<Value>,
+    writes: Mutex<Vec<String>>,
+    clients: Box<Clients>,
+    delay: Duration,
+    calls: AtomicUsize,
+    running: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Default for FakeRouter {
+    fn default() -> Self {
+        Self {
+            wireless: Mutex::new(json!({})),
+            writes: Mutex::new(Vec::new()),
+            clients: Box::new(|_, _| Ok(HostapdClients::default())),
+            delay: Duration::ZERO,
+            calls: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl FakeRouter {
+    pub fn with_wireless(self, status: Value) -> Self {
+        *self.wireless.lock().unwrap() = status;
+        self
+    }
+
+    pub fn with_clients(
+        mut self,
+        clients: impl Fn(&FakeRouter, &str) -> Result<HostapdClients, RouterError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Self {
+        self.clients = Box::new(clients);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// `wifi_clients` calls so far.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// UCI writes so far, as `section.option=value`.
+    pub fn writes(&self) -> Vec<String> {
+        self.writes.lock().unwrap().clone()
+    }
+
+    /// The current value of `option` in the `wifi-iface` `section`.
+    pub fn option(&self, section: &str, option: &str) -> Option<String> {
+        let mut wireless = self.wireless.lock().unwrap();
+        let value = interface(&mut wireless, section)?.get(option)?;
+        value.as_str().map(str::to_string)
+    }
+
+    /// Most `wifi_clients` calls in flight at once.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// The config of the interface in `section`.
+fn interface<'a>(wireless: &'a mut Value, section: &str) -> Option<&'a mut Value> {
+    let radios = wireless.as_object_mut()?.values_mut();
+    radios
+        .filter_map(|radio| radio.get_mut("interfaces")?.as_array_mut())
+        .flatten()
+        .find(|iface| iface["section"] == section)?
+        .get_mut("config")
+}
+
+fn not_found(object: &str, method: &str) -> RouterError {
+    RouterError::NotFound {
+        object: object.to_string(),
+        method: method.to_string(),
+    }
+}
+
+#[async_trait]
+impl SystemInfoProvider for FakeRouter {
+    async fn system_info(&self) -> Result<SystemInfo, RouterError> {
+        Err(not_found("system", "info"))
+    }
+
+    async fn board_info(&self) -> Result<BoardInfo, RouterError> {
+        Err(not_found("system", "board"))
+    }
+}
+
+#[async_trait]
+impl WifiInfoProvider for FakeRouter {
+    async fn wireless_status(&self) -> Result<WirelessStatus, RouterError> {
+        let wireless = self.wireless.lock().unwrap().clone();
+        Ok(serde_json::from_value(wireless).unwrap())
+    }
+
+    async fn wifi_clients(&self, iface: &str) -> Result<HostapdClients, RouterError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        (self.clients)(self, iface)
+    }
+
+    async fn wifi_stations(&self, _iface: &str) -> Result<AssocList, RouterError> {
+        Err(not_found("iwinfo", "assoclist"))
+    }
+
+    async fn wifi_scan(&self, _iface: &str) -> Result<ScanList, RouterError> {
+        Err(not_found("iwinfo", "scan"))
+    }
+
+    async fn wifi_survey(&self, _iface: &str) -> Result<SurveyList, RouterError> {
+        Err(not_found("iwinfo", "survey"))
+    }
+}
+
+#[async_trait]
+impl NetworkInfoProvider for FakeRouter {
+    async fn dhcp_leases(&self) -> Result<DhcpLeases, RouterError> {
+        Err(not_found("luci-rpc", "getDHCPLeases"))
+    }
+}
+
+#[async_trait]
+impl WifiControl for FakeRouter {
+    async fn set_channel(&self, radio: &str, channel: u32) -> Result<(), RouterError> {
+        let mut writes = self.writes.lock().unwrap();
+        writes.push(format!("{radio}.channel={channel}"));
+        Ok(())
+    }
+
+    async fn configure_interfaces(&self, changes: &[InterfaceOptions]) -> Result<(), RouterError> {
+        let mut wireless = self.wireless.lock().unwrap();
+        let mut writes = self.writes.lock().unwrap();
+        for change in changes {
+            for (option, value) in &change.values {
+                if let Some(config) = interface(&mut wireless, &change.section) {
+                    config[*option] = json!(value);
+                }
+                writes.push(format!("{}.{option}={value}", change.section));
+            }
+        }
+        Ok(())
+    }
+}